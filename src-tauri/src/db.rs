@@ -1,2 +1,5 @@
+pub mod charts;
+pub mod local_charts;
 pub mod schemas;
+pub mod test_runs;
 pub mod connection;
\ No newline at end of file
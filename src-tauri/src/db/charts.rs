@@ -0,0 +1,155 @@
+use super::connection::DbConnection;
+use rusqlite::{params, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::State;
+
+/// A chart in the offline catalog, as listed in its repository's index.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogChart {
+    pub repo_name: String,
+    pub name: String,
+    pub latest_version: String,
+    pub app_version: Option<String>,
+    pub description: Option<String>,
+    /// Every published version, newest first.
+    pub versions: Vec<String>,
+    pub keywords: Vec<String>,
+    /// Whether the latest version is marked deprecated.
+    pub deprecated: bool,
+}
+
+/// Replace the catalog entries of a repository with the charts read from its index.
+pub fn replace_repo_charts(
+    db: &State<DbConnection>,
+    repo_name: &str,
+    index_modified: i64,
+    charts: &[CatalogChart],
+) -> Result<(), String> {
+    let mut conn = db
+        .lock()
+        .map_err(|e| format!("Failed to acquire database lock: {}", e))?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    tx.execute(
+        "DELETE FROM charts WHERE repo_name = ?1",
+        params![repo_name],
+    )
+    .map_err(|e| format!("Failed to clear charts: {}", e))?;
+    {
+        let mut stmt = tx
+            .prepare(
+                "INSERT INTO charts
+                 (repo_name, name, latest_version, app_version, description, versions, keywords, deprecated)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )
+            .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        for chart in charts {
+            let versions = serde_json::to_string(&chart.versions)
+                .map_err(|e| format!("Failed to serialize versions: {}", e))?;
+            let keywords = serde_json::to_string(&chart.keywords)
+                .map_err(|e| format!("Failed to serialize keywords: {}", e))?;
+            stmt.execute(params![
+                repo_name,
+                chart.name,
+                chart.latest_version,
+                chart.app_version,
+                chart.description,
+                versions,
+                keywords,
+                chart.deprecated,
+            ])
+            .map_err(|e| format!("Failed to store chart: {}", e))?;
+        }
+    }
+    tx.execute(
+        "INSERT OR REPLACE INTO chart_indexes (repo_name, index_modified) VALUES (?1, ?2)",
+        params![repo_name, index_modified],
+    )
+    .map_err(|e| format!("Failed to store index state: {}", e))?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit charts: {}", e))
+}
+
+/// Remove a repository that no longer has a cached index from the catalog.
+pub fn remove_repo_charts(db: &State<DbConnection>, repo_name: &str) -> Result<(), String> {
+    let conn = db
+        .lock()
+        .map_err(|e| format!("Failed to acquire database lock: {}", e))?;
+
+    conn.execute(
+        "DELETE FROM charts WHERE repo_name = ?1",
+        params![repo_name],
+    )
+    .map_err(|e| format!("Failed to delete charts: {}", e))?;
+    conn.execute(
+        "DELETE FROM chart_indexes WHERE repo_name = ?1",
+        params![repo_name],
+    )
+    .map_err(|e| format!("Failed to delete index state: {}", e))?;
+
+    Ok(())
+}
+
+/// Index modification time per repository in the catalog.
+pub fn list_indexed_repos(db: &State<DbConnection>) -> Result<HashMap<String, i64>, String> {
+    let conn = db
+        .lock()
+        .map_err(|e| format!("Failed to acquire database lock: {}", e))?;
+
+    let mut stmt = conn
+        .prepare("SELECT repo_name, index_modified FROM chart_indexes")
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let repos = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Failed to execute query: {}", e))?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|e| format!("Failed to collect results: {}", e))?;
+
+    Ok(repos)
+}
+
+/// Every chart in the catalog, ordered by repository and name.
+pub fn list_catalog_charts(db: &State<DbConnection>) -> Result<Vec<CatalogChart>, String> {
+    let conn = db
+        .lock()
+        .map_err(|e| format!("Failed to acquire database lock: {}", e))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT repo_name, name, latest_version, app_version, description, versions, keywords, deprecated
+             FROM charts
+             ORDER BY repo_name, name",
+        )
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let json_column = |index: usize, name: &str, text: String| {
+        serde_json::from_str::<Vec<String>>(&text).map_err(|_| {
+            rusqlite::Error::InvalidColumnType(index, name.to_string(), rusqlite::types::Type::Text)
+        })
+    };
+
+    let charts = stmt
+        .query_map([], |row| {
+            Ok(CatalogChart {
+                repo_name: row.get(0)?,
+                name: row.get(1)?,
+                latest_version: row.get(2)?,
+                app_version: row.get(3)?,
+                description: row.get(4)?,
+                versions: json_column(5, "versions", row.get(5)?)?,
+                keywords: json_column(6, "keywords", row.get(6)?)?,
+                deprecated: row.get(7)?,
+            })
+        })
+        .map_err(|e| format!("Failed to execute query: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect results: {}", e))?;
+
+    Ok(charts)
+}
@@ -15,7 +15,13 @@ pub fn init_database() -> Result<Connection> {
     
     let db_path = app_data_dir.join("rudder.db");
     let conn = Connection::open(db_path)?;
-    
+    create_tables(&conn)?;
+
+    Ok(conn)
+}
+
+/// Create the tables and indexes used by the app if they don't exist yet
+pub fn create_tables(conn: &Connection) -> Result<()> {
     // Create chart_schemas table
     conn.execute(
         "CREATE TABLE IF NOT EXISTS chart_schemas (
@@ -26,10 +32,15 @@ pub fn init_database() -> Result<Connection> {
             namespace TEXT,
             schema_content TEXT NOT NULL,
             created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            inferred INTEGER NOT NULL DEFAULT 0,
             UNIQUE(chart_name, chart_version, repo_name)
         )",
         [],
     )?;
+
+    // Databases created before schemas could be inferred lack the column
+    add_column_if_missing(conn, "chart_schemas", "inferred", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "chart_schemas", "default_values", "TEXT")?;
     
     // Create index for faster lookups
     conn.execute(
@@ -38,7 +49,88 @@ pub fn init_database() -> Result<Connection> {
         [],
     )?;
 
-    Ok(conn)
+    // Offline chart catalog built from helm's cached repository indexes
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS charts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            repo_name TEXT NOT NULL,
+            name TEXT NOT NULL,
+            latest_version TEXT NOT NULL,
+            app_version TEXT,
+            description TEXT,
+            versions TEXT NOT NULL,
+            keywords TEXT NOT NULL,
+            deprecated INTEGER NOT NULL DEFAULT 0,
+            UNIQUE(repo_name, name)
+        )",
+        [],
+    )?;
+
+    // Modification time of the index file each repository's charts were read from
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS chart_indexes (
+            repo_name TEXT PRIMARY KEY,
+            index_modified INTEGER NOT NULL,
+            indexed_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    // Chart directories and archives on disk, with the hash of their last read contents
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS local_charts (
+            path TEXT PRIMARY KEY,
+            chart_name TEXT NOT NULL,
+            chart_version TEXT NOT NULL,
+            content_hash TEXT NOT NULL,
+            registered_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    // History of `helm test` runs, with the per-pod results as JSON
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS release_test_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            release_name TEXT NOT NULL,
+            namespace TEXT NOT NULL,
+            revision INTEGER,
+            success INTEGER NOT NULL,
+            started_at TEXT NOT NULL,
+            duration_secs REAL NOT NULL,
+            results TEXT NOT NULL,
+            error TEXT
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_release_test_runs_release
+         ON release_test_runs (release_name, namespace, started_at)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// Add a column to an existing table unless it already has it
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>>>()?;
+    if !columns.iter().any(|c| c == column) {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+    }
+    Ok(())
 }
 
 pub fn get_db_connection(state: &State<DbConnection>) -> Result<Arc<Mutex<Connection>>, String> {
@@ -0,0 +1,107 @@
+use super::connection::DbConnection;
+use rusqlite::{params, OptionalExtension, Result};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+/// A chart directory or `.tgz` archive registered as a chart source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalChartEntry {
+    /// Absolute path of the chart directory or archive.
+    pub path: String,
+    pub chart_name: String,
+    pub chart_version: String,
+    /// Hash of the chart contents when it was last read.
+    pub content_hash: String,
+    pub registered_at: Option<String>,
+}
+
+/// Register a local chart or update the contents recorded for it.
+pub fn upsert_local_chart(db: &State<DbConnection>, entry: &LocalChartEntry) -> Result<(), String> {
+    let conn = db
+        .lock()
+        .map_err(|e| format!("Failed to acquire database lock: {}", e))?;
+
+    conn.execute(
+        "INSERT INTO local_charts (path, chart_name, chart_version, content_hash)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(path) DO UPDATE SET
+             chart_name = excluded.chart_name,
+             chart_version = excluded.chart_version,
+             content_hash = excluded.content_hash",
+        params![
+            entry.path,
+            entry.chart_name,
+            entry.chart_version,
+            entry.content_hash
+        ],
+    )
+    .map_err(|e| format!("Failed to store local chart: {}", e))?;
+
+    Ok(())
+}
+
+pub fn get_local_chart(
+    db: &State<DbConnection>,
+    path: &str,
+) -> Result<Option<LocalChartEntry>, String> {
+    let conn = db
+        .lock()
+        .map_err(|e| format!("Failed to acquire database lock: {}", e))?;
+
+    conn.query_row(
+        "SELECT path, chart_name, chart_version, content_hash, registered_at
+         FROM local_charts
+         WHERE path = ?1",
+        params![path],
+        local_chart_from_row,
+    )
+    .optional()
+    .map_err(|e| format!("Failed to execute query: {}", e))
+}
+
+/// Every registered local chart, ordered by path.
+pub fn list_local_charts(db: &State<DbConnection>) -> Result<Vec<LocalChartEntry>, String> {
+    let conn = db
+        .lock()
+        .map_err(|e| format!("Failed to acquire database lock: {}", e))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT path, chart_name, chart_version, content_hash, registered_at
+             FROM local_charts
+             ORDER BY path",
+        )
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let charts = stmt
+        .query_map([], local_chart_from_row)
+        .map_err(|e| format!("Failed to execute query: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect results: {}", e))?;
+
+    Ok(charts)
+}
+
+/// Unregister a local chart. Returns whether it was registered.
+pub fn remove_local_chart(db: &State<DbConnection>, path: &str) -> Result<bool, String> {
+    let conn = db
+        .lock()
+        .map_err(|e| format!("Failed to acquire database lock: {}", e))?;
+
+    let removed = conn
+        .execute("DELETE FROM local_charts WHERE path = ?1", params![path])
+        .map_err(|e| format!("Failed to delete local chart: {}", e))?;
+
+    Ok(removed > 0)
+}
+
+fn local_chart_from_row(row: &rusqlite::Row) -> Result<LocalChartEntry> {
+    Ok(LocalChartEntry {
+        path: row.get(0)?,
+        chart_name: row.get(1)?,
+        chart_version: row.get(2)?,
+        content_hash: row.get(3)?,
+        registered_at: row.get(4)?,
+    })
+}
@@ -10,9 +10,16 @@ pub struct ChartSchema {
     pub repo_name: String,
     pub namespace: Option<String>,
     pub schema_content: serde_json::Value,
+    /// Whether the schema was inferred from values rather than read from the
+    /// chart's `values.schema.json`.
+    pub inferred: bool,
+    /// The chart's default values, which helm merges the user's values into
+    /// before validating them. Unknown for schemas not read from a chart.
+    pub default_values: Option<serde_json::Value>,
     pub created_at: Option<String>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn store_chart_schema(
     db: &State<DbConnection>,
     chart_name: &str,
@@ -20,18 +27,24 @@ pub fn store_chart_schema(
     repo_name: &str,
     namespace: Option<&str>,
     schema_content: &serde_json::Value,
+    inferred: bool,
+    default_values: Option<&serde_json::Value>,
 ) -> Result<(), String> {
     let conn = db
         .lock()
         .map_err(|e| format!("Failed to acquire database lock: {}", e))?;
     let schema_json = serde_json::to_string(schema_content)
         .map_err(|e| format!("Failed to serialize schema: {}", e))?;
+    let values_json = default_values
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| format!("Failed to serialize default values: {}", e))?;
 
     conn.execute(
         "INSERT OR REPLACE INTO chart_schemas 
-         (chart_name, chart_version, repo_name, namespace, schema_content) 
-         VALUES (?1, ?2, ?3, ?4, ?5)",
-        params![chart_name, chart_version, repo_name, namespace, schema_json],
+         (chart_name, chart_version, repo_name, namespace, schema_content, inferred, default_values) 
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![chart_name, chart_version, repo_name, namespace, schema_json, inferred, values_json],
     )
     .map_err(|e| format!("Failed to store schema: {}", e))?;
 
@@ -50,7 +63,7 @@ pub fn get_chart_schema(
 
     let mut stmt = conn
         .prepare(
-            "SELECT chart_name, chart_version, repo_name, namespace, schema_content, created_at 
+            "SELECT chart_name, chart_version, repo_name, namespace, schema_content, created_at, inferred, default_values 
          FROM chart_schemas 
          WHERE chart_name = ?1 AND chart_version = ?2 AND repo_name = ?3",
         )
@@ -67,6 +80,17 @@ pub fn get_chart_schema(
                         rusqlite::types::Type::Text,
                     )
                 })?;
+            let values_json: Option<String> = row.get(7)?;
+            let default_values = values_json
+                .map(|json| serde_json::from_str(&json))
+                .transpose()
+                .map_err(|_| {
+                    rusqlite::Error::InvalidColumnType(
+                        7,
+                        "default_values".to_string(),
+                        rusqlite::types::Type::Text,
+                    )
+                })?;
 
             Ok(ChartSchema {
                 chart_name: row.get(0)?,
@@ -75,6 +99,8 @@ pub fn get_chart_schema(
                 namespace: row.get(3)?,
                 schema_content,
                 created_at: row.get(5)?,
+                inferred: row.get(6)?,
+                default_values,
             })
         })
         .map_err(|e| format!("Failed to execute query: {}", e))?
@@ -91,7 +117,7 @@ pub fn list_cached_schemas(db: &State<DbConnection>) -> Result<Vec<ChartSchema>,
 
     let mut stmt = conn
         .prepare(
-            "SELECT chart_name, chart_version, repo_name, namespace, schema_content, created_at 
+            "SELECT chart_name, chart_version, repo_name, namespace, schema_content, created_at, inferred, default_values 
          FROM chart_schemas 
          ORDER BY created_at DESC",
         )
@@ -108,6 +134,17 @@ pub fn list_cached_schemas(db: &State<DbConnection>) -> Result<Vec<ChartSchema>,
                         rusqlite::types::Type::Text,
                     )
                 })?;
+            let values_json: Option<String> = row.get(7)?;
+            let default_values = values_json
+                .map(|json| serde_json::from_str(&json))
+                .transpose()
+                .map_err(|_| {
+                    rusqlite::Error::InvalidColumnType(
+                        7,
+                        "default_values".to_string(),
+                        rusqlite::types::Type::Text,
+                    )
+                })?;
 
             Ok(ChartSchema {
                 chart_name: row.get(0)?,
@@ -116,6 +153,8 @@ pub fn list_cached_schemas(db: &State<DbConnection>) -> Result<Vec<ChartSchema>,
                 namespace: row.get(3)?,
                 schema_content,
                 created_at: row.get(5)?,
+                inferred: row.get(6)?,
+                default_values,
             })
         })
         .map_err(|e| format!("Failed to execute query: {}", e))?
@@ -0,0 +1,94 @@
+use super::connection::DbConnection;
+use crate::helm::release_test::{ReleaseTestRun, TestPodResult};
+use chrono::{DateTime, SecondsFormat};
+use rusqlite::{params, Result};
+use tauri::State;
+
+/// Store a finished test run and return its ID.
+pub fn insert_test_run(db: &State<DbConnection>, run: &ReleaseTestRun) -> Result<i64, String> {
+    let conn = db
+        .lock()
+        .map_err(|e| format!("Failed to acquire database lock: {}", e))?;
+    let results = serde_json::to_string(&run.results)
+        .map_err(|e| format!("Failed to serialize test results: {}", e))?;
+
+    conn.execute(
+        "INSERT INTO release_test_runs
+         (release_name, namespace, revision, success, started_at, duration_secs, results, error)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            run.release_name,
+            run.namespace,
+            run.revision,
+            run.success,
+            // Fixed-width UTC timestamps so that they sort as text
+            run.started_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+            run.duration_secs,
+            results,
+            run.error,
+        ],
+    )
+    .map_err(|e| format!("Failed to store test run: {}", e))?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Test runs of a release, newest first.
+pub fn list_test_runs(
+    db: &State<DbConnection>,
+    release_name: &str,
+    namespace: &str,
+    limit: usize,
+) -> Result<Vec<ReleaseTestRun>, String> {
+    let conn = db
+        .lock()
+        .map_err(|e| format!("Failed to acquire database lock: {}", e))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, release_name, namespace, revision, success, started_at, duration_secs, results, error
+             FROM release_test_runs
+             WHERE release_name = ?1 AND namespace = ?2
+             ORDER BY started_at DESC, id DESC
+             LIMIT ?3",
+        )
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let runs = stmt
+        .query_map(params![release_name, namespace, limit as i64], |row| {
+            let started_at: String = row.get(5)?;
+            let started_at = DateTime::parse_from_rfc3339(&started_at).map_err(|_| {
+                rusqlite::Error::InvalidColumnType(
+                    5,
+                    "started_at".to_string(),
+                    rusqlite::types::Type::Text,
+                )
+            })?;
+            let results_json: String = row.get(7)?;
+            let results: Vec<TestPodResult> =
+                serde_json::from_str(&results_json).map_err(|_| {
+                    rusqlite::Error::InvalidColumnType(
+                        7,
+                        "results".to_string(),
+                        rusqlite::types::Type::Text,
+                    )
+                })?;
+
+            Ok(ReleaseTestRun {
+                id: row.get(0)?,
+                release_name: row.get(1)?,
+                namespace: row.get(2)?,
+                revision: row.get(3)?,
+                success: row.get(4)?,
+                started_at: started_at.into(),
+                duration_secs: row.get(6)?,
+                results,
+                error: row.get(8)?,
+            })
+        })
+        .map_err(|e| format!("Failed to execute query: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect results: {}", e))?;
+
+    Ok(runs)
+}
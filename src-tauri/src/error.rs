@@ -0,0 +1,329 @@
+//! Error module
+//!
+//! Typed error returned by every Tauri command, so the UI can tell e.g. a
+//! missing release from an unreachable cluster instead of matching strings.
+
+use crate::runner::command::CommandOutput;
+use crate::schema::validate::ValidationError;
+use serde::Serialize;
+use std::fmt;
+
+/// Broad category of a failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorKind {
+    /// The requested Helm release does not exist in the namespace.
+    ReleaseNotFound,
+    /// The chart or chart version could not be found in any repository.
+    ChartNotFound,
+    /// The Kubernetes API server could not be reached.
+    ClusterUnreachable,
+    /// The current credentials are not allowed to perform the action.
+    Forbidden,
+    /// The cluster rejected the current credentials.
+    Unauthorized,
+    /// The helm or kubectl binary is not installed or not on PATH.
+    BinaryMissing,
+    /// The supplied chart values are malformed or rejected by the chart.
+    InvalidValues,
+    /// No schema is cached for the chart the values are checked against.
+    SchemaNotFound,
+    /// A command argument was rejected before anything ran.
+    InvalidInput,
+    /// Another helm operation on the release is still in progress.
+    Conflict,
+    /// A chart repository or registry could not be reached.
+    Network,
+    /// The operation did not finish in time.
+    Timeout,
+    /// The operation was cancelled by the user.
+    Cancelled,
+    /// Reading or writing the local schema cache failed.
+    Database,
+    /// Reading or writing a local file failed.
+    Io,
+    /// helm or kubectl failed for a reason that could not be classified.
+    CommandFailed,
+}
+
+impl ErrorKind {
+    /// Classify a helm/kubectl error message by its well-known phrases.
+    pub fn classify(message: &str) -> Self {
+        let lower = message.to_lowercase();
+        let has = |patterns: &[&str]| patterns.iter().any(|p| lower.contains(p));
+
+        if has(&["operation timeout", "timed out waiting for the condition", "context deadline exceeded"]) {
+            ErrorKind::Timeout
+        } else if has(&["was cancelled"]) {
+            ErrorKind::Cancelled
+        } else if has(&["another operation (install/upgrade/rollback) is in progress"]) {
+            ErrorKind::Conflict
+        } else if has(&["release: not found", "release not found", "has no deployed releases"]) {
+            ErrorKind::ReleaseNotFound
+        } else if has(&["forbidden"]) {
+            ErrorKind::Forbidden
+        } else if has(&["unauthorized", "you must be logged in to the server"]) {
+            ErrorKind::Unauthorized
+        } else if has(&[
+            "kubernetes cluster unreachable",
+            "unable to connect to the server",
+            "the connection to the server",
+            "was refused - did you specify the right host or port",
+        ]) {
+            ErrorKind::ClusterUnreachable
+        } else if has(&[
+            "values don't meet the specifications of the schema",
+            "error converting yaml to json",
+            "invalid json values",
+            "yaml parse error",
+        ]) {
+            ErrorKind::InvalidValues
+        } else if has(&["no chart version found", "no chart name found", "chart not found", "not found in repository"]) {
+            ErrorKind::ChartNotFound
+        } else if has(&["timeout", "network", "connection", "no such host", "tls handshake"]) {
+            ErrorKind::Network
+        } else {
+            ErrorKind::CommandFailed
+        }
+    }
+
+    /// Whether retrying against another repository is pointless because the
+    /// network itself is the problem.
+    pub fn is_network(self) -> bool {
+        matches!(
+            self,
+            ErrorKind::Network | ErrorKind::ClusterUnreachable | ErrorKind::Timeout
+        )
+    }
+
+    fn remediation(self) -> Option<&'static str> {
+        let hint = match self {
+            ErrorKind::ReleaseNotFound => "Check the release name and namespace with `helm ls -A`.",
+            ErrorKind::ChartNotFound => "Run `helm repo update` and check the chart name and version.",
+            ErrorKind::ClusterUnreachable => {
+                "Check that the current kube context points to a running cluster with `kubectl cluster-info`."
+            }
+            ErrorKind::Forbidden => "Ask a cluster admin for RBAC permissions for this action in the namespace.",
+            ErrorKind::Unauthorized => "Your credentials were rejected; refresh them or switch to another kube context.",
+            ErrorKind::BinaryMissing => "Install helm and kubectl (https://helm.sh/docs/intro/install/) and make sure they are on PATH.",
+            ErrorKind::InvalidValues => "Fix the highlighted values and try again.",
+            ErrorKind::SchemaNotFound => "Open the release editor to cache the chart's schema, or skip validation.",
+            ErrorKind::Conflict => "Wait for the running operation to finish, or roll back the release if it is stuck.",
+            ErrorKind::Network => "Check your network connection and the repository URL.",
+            ErrorKind::Timeout => "Retry, or raise the timeout for this command.",
+            _ => return None,
+        };
+        Some(hint)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RudderError {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// Raw stderr of the failed helm/kubectl process, if any.
+    pub stderr: Option<String>,
+    pub remediation: Option<String>,
+    /// Values that do not match the chart's schema.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub validation_errors: Vec<ValidationError>,
+}
+
+impl RudderError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            stderr: None,
+            remediation: kind.remediation().map(String::from),
+            validation_errors: Vec::new(),
+        }
+    }
+
+    pub fn with_remediation(mut self, remediation: impl Into<String>) -> Self {
+        self.remediation = Some(remediation.into());
+        self
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::InvalidInput, message)
+    }
+
+    pub fn invalid_values(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::InvalidValues, message)
+    }
+
+    /// Error for values rejected by the chart's schema, listing every violation.
+    pub fn schema_violations(errors: Vec<ValidationError>) -> Self {
+        let first = errors.first().map_or(String::new(), |e| {
+            let path = if e.path.is_empty() { "values" } else { &e.path };
+            format!(": {}: {}", path, e.message)
+        });
+        let more = match errors.len() {
+            0 | 1 => String::new(),
+            n => format!(" (and {} more)", n - 1),
+        };
+        let mut error = Self::invalid_values(format!(
+            "Values don't match the chart schema{}{}",
+            first, more
+        ));
+        error.validation_errors = errors;
+        error
+    }
+
+    pub fn database(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Database, message)
+    }
+
+    pub fn io(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Io, message)
+    }
+
+    /// Error for a helm/kubectl process that could not be run to completion,
+    /// e.g. because the binary is missing or the operation timed out.
+    pub fn spawn_failed(context: &str, error: String) -> Self {
+        let lower = error.to_lowercase();
+        let kind = if lower.contains("no such file")
+            || lower.contains("program not found")
+            || lower.contains("cannot find the file")
+        {
+            ErrorKind::BinaryMissing
+        } else {
+            ErrorKind::classify(&error)
+        };
+
+        Self::new(kind, format!("{}: {}", context, error))
+    }
+
+    /// Error for a helm/kubectl process that exited unsuccessfully, classified
+    /// from its stderr. `context` is used as the message if stderr is empty.
+    pub fn command_failed(context: &str, output: &CommandOutput) -> Self {
+        Self::from_stderr(context, &String::from_utf8_lossy(&output.stderr))
+    }
+
+    /// Classify the stderr of a failed helm/kubectl invocation.
+    pub fn from_stderr(context: &str, stderr: &str) -> Self {
+        let stderr = stderr.trim();
+        // Prefer helm's `Error: ...` line over warnings and trailing detail
+        let mut lines = stderr
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("WARNING:"));
+        let message = lines
+            .clone()
+            .find(|line| line.starts_with("Error: ") || line.starts_with("error: "))
+            .or_else(|| lines.next_back())
+            .map(|line| line.trim_start_matches("Error: ").trim_start_matches("error: "))
+            .unwrap_or(context);
+
+        let mut error = Self::new(ErrorKind::classify(stderr), message);
+        if !stderr.is_empty() {
+            error.stderr = Some(stderr.to_string());
+        }
+        error
+    }
+}
+
+impl fmt::Display for RudderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for RudderError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_common_helm_and_kubectl_errors() {
+        let cases = [
+            ("Error: release: not found", ErrorKind::ReleaseNotFound),
+            (
+                "Error: Kubernetes cluster unreachable: Get \"https://127.0.0.1:6443/version\": dial tcp 127.0.0.1:6443: connect: connection refused",
+                ErrorKind::ClusterUnreachable,
+            ),
+            (
+                "The connection to the server localhost:8080 was refused - did you specify the right host or port?",
+                ErrorKind::ClusterUnreachable,
+            ),
+            (
+                "Error from server (Forbidden): pods is forbidden: User \"dev\" cannot list resource \"pods\" in API group \"\" in the namespace \"kube-system\"",
+                ErrorKind::Forbidden,
+            ),
+            ("error: You must be logged in to the server (Unauthorized)", ErrorKind::Unauthorized),
+            (
+                "Error: UPGRADE FAILED: values don't meet the specifications of the schema(s) in the following chart(s):\nnginx:\n- replicaCount: Invalid type. Expected: integer, given: string",
+                ErrorKind::InvalidValues,
+            ),
+            (
+                "Error: UPGRADE FAILED: another operation (install/upgrade/rollback) is in progress",
+                ErrorKind::Conflict,
+            ),
+            ("Error: chart \"nginx\" matching 99.0.0 not found in bitnami index. (try 'helm repo update'): no chart version found for nginx-99.0.0", ErrorKind::ChartNotFound),
+            ("Error: UPGRADE FAILED: timed out waiting for the condition", ErrorKind::Timeout),
+            ("Error: looks like \"https://charts.example.com\" is not a valid chart repository or cannot be reached: dial tcp: lookup charts.example.com: no such host", ErrorKind::Network),
+            ("Error: something unexpected", ErrorKind::CommandFailed),
+        ];
+
+        for (stderr, kind) in cases {
+            assert_eq!(ErrorKind::classify(stderr), kind, "{}", stderr);
+        }
+    }
+
+    #[test]
+    fn from_stderr_keeps_raw_output_and_strips_prefixes() {
+        let error = RudderError::from_stderr(
+            "helm status failed",
+            "WARNING: Kubernetes configuration file is group-readable.\nError: release: not found\n",
+        );
+
+        assert_eq!(error.kind, ErrorKind::ReleaseNotFound);
+        assert_eq!(error.message, "release: not found");
+        assert_eq!(
+            error.stderr.as_deref(),
+            Some("WARNING: Kubernetes configuration file is group-readable.\nError: release: not found")
+        );
+        assert!(error.remediation.is_some());
+    }
+
+    #[test]
+    fn spawn_failures_are_classified() {
+        assert_eq!(
+            RudderError::spawn_failed("Helm command failed", "No such file or directory (os error 2)".into()).kind,
+            ErrorKind::BinaryMissing
+        );
+        assert_eq!(
+            RudderError::spawn_failed(
+                "kubectl command failed",
+                "Operation timeout: `kubectl get pods` did not finish within 60s".into()
+            )
+            .kind,
+            ErrorKind::Timeout
+        );
+        assert_eq!(
+            RudderError::spawn_failed("Helm command failed", "Operation op-1 was cancelled".into()).kind,
+            ErrorKind::Cancelled
+        );
+        assert_eq!(
+            RudderError::spawn_failed("Helm command failed", "Permission denied (os error 13)".into()).kind,
+            ErrorKind::CommandFailed
+        );
+    }
+
+    #[test]
+    fn serializes_for_the_frontend() {
+        let error = RudderError::from_stderr("", "Error: release: not found");
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "kind": "releaseNotFound",
+                "message": "release: not found",
+                "stderr": "Error: release: not found",
+                "remediation": "Check the release name and namespace with `helm ls -A`.",
+            })
+        );
+    }
+}
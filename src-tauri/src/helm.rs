@@ -0,0 +1,18 @@
+pub mod catalog;
+pub mod chart_ref;
+pub mod chart_tree;
+pub mod diff;
+pub mod install;
+pub mod inventory;
+pub mod local_chart;
+pub mod manifest;
+pub mod oci;
+pub mod preview;
+pub mod release;
+pub mod release_test;
+pub mod repo;
+pub mod revision;
+pub mod status;
+pub mod uninstall;
+pub mod upgrade;
+pub mod values_file;
\ No newline at end of file
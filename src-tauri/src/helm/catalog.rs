@@ -0,0 +1,501 @@
+//! Chart catalog module
+//!
+//! Offline chart search over the repository indexes that helm caches on
+//! `helm repo add` and `helm repo update`. Each `<repo>-index.yaml` is parsed
+//! into the `charts` table and read again only when the file changes.
+
+use crate::db::charts::{self, CatalogChart};
+use crate::db::connection::DbConnection;
+use crate::error::RudderError;
+use crate::runner::command::CommandRunner;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::State;
+
+const INDEX_SUFFIX: &str = "-index.yaml";
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+
+#[derive(Deserialize)]
+struct RepoIndex {
+    #[serde(default)]
+    entries: BTreeMap<String, Vec<IndexEntry>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexEntry {
+    #[serde(deserialize_with = "scalar_string")]
+    version: String,
+    #[serde(default, deserialize_with = "optional_scalar_string")]
+    app_version: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    keywords: Option<Vec<String>>,
+    #[serde(default)]
+    deprecated: bool,
+}
+
+/// Versions such as `appVersion: 1.16` are written unquoted in many indexes
+/// and `Chart.yaml` files.
+pub(crate) fn scalar_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    match serde_yaml::Value::deserialize(deserializer)? {
+        serde_yaml::Value::String(s) => Ok(s),
+        serde_yaml::Value::Number(n) => Ok(n.to_string()),
+        serde_yaml::Value::Bool(b) => Ok(b.to_string()),
+        _ => Err(serde::de::Error::custom("expected a version string")),
+    }
+}
+
+pub(crate) fn optional_scalar_string<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    match serde_yaml::Value::deserialize(deserializer)? {
+        serde_yaml::Value::Null => Ok(None),
+        value => scalar_string(value)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+/// Parse a cached repository index into one catalog entry per chart.
+pub fn parse_index(repo_name: &str, yaml: &str) -> Result<Vec<CatalogChart>, String> {
+    let index: RepoIndex = serde_yaml::from_str(yaml)
+        .map_err(|e| format!("Failed to parse index of repository {}: {}", repo_name, e))?;
+
+    Ok(index
+        .entries
+        .into_iter()
+        .filter_map(|(name, mut entries)| {
+            entries.sort_by(|a, b| compare_versions(&b.version, &a.version));
+            let latest = entries.first()?;
+            Some(CatalogChart {
+                repo_name: repo_name.to_string(),
+                name,
+                latest_version: latest.version.clone(),
+                app_version: latest.app_version.clone(),
+                description: latest.description.clone(),
+                keywords: latest.keywords.clone().unwrap_or_default(),
+                deprecated: latest.deprecated,
+                versions: entries.into_iter().map(|e| e.version).collect(),
+            })
+        })
+        .collect())
+}
+
+/// Compare two chart versions by semver precedence, tolerating a leading `v`
+/// and missing minor or patch numbers.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    fn split(version: &str) -> (Vec<u64>, Option<&str>) {
+        let version = version.trim_start_matches('v');
+        let version = version.split('+').next().unwrap_or_default();
+        let (core, pre) = match version.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (version, None),
+        };
+        let mut numbers: Vec<u64> = core.split('.').map(|n| n.parse().unwrap_or(0)).collect();
+        numbers.resize(3.max(numbers.len()), 0);
+        (numbers, pre)
+    }
+
+    let (a_core, a_pre) = split(a);
+    let (b_core, b_pre) = split(b);
+    a_core.cmp(&b_core).then_with(|| match (a_pre, b_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => a
+            .split('.')
+            .zip(b.split('.'))
+            .map(|(x, y)| match (x.parse::<u64>(), y.parse::<u64>()) {
+                (Ok(x), Ok(y)) => x.cmp(&y),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => x.cmp(y),
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| a.split('.').count().cmp(&b.split('.').count())),
+    })
+}
+
+/// Directory in which helm caches repository indexes.
+pub async fn repository_cache_dir(runner: &dyn CommandRunner) -> PathBuf {
+    // `helm env` prints KEY="value" lines and works without a cluster or network
+    if let Ok(output) = runner.run("helm", ["env"]).await {
+        if output.success() {
+            let env = String::from_utf8_lossy(&output.stdout);
+            let dir = env
+                .lines()
+                .find_map(|line| line.strip_prefix("HELM_REPOSITORY_CACHE="))
+                .map(|value| value.trim().trim_matches('"'))
+                .filter(|value| !value.is_empty());
+            if let Some(dir) = dir {
+                return PathBuf::from(dir);
+            }
+        }
+    }
+
+    dirs::cache_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("helm")
+        .join("repository")
+}
+
+/// Repositories re-read or dropped by a catalog sync.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogSync {
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    /// Repositories whose index could not be read; their previous entries are kept.
+    pub failed: Vec<String>,
+}
+
+/// Cached index files by repository name, with their modification time in milliseconds.
+fn index_files(cache_dir: &Path) -> HashMap<String, (PathBuf, i64)> {
+    let Ok(entries) = std::fs::read_dir(cache_dir) else {
+        return HashMap::new();
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let repo_name = file_name.strip_suffix(INDEX_SUFFIX)?.to_string();
+            let modified = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()?
+                .duration_since(UNIX_EPOCH)
+                .ok()?
+                .as_millis() as i64;
+            Some((repo_name, (entry.path(), modified)))
+        })
+        .collect()
+}
+
+/// Bring the `charts` table in line with the index files in `cache_dir`,
+/// re-reading only indexes that changed since the last sync.
+pub fn sync_catalog(
+    db: &State<DbConnection>,
+    cache_dir: &Path,
+) -> Result<CatalogSync, RudderError> {
+    let indexed = charts::list_indexed_repos(db).map_err(RudderError::database)?;
+    let files = index_files(cache_dir);
+    let mut sync = CatalogSync::default();
+
+    for (repo_name, (path, modified)) in &files {
+        if indexed.get(repo_name) == Some(modified) {
+            continue;
+        }
+        let parsed = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+            .and_then(|yaml| parse_index(repo_name, &yaml));
+        match parsed {
+            Ok(entries) => {
+                charts::replace_repo_charts(db, repo_name, *modified, &entries)
+                    .map_err(RudderError::database)?;
+                sync.updated.push(repo_name.clone());
+            }
+            Err(e) => {
+                println!("Skipping chart index of {}: {}", repo_name, e);
+                sync.failed.push(repo_name.clone());
+            }
+        }
+    }
+
+    for repo_name in indexed.keys().filter(|repo| !files.contains_key(*repo)) {
+        charts::remove_repo_charts(db, repo_name).map_err(RudderError::database)?;
+        sync.removed.push(repo_name.clone());
+    }
+
+    sync.updated.sort();
+    sync.removed.sort();
+    sync.failed.sort();
+    Ok(sync)
+}
+
+/// Filters for `search_charts`; every field may be omitted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ChartSearchQuery {
+    /// Free text matched fuzzily against chart names, then keywords and descriptions.
+    pub query: String,
+    /// Only search this repository.
+    pub repo: Option<String>,
+    pub include_deprecated: bool,
+    /// Maximum number of results, 50 by default.
+    pub limit: Option<usize>,
+}
+
+/// A catalog chart matching a search, with its relevance.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChartMatch {
+    #[serde(flatten)]
+    pub chart: CatalogChart,
+    pub score: u32,
+}
+
+/// Rank catalog charts against a query, best matches first.
+pub fn search(charts: Vec<CatalogChart>, query: &ChartSearchQuery) -> Vec<ChartMatch> {
+    let mut matches: Vec<ChartMatch> = charts
+        .into_iter()
+        .filter(|chart| query.include_deprecated || !chart.deprecated)
+        .filter(|chart| {
+            query
+                .repo
+                .as_ref()
+                .is_none_or(|repo| *repo == chart.repo_name)
+        })
+        .filter_map(|chart| {
+            let score = fuzzy_score(&query.query, &chart)?;
+            Some(ChartMatch { chart, score })
+        })
+        .collect();
+
+    matches.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.chart.deprecated.cmp(&b.chart.deprecated))
+            .then_with(|| a.chart.name.cmp(&b.chart.name))
+            .then_with(|| a.chart.repo_name.cmp(&b.chart.repo_name))
+    });
+    matches.truncate(query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT));
+    matches
+}
+
+/// Relevance of a chart for a query, `None` if it does not match at all.
+///
+/// Name matches rank above keyword and description matches; within names an
+/// exact match beats a prefix, a substring, an in-order subsequence such as
+/// `kps` for `kube-prometheus-stack`, and finally a near miss with a typo.
+pub fn fuzzy_score(query: &str, chart: &CatalogChart) -> Option<u32> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    // `repo/name` queries are matched against the qualified name
+    let target = if query.contains('/') {
+        format!("{}/{}", chart.repo_name, chart.name).to_lowercase()
+    } else {
+        chart.name.to_lowercase()
+    };
+    let capped = |n: usize, max: u32| (n as u32).min(max);
+
+    if target == query {
+        return Some(1000);
+    }
+    if target.starts_with(&query) {
+        return Some(900 - capped(target.len() - query.len(), 100));
+    }
+    if let Some(position) = target.find(&query) {
+        return Some(700 - capped(position, 100));
+    }
+    if let Some(gaps) = subsequence_gaps(&query, &target) {
+        return Some(500 - capped(gaps * 10, 200));
+    }
+    if query.chars().count() >= 4 {
+        let distance = edit_distance(&query, &target);
+        if distance <= 2 {
+            return Some(280 - 40 * distance as u32);
+        }
+    }
+
+    let keywords: Vec<String> = chart.keywords.iter().map(|k| k.to_lowercase()).collect();
+    if keywords.contains(&query) {
+        return Some(180);
+    }
+    if keywords.iter().any(|k| k.contains(&query)) {
+        return Some(150);
+    }
+    let description = chart
+        .description
+        .as_deref()
+        .unwrap_or_default()
+        .to_lowercase();
+    if description.contains(&query) {
+        return Some(100);
+    }
+    None
+}
+
+/// Number of skipped characters if `query` occurs in order within `target`.
+fn subsequence_gaps(query: &str, target: &str) -> Option<usize> {
+    let mut gaps = 0;
+    let mut started = false;
+    let mut target_chars = target.chars();
+    for q in query.chars() {
+        loop {
+            let t = target_chars.next()?;
+            if t == q {
+                started = true;
+                break;
+            }
+            if started {
+                gaps += 1;
+            }
+        }
+    }
+    Some(gaps)
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tauri::Manager;
+
+    const BITNAMI_INDEX: &str = r#"apiVersion: v1
+entries:
+  nginx:
+    - name: nginx
+      version: 15.3.5
+      appVersion: 1.25.2
+      description: NGINX Open Source is a web server.
+      keywords: [nginx, http, web]
+    - name: nginx
+      version: 15.4.0
+      appVersion: 1.25.3
+      description: NGINX Open Source is a web server and reverse proxy.
+      keywords: [nginx, http, web, proxy]
+    - name: nginx
+      version: 15.4.0-rc.1
+      appVersion: 1.25.3
+  kube-prometheus-stack:
+    - name: kube-prometheus-stack
+      version: "55.5.0"
+      appVersion: v0.70.0
+      description: Prometheus Operator, Grafana and Alertmanager.
+      keywords: [operator, prometheus, monitoring]
+  redis:
+    - name: redis
+      version: 18.6.1
+      appVersion: 7.2
+      description: Redis is an in-memory key-value store.
+      keywords: [redis, keyvalue, database]
+  memcached-legacy:
+    - name: memcached-legacy
+      version: 1.0.0
+      description: Old memcached packaging.
+      deprecated: true
+generated: "2024-05-01T10:00:00Z"
+"#;
+
+    fn catalog() -> Vec<CatalogChart> {
+        parse_index("bitnami", BITNAMI_INDEX).unwrap()
+    }
+
+    fn names(matches: &[ChartMatch]) -> Vec<&str> {
+        matches.iter().map(|m| m.chart.name.as_str()).collect()
+    }
+
+    #[test]
+    fn parses_index_entries_newest_first() {
+        let charts = catalog();
+        let nginx = charts.iter().find(|c| c.name == "nginx").unwrap();
+
+        assert_eq!(nginx.latest_version, "15.4.0");
+        assert_eq!(nginx.versions, vec!["15.4.0", "15.4.0-rc.1", "15.3.5"]);
+        assert_eq!(nginx.app_version.as_deref(), Some("1.25.3"));
+        assert!(nginx.keywords.contains(&"proxy".to_string()));
+
+        let redis = charts.iter().find(|c| c.name == "redis").unwrap();
+        assert_eq!(redis.app_version.as_deref(), Some("7.2"));
+        assert!(charts
+            .iter()
+            .any(|c| c.name == "memcached-legacy" && c.deprecated));
+    }
+
+    #[test]
+    fn compares_versions_by_semver_precedence() {
+        assert_eq!(compare_versions("1.10.0", "1.9.0"), Ordering::Greater);
+        assert_eq!(compare_versions("2.0.0-rc.1", "2.0.0"), Ordering::Less);
+        assert_eq!(
+            compare_versions("2.0.0-rc.10", "2.0.0-rc.2"),
+            Ordering::Greater
+        );
+        assert_eq!(compare_versions("v1.2", "1.2.0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn ranks_fuzzy_matches() {
+        let search_for = |text: &str| {
+            search(
+                catalog(),
+                &ChartSearchQuery {
+                    query: text.into(),
+                    ..Default::default()
+                },
+            )
+        };
+
+        assert_eq!(names(&search_for("nginx")), vec!["nginx"]);
+        assert_eq!(names(&search_for("kps")), vec!["kube-prometheus-stack"]);
+        assert_eq!(names(&search_for("reddis")), vec!["redis"]);
+        assert_eq!(
+            names(&search_for("monitoring")),
+            vec!["kube-prometheus-stack"]
+        );
+        assert_eq!(names(&search_for("bitnami/red")), vec!["redis"]);
+        assert!(search_for("memcached").is_empty());
+
+        let everything = search(
+            catalog(),
+            &ChartSearchQuery {
+                include_deprecated: true,
+                limit: Some(2),
+                ..Default::default()
+            },
+        );
+        assert_eq!(everything.len(), 2);
+    }
+
+    #[test]
+    fn syncs_only_changed_indexes() {
+        let app = tauri::test::mock_app();
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::connection::create_tables(&conn).unwrap();
+        let db: DbConnection = Arc::new(Mutex::new(conn));
+        app.manage(db);
+
+        let cache = tempfile::tempdir().unwrap();
+        std::fs::write(cache.path().join("bitnami-index.yaml"), BITNAMI_INDEX).unwrap();
+        std::fs::write(cache.path().join("bitnami-charts.txt"), "nginx\n").unwrap();
+        std::fs::write(cache.path().join("broken-index.yaml"), "entries: [").unwrap();
+
+        let first = sync_catalog(&app.state(), cache.path()).unwrap();
+        assert_eq!(first.updated, vec!["bitnami"]);
+        assert_eq!(first.failed, vec!["broken"]);
+        assert_eq!(charts::list_catalog_charts(&app.state()).unwrap().len(), 4);
+
+        let second = sync_catalog(&app.state(), cache.path()).unwrap();
+        assert!(second.updated.is_empty());
+
+        std::fs::remove_file(cache.path().join("bitnami-index.yaml")).unwrap();
+        let third = sync_catalog(&app.state(), cache.path()).unwrap();
+        assert_eq!(third.removed, vec!["bitnami"]);
+        assert!(charts::list_catalog_charts(&app.state())
+            .unwrap()
+            .is_empty());
+    }
+}
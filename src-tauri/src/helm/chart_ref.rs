@@ -0,0 +1,177 @@
+//! Chart reference module
+//!
+//! Charts come either from a repository added with `helm repo add`
+//! (`bitnami/nginx`) or from an OCI registry (`oci://ghcr.io/org/charts/nginx`).
+//! Both are passed to helm as-is; only how versions are listed and how the
+//! schema cache is keyed differ.
+
+use crate::error::RudderError;
+use std::fmt;
+
+pub const OCI_SCHEME: &str = "oci://";
+
+/// A validated chart reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChartRef {
+    /// `repo/chart` in a repository known to helm.
+    Repo { repo: String, name: String },
+    /// `oci://registry/path/chart` in an OCI registry.
+    Oci { reference: String },
+}
+
+impl ChartRef {
+    /// Parse a `repo/chart` or `oci://registry/path/chart` reference.
+    pub fn parse(chart: &str) -> Result<Self, RudderError> {
+        if let Some(location) = chart.strip_prefix(OCI_SCHEME) {
+            let (registry, path) = location.split_once('/').unwrap_or((location, ""));
+            if is_valid_registry(registry) && !path.is_empty() && path.split('/').all(is_valid_part)
+            {
+                return Ok(ChartRef::Oci {
+                    reference: chart.to_string(),
+                });
+            }
+        } else if let Some((repo, name)) = chart.split_once('/') {
+            if is_valid_part(repo) && is_valid_part(name) {
+                return Ok(ChartRef::Repo {
+                    repo: repo.to_string(),
+                    name: name.to_string(),
+                });
+            }
+        }
+
+        Err(RudderError::invalid_input(format!(
+            "Invalid chart '{}': expected a repository chart such as bitnami/nginx or an OCI reference such as oci://ghcr.io/org/charts/nginx",
+            chart
+        )))
+    }
+
+    /// Chart reference for a repository name and chart name as used by the
+    /// schema cache, where the repository may also be an OCI registry path
+    /// (`oci://ghcr.io/org/charts`) or a full OCI reference.
+    pub fn from_repo(repo_name: &str, chart_name: &str) -> Result<Self, RudderError> {
+        let repo_name = repo_name.trim_end_matches('/');
+        if is_oci(repo_name) && repo_name.ends_with(&format!("/{}", chart_name)) {
+            return Self::parse(repo_name);
+        }
+        Self::parse(&format!("{}/{}", repo_name, chart_name))
+    }
+
+    /// Name of the chart, i.e. the last path segment.
+    pub fn name(&self) -> &str {
+        match self {
+            ChartRef::Repo { name, .. } => name,
+            ChartRef::Oci { reference } => reference.rsplit('/').next().unwrap_or_default(),
+        }
+    }
+
+    /// Repository column of the schema cache: the repository name, or the
+    /// full reference for OCI charts, which have no repository name.
+    pub fn cache_repo(&self) -> String {
+        match self {
+            ChartRef::Repo { repo, .. } => repo.clone(),
+            ChartRef::Oci { reference } => reference.clone(),
+        }
+    }
+
+    /// Registry host and repository path of an OCI chart,
+    /// e.g. `("ghcr.io", "org/charts/nginx")`.
+    pub fn oci_location(&self) -> Option<(&str, &str)> {
+        match self {
+            ChartRef::Oci { reference } => reference[OCI_SCHEME.len()..].split_once('/'),
+            ChartRef::Repo { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for ChartRef {
+    /// The reference as passed to helm.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChartRef::Repo { repo, name } => write!(f, "{}/{}", repo, name),
+            ChartRef::Oci { reference } => f.write_str(reference),
+        }
+    }
+}
+
+/// Whether a chart or repository string is an OCI reference.
+pub fn is_oci(chart: &str) -> bool {
+    chart.starts_with(OCI_SCHEME)
+}
+
+fn is_valid_part(part: &str) -> bool {
+    !part.is_empty()
+        && !part.starts_with(['-', '.'])
+        && part
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+}
+
+/// Registry host with an optional port, e.g. `localhost:5000`.
+pub fn is_valid_registry(registry: &str) -> bool {
+    match registry.split_once(':') {
+        Some((host, port)) => {
+            is_valid_part(host) && !port.is_empty() && port.chars().all(|c| c.is_ascii_digit())
+        }
+        None => is_valid_part(registry),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_repository_and_oci_references() {
+        let repo = ChartRef::parse("bitnami/nginx").unwrap();
+        assert_eq!(repo.name(), "nginx");
+        assert_eq!(repo.cache_repo(), "bitnami");
+        assert_eq!(repo.oci_location(), None);
+
+        let oci = ChartRef::parse("oci://registry-1.docker.io/bitnamicharts/nginx").unwrap();
+        assert_eq!(oci.name(), "nginx");
+        assert_eq!(
+            oci.cache_repo(),
+            "oci://registry-1.docker.io/bitnamicharts/nginx"
+        );
+        assert_eq!(
+            oci.oci_location(),
+            Some(("registry-1.docker.io", "bitnamicharts/nginx"))
+        );
+        assert_eq!(
+            oci.to_string(),
+            "oci://registry-1.docker.io/bitnamicharts/nginx"
+        );
+
+        for invalid in [
+            "nginx",
+            "bitnami/--set=x",
+            "oci://",
+            "oci://ghcr.io",
+            "oci://ghcr.io/org//nginx",
+            "oci://ghcr.io/org/nginx:1.0",
+            "oci://-x/nginx",
+        ] {
+            assert!(ChartRef::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn builds_references_from_cache_keys() {
+        assert_eq!(
+            ChartRef::from_repo("oci://ghcr.io/org/charts", "web").unwrap(),
+            ChartRef::parse("oci://ghcr.io/org/charts/web").unwrap()
+        );
+        assert_eq!(
+            ChartRef::from_repo("oci://ghcr.io/org/charts/web", "web").unwrap(),
+            ChartRef::parse("oci://ghcr.io/org/charts/web").unwrap()
+        );
+        assert_eq!(
+            ChartRef::from_repo("localhost:5000", "web").map_err(|e| e.kind),
+            Err(crate::error::ErrorKind::InvalidInput)
+        );
+        assert_eq!(
+            ChartRef::from_repo("bitnami", "nginx").unwrap().to_string(),
+            "bitnami/nginx"
+        );
+    }
+}
@@ -0,0 +1,357 @@
+//! Chart tree module
+//!
+//! Reads a chart from disk, as a chart directory or a packaged `.tgz`,
+//! together with the subcharts vendored in its `charts/` directory, which
+//! may themselves be directories or archives. Only the files a values schema
+//! is built from are kept, including the JSON files its `$ref`s may point to;
+//! every file counts towards the content hash.
+
+use crate::error::RudderError;
+use flate2::read::GzDecoder;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Upper bound for an unpacked chart archive, so a corrupt file cannot exhaust memory.
+const MAX_ARCHIVE_SIZE: u64 = 64 * 1024 * 1024;
+const CHARTS_DIR: &str = "charts/";
+
+/// A chart and its subcharts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChartTree {
+    pub chart_yaml: Option<String>,
+    pub values_yaml: Option<String>,
+    pub values_schema: Option<String>,
+    /// Other JSON files of the chart, which its schema may refer to, keyed by
+    /// their path relative to the chart root.
+    pub schema_files: BTreeMap<String, String>,
+    /// Subcharts in `charts/`, keyed by their directory or archive name.
+    pub subcharts: BTreeMap<String, ChartTree>,
+}
+
+impl ChartTree {
+    /// Build a tree from `/`-separated paths relative to the chart root.
+    fn from_files<'a>(
+        files: impl IntoIterator<Item = (String, &'a [u8])>,
+    ) -> Result<Self, RudderError> {
+        let mut tree = ChartTree::default();
+        let mut nested: BTreeMap<String, Vec<(String, &[u8])>> = BTreeMap::new();
+        for (path, contents) in files {
+            let text = || Some(String::from_utf8_lossy(contents).into_owned());
+            match path.as_str() {
+                "Chart.yaml" => tree.chart_yaml = text(),
+                "values.yaml" => tree.values_yaml = text(),
+                "values.schema.json" => tree.values_schema = text(),
+                _ => {
+                    let Some(rest) = path.strip_prefix(CHARTS_DIR) else {
+                        if path.ends_with(".json") {
+                            tree.schema_files
+                                .insert(path.clone(), text().unwrap_or_default());
+                        }
+                        continue;
+                    };
+                    match rest.split_once('/') {
+                        Some((dir, inner)) => nested
+                            .entry(dir.to_string())
+                            .or_default()
+                            .push((inner.to_string(), contents)),
+                        None if rest.ends_with(".tgz") => {
+                            let subchart = Self::from_archive(contents)?;
+                            tree.subcharts.insert(rest.to_string(), subchart);
+                        }
+                        None => {}
+                    }
+                }
+            }
+        }
+        for (dir, files) in nested {
+            tree.subcharts.insert(dir, Self::from_files(files)?);
+        }
+        Ok(tree)
+    }
+
+    /// Read a packaged chart.
+    fn from_archive(archive: &[u8]) -> Result<Self, RudderError> {
+        Self::from_archive_within(archive, MAX_ARCHIVE_SIZE)
+    }
+
+    /// Read a packaged chart that unpacks to at most `max_size` bytes.
+    fn from_archive_within(archive: &[u8], max_size: u64) -> Result<Self, RudderError> {
+        let invalid = |e: io::Error| match e.kind() {
+            io::ErrorKind::FileTooLarge => RudderError::invalid_input(format!(
+                "Chart archive is too large: it unpacks to more than {} MiB",
+                max_size / (1024 * 1024)
+            )),
+            _ => RudderError::invalid_input(format!("Not a .tgz chart archive: {}", e)),
+        };
+
+        let mut tar = tar::Archive::new(SizeLimit {
+            inner: GzDecoder::new(archive),
+            remaining: max_size,
+        });
+        let mut files = Vec::new();
+        for entry in tar.entries().map_err(invalid)? {
+            let mut entry = entry.map_err(invalid)?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            // Packaged charts hold a single top-level directory named after the chart
+            let name = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+            let Some((_, relative_path)) = name.split_once('/') else {
+                continue;
+            };
+            if !Self::is_kept(relative_path) {
+                continue;
+            }
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents).map_err(invalid)?;
+            files.push((relative_path.to_string(), contents));
+        }
+        Self::from_files(
+            files
+                .iter()
+                .map(|(path, contents)| (path.clone(), contents.as_slice())),
+        )
+    }
+
+    /// Whether the file at this relative path is kept in the tree.
+    fn is_kept(relative_path: &str) -> bool {
+        matches!(
+            relative_path.rsplit('/').next(),
+            Some("Chart.yaml" | "values.yaml" | "values.schema.json")
+        ) || relative_path.ends_with(".json")
+            || (relative_path.starts_with(CHARTS_DIR) && relative_path.ends_with(".tgz"))
+    }
+}
+
+/// Read a chart directory or `.tgz` archive and hash its contents.
+pub fn read_chart(path: &Path) -> Result<(ChartTree, String), RudderError> {
+    if path.is_dir() {
+        return read_chart_dir(path);
+    }
+
+    let archive = fs::read(path)
+        .map_err(|e| RudderError::io(format!("Failed to read '{}': {}", path.display(), e)))?;
+    let tree = ChartTree::from_archive(&archive).map_err(|e| {
+        RudderError::invalid_input(format!(
+            "'{}' is not a chart directory or a .tgz chart archive: {}",
+            path.display(),
+            e.message
+        ))
+    })?;
+    Ok((tree, format!("{:x}", Sha256::digest(&archive))))
+}
+
+fn read_chart_dir(dir: &Path) -> Result<(ChartTree, String), RudderError> {
+    let mut paths = Vec::new();
+    collect_files(dir, dir, &mut paths)?;
+    paths.sort();
+
+    // Relative paths are hashed with the contents so renames change the hash too
+    let mut hasher = Sha256::new();
+    let mut kept = Vec::new();
+    for (relative_path, path) in paths {
+        let contents = fs::read(&path)
+            .map_err(|e| RudderError::io(format!("Failed to read '{}': {}", path.display(), e)))?;
+        hasher.update(relative_path.as_bytes());
+        hasher.update([0]);
+        hasher.update((contents.len() as u64).to_le_bytes());
+        hasher.update(&contents);
+        if ChartTree::is_kept(&relative_path) {
+            kept.push((relative_path, contents));
+        }
+    }
+
+    let tree = ChartTree::from_files(
+        kept.iter()
+            .map(|(path, contents)| (path.clone(), contents.as_slice())),
+    )?;
+    Ok((tree, format!("{:x}", hasher.finalize())))
+}
+
+/// Every file below `dir`, as a `/`-separated path relative to `root`.
+fn collect_files(
+    root: &Path,
+    dir: &Path,
+    files: &mut Vec<(String, PathBuf)>,
+) -> Result<(), RudderError> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| RudderError::io(format!("Failed to read '{}': {}", dir.display(), e)))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| RudderError::io(e.to_string()))?;
+        let path = entry.path();
+        // Symlinked directories are not followed, so a link cycle cannot recurse forever
+        let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+        if is_dir {
+            collect_files(root, &path, files)?;
+        } else if path.is_file() {
+            let relative_path = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((relative_path, path));
+        }
+    }
+    Ok(())
+}
+
+/// Reader that fails instead of reading more than `remaining` bytes.
+struct SizeLimit<R> {
+    inner: R,
+    remaining: u64,
+}
+
+impl<R: Read> Read for SizeLimit<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.remaining = self
+            .remaining
+            .checked_sub(read as u64)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::FileTooLarge, "archive too large"))?;
+        Ok(read)
+    }
+}
+
+/// Builders for chart archives in tests.
+#[cfg(test)]
+pub(crate) mod archive {
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    const TAR_BLOCK: usize = 512;
+
+    /// Append a tar entry of the given type (`b'0'` for a regular file).
+    pub fn tar_entry(tar: &mut Vec<u8>, name: &str, contents: &[u8], kind: u8) {
+        let mut header = vec![0u8; TAR_BLOCK];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[124..135].copy_from_slice(format!("{:011o}", contents.len()).as_bytes());
+        header[156] = kind;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        // The checksum is computed with its own field set to spaces
+        header[148..156].fill(b' ');
+        let checksum: u32 = header.iter().map(|&b| u32::from(b)).sum();
+        header[148..155].copy_from_slice(format!("{:06o}\0", checksum).as_bytes());
+        tar.extend(header);
+        tar.extend(contents);
+        tar.resize(tar.len().div_ceil(TAR_BLOCK) * TAR_BLOCK, 0);
+    }
+
+    /// A gzip-compressed tar archive of regular files.
+    pub fn tgz(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut tar = Vec::new();
+        for (name, contents) in files {
+            tar_entry(&mut tar, name, contents, b'0');
+        }
+        gzip(tar)
+    }
+
+    /// Terminate a tar archive and compress it.
+    pub fn gzip(mut tar: Vec<u8>) -> Vec<u8> {
+        tar.extend([0u8; TAR_BLOCK * 2]);
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&tar).unwrap();
+        encoder.finish().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::archive::tgz;
+    use super::*;
+
+    #[test]
+    fn reads_subcharts_from_directories_and_nested_archives() {
+        let redis = tgz(&[
+            ("redis/Chart.yaml", b"name: redis\nversion: 18.0.0\n"),
+            ("redis/values.yaml", b"architecture: replication\n"),
+            ("redis/templates/master.yaml", b"kind: StatefulSet\n"),
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("charts/postgresql/templates")).unwrap();
+        fs::write(root.join("Chart.yaml"), "name: web\nversion: 1.0.0\n").unwrap();
+        fs::write(
+            root.join("charts/postgresql/Chart.yaml"),
+            "name: postgresql\n",
+        )
+        .unwrap();
+        fs::write(root.join("charts/postgresql/values.schema.json"), "{}").unwrap();
+        fs::create_dir_all(root.join("charts/postgresql/schemas")).unwrap();
+        fs::write(root.join("charts/postgresql/schemas/auth.json"), "{}").unwrap();
+        fs::write(
+            root.join("charts/postgresql/templates/svc.yaml"),
+            "kind: Service\n",
+        )
+        .unwrap();
+        fs::write(root.join("charts/redis-18.0.0.tgz"), &redis).unwrap();
+
+        let (tree, hash) = read_chart(root).unwrap();
+
+        assert_eq!(
+            tree.chart_yaml.as_deref(),
+            Some("name: web\nversion: 1.0.0\n")
+        );
+        assert_eq!(
+            tree.subcharts.keys().collect::<Vec<_>>(),
+            vec!["postgresql", "redis-18.0.0.tgz"]
+        );
+        assert_eq!(
+            tree.subcharts["postgresql"].values_schema.as_deref(),
+            Some("{}")
+        );
+        assert_eq!(
+            tree.subcharts["postgresql"]
+                .schema_files
+                .keys()
+                .collect::<Vec<_>>(),
+            vec!["schemas/auth.json"]
+        );
+        assert_eq!(
+            tree.subcharts["redis-18.0.0.tgz"].values_yaml.as_deref(),
+            Some("architecture: replication\n")
+        );
+
+        fs::write(
+            root.join("charts/postgresql/templates/svc.yaml"),
+            "kind: Pod\n",
+        )
+        .unwrap();
+        assert_ne!(read_chart(root).unwrap().1, hash);
+    }
+
+    #[test]
+    fn rejects_archives_that_unpack_too_large() {
+        let template = vec![b'#'; 2 * 1024 * 1024];
+        let archive = tgz(&[
+            ("web/Chart.yaml", b"name: web\n"),
+            ("web/templates/big.yaml", &template),
+        ]);
+
+        let error = ChartTree::from_archive_within(&archive, 1024 * 1024).unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Chart archive is too large: it unpacks to more than 1 MiB"
+        );
+        assert!(ChartTree::from_archive_within(&archive, 4 * 1024 * 1024).is_ok());
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("web.tgz");
+        fs::write(&path, "not gzip").unwrap();
+
+        let error = read_chart(&path).unwrap_err();
+
+        assert!(error
+            .message
+            .contains("is not a chart directory or a .tgz chart archive"));
+    }
+}
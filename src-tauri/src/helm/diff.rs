@@ -0,0 +1,322 @@
+//! Helm diff module
+//!
+//! Structured differences between two sets of values or two rendered
+//! manifests, shared by revision comparison and upgrade previews. The values
+//! of Secrets are hidden; a diff only tells whether each of them changed.
+
+use super::manifest::{ManifestResource, ResourceKey};
+use serde::Serialize;
+use serde_json::Value;
+use similar::TextDiff;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Shown instead of a Secret value.
+pub const SECRET_HIDDEN: &str = "(hidden)";
+const SECRET_UNCHANGED: &str = "(hidden, unchanged)";
+const SECRET_OLD: &str = "(hidden, old value)";
+const SECRET_NEW: &str = "(hidden, new value)";
+
+/// How an entry differs between the old and the new side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A single value that differs, addressed by its key path, e.g. `image.tag`
+/// or `podAnnotations["prometheus.io/scrape"]`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValueChange {
+    pub path: String,
+    pub change: DiffKind,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+/// Key paths that differ between `old` and `new`, in key order.
+///
+/// Objects and arrays are compared member by member; any other difference,
+/// including a change of type, is reported at the path where it occurs.
+pub fn diff_values(old: &Value, new: &Value) -> Vec<ValueChange> {
+    let mut changes = Vec::new();
+    diff_at("", old, new, &mut changes);
+    changes
+}
+
+fn diff_at(path: &str, old: &Value, new: &Value, changes: &mut Vec<ValueChange>) {
+    match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            let keys: BTreeSet<&String> = old_map.keys().chain(new_map.keys()).collect();
+            for key in keys {
+                let child = child_path(path, key);
+                match (old_map.get(key), new_map.get(key)) {
+                    (Some(o), Some(n)) => diff_at(&child, o, n, changes),
+                    (o, n) => changes.push(added_or_removed(child, o, n)),
+                }
+            }
+        }
+        (Value::Array(old_items), Value::Array(new_items)) => {
+            for i in 0..old_items.len().max(new_items.len()) {
+                let child = format!("{}[{}]", path, i);
+                match (old_items.get(i), new_items.get(i)) {
+                    (Some(o), Some(n)) => diff_at(&child, o, n, changes),
+                    (o, n) => changes.push(added_or_removed(child, o, n)),
+                }
+            }
+        }
+        _ if old != new => changes.push(ValueChange {
+            path: path.to_string(),
+            change: DiffKind::Changed,
+            old: Some(old.clone()),
+            new: Some(new.clone()),
+        }),
+        _ => {}
+    }
+}
+
+fn added_or_removed(path: String, old: Option<&Value>, new: Option<&Value>) -> ValueChange {
+    ValueChange {
+        path,
+        change: if old.is_none() { DiffKind::Added } else { DiffKind::Removed },
+        old: old.cloned(),
+        new: new.cloned(),
+    }
+}
+
+/// Append `key` to a dotted path, quoting keys that contain path syntax.
+fn child_path(path: &str, key: &str) -> String {
+    let plain = !key.is_empty() && !key.contains(['.', '[', ']', '"']);
+    match (path.is_empty(), plain) {
+        (true, true) => key.to_string(),
+        (false, true) => format!("{}.{}", path, key),
+        (_, false) => format!("{}[{}]", path, Value::String(key.to_string())),
+    }
+}
+
+/// Unified diff of two texts with three lines of context.
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(old_label, new_label)
+        .to_string()
+}
+
+/// A Kubernetes object that differs between two manifests.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceDiff {
+    #[serde(flatten)]
+    pub key: ResourceKey,
+    pub change: DiffKind,
+    /// Field-level changes of a changed object, e.g. `spec.replicas`.
+    pub changes: Vec<ValueChange>,
+    /// Unified diff of the rendered YAML.
+    pub diff: String,
+}
+
+/// Number of objects per kind of change, e.g. "3 changed, 1 added".
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffSummary {
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+}
+
+impl DiffSummary {
+    pub fn of(resources: &[ResourceDiff]) -> Self {
+        let count = |kind| resources.iter().filter(|r| r.change == kind).count();
+        Self {
+            added: count(DiffKind::Added),
+            removed: count(DiffKind::Removed),
+            changed: count(DiffKind::Changed),
+        }
+    }
+}
+
+/// The resource with its `data` and `stringData` values replaced by
+/// placeholders if it is a Secret. Values equal to those of `other` read as
+/// unchanged, the others as `marker`.
+pub fn hide_secret_values<'a>(
+    resource: &'a ManifestResource,
+    other: Option<&ManifestResource>,
+    marker: &str,
+) -> Cow<'a, ManifestResource> {
+    if resource.key.kind != "Secret" {
+        return Cow::Borrowed(resource);
+    }
+    let mut hidden = resource.clone();
+    for field in ["data", "stringData"] {
+        let Some(values) = hidden.object.get_mut(field).and_then(Value::as_object_mut) else {
+            continue;
+        };
+        for (key, value) in values.iter_mut() {
+            let unchanged = other.is_some_and(|o| o.object[field].get(key) == Some(&*value));
+            *value = Value::from(if unchanged { SECRET_UNCHANGED } else { marker });
+        }
+    }
+    hidden.yaml = serde_yaml::to_string(&hidden.object).unwrap_or_default();
+    Cow::Owned(hidden)
+}
+
+/// Objects added, removed or changed between two manifests, sorted by kind and name.
+pub fn diff_resources(
+    old: &[ManifestResource],
+    new: &[ManifestResource],
+    old_label: &str,
+    new_label: &str,
+) -> Vec<ResourceDiff> {
+    let old_by_key: BTreeMap<&ResourceKey, &ManifestResource> =
+        old.iter().map(|r| (&r.key, r)).collect();
+    let new_by_key: BTreeMap<&ResourceKey, &ManifestResource> =
+        new.iter().map(|r| (&r.key, r)).collect();
+    let keys: BTreeSet<&ResourceKey> = old_by_key.keys().chain(new_by_key.keys()).copied().collect();
+
+    keys.into_iter()
+        .filter_map(|key| {
+            let (old, new) = match (old_by_key.get(key), new_by_key.get(key)) {
+                (Some(o), Some(n)) if o.yaml == n.yaml => return None,
+                (o, n) => (
+                    o.map(|o| hide_secret_values(o, n.copied(), SECRET_OLD)),
+                    n.map(|n| hide_secret_values(n, o.copied(), SECRET_NEW)),
+                ),
+            };
+            let (change, changes) = match (&old, &new) {
+                (Some(o), Some(n)) => (DiffKind::Changed, diff_values(&o.object, &n.object)),
+                (None, _) => (DiffKind::Added, Vec::new()),
+                (_, None) => (DiffKind::Removed, Vec::new()),
+            };
+            Some(ResourceDiff {
+                key: key.clone(),
+                change,
+                changes,
+                diff: unified_diff(
+                    old.as_ref().map(|r| r.yaml.as_str()).unwrap_or_default(),
+                    new.as_ref().map(|r| r.yaml.as_str()).unwrap_or_default(),
+                    old_label,
+                    new_label,
+                ),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helm::manifest::parse_manifest;
+    use serde_json::json;
+
+    #[test]
+    fn reports_value_paths_that_changed() {
+        let old = json!({
+            "replicaCount": 1,
+            "image": {"repository": "nginx", "tag": "1.25"},
+            "podAnnotations": {"prometheus.io/scrape": "true"},
+            "ports": [80, 443],
+            "debug": true,
+        });
+        let new = json!({
+            "replicaCount": 3,
+            "image": {"repository": "nginx", "tag": "1.26"},
+            "podAnnotations": {},
+            "ports": [80, 443, 8080],
+            "resources": {"limits": {"memory": "512Mi"}},
+        });
+
+        let changes: Vec<(String, DiffKind)> = diff_values(&old, &new)
+            .into_iter()
+            .map(|c| (c.path, c.change))
+            .collect();
+
+        assert_eq!(
+            changes,
+            vec![
+                ("debug".to_string(), DiffKind::Removed),
+                ("image.tag".to_string(), DiffKind::Changed),
+                (r#"podAnnotations["prometheus.io/scrape"]"#.to_string(), DiffKind::Removed),
+                ("ports[2]".to_string(), DiffKind::Added),
+                ("replicaCount".to_string(), DiffKind::Changed),
+                ("resources".to_string(), DiffKind::Added),
+            ]
+        );
+        assert!(diff_values(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn hides_secret_values() {
+        let old = parse_manifest(
+            "kind: Secret\nmetadata:\n  name: web\ndata:\n  user: YWRtaW4=\n  password: b2xk\nstringData:\n  token: abc\n",
+        )
+        .unwrap();
+        let new = parse_manifest(
+            "kind: Secret\nmetadata:\n  name: web\ndata:\n  user: YWRtaW4=\n  password: bmV3\nstringData:\n  token: abc\n  key: xyz\n",
+        )
+        .unwrap();
+
+        let diffs = diff_resources(&old, &new, "revision 1", "revision 2");
+
+        let changes: Vec<(&str, DiffKind, Option<Value>, Option<Value>)> = diffs[0]
+            .changes
+            .iter()
+            .map(|c| (c.path.as_str(), c.change, c.old.clone(), c.new.clone()))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (
+                    "data.password",
+                    DiffKind::Changed,
+                    Some(json!(SECRET_OLD)),
+                    Some(json!(SECRET_NEW))
+                ),
+                ("stringData.key", DiffKind::Added, None, Some(json!(SECRET_NEW))),
+            ]
+        );
+        assert!(diffs[0].diff.contains(&format!("  user: {}", SECRET_UNCHANGED)));
+        for secret in ["YWRtaW4=", "b2xk", "bmV3", "abc", "xyz"] {
+            assert!(!diffs[0].diff.contains(secret), "{}", diffs[0].diff);
+        }
+    }
+
+    #[test]
+    fn groups_manifest_changes_by_resource() {
+        let old = parse_manifest(
+            "---\nkind: Service\nmetadata:\n  name: web\n---\nkind: Deployment\nmetadata:\n  name: web\nspec:\n  replicas: 1\n---\nkind: ConfigMap\nmetadata:\n  name: old\n",
+        )
+        .unwrap();
+        let new = parse_manifest(
+            "---\nkind: Service\nmetadata:\n  name: web\n---\nkind: Deployment\nmetadata:\n  name: web\nspec:\n  replicas: 3\n---\nkind: Secret\nmetadata:\n  name: new\n",
+        )
+        .unwrap();
+
+        let diffs = diff_resources(&old, &new, "revision 1", "revision 2");
+
+        let summary: Vec<(String, DiffKind)> = diffs
+            .iter()
+            .map(|d| (d.key.to_string(), d.change))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("ConfigMap/old".to_string(), DiffKind::Removed),
+                ("Deployment/web".to_string(), DiffKind::Changed),
+                ("Secret/new".to_string(), DiffKind::Added),
+            ]
+        );
+        assert_eq!(diffs[1].changes.len(), 1);
+        assert_eq!(diffs[1].changes[0].path, "spec.replicas");
+        assert!(diffs[0].changes.is_empty());
+        assert_eq!(
+            DiffSummary::of(&diffs),
+            DiffSummary { added: 1, removed: 1, changed: 1 }
+        );
+        assert!(diffs[1].diff.starts_with("--- revision 1\n+++ revision 2\n"));
+        assert!(diffs[1].diff.contains("-  replicas: 1\n+  replicas: 3\n"));
+    }
+}
@@ -0,0 +1,304 @@
+//! Helm install module
+//!
+//! Installs a repository or OCI chart as a new release. The install form is built
+//! from the chart's versions and schema before the release exists, and the
+//! release name is checked so helm never fails halfway on a name clash.
+
+use super::chart_ref::ChartRef;
+use super::release::ReleaseStatus;
+use super::upgrade::{validate_release_name, UpgradeOptions};
+use crate::error::{ErrorKind, RudderError};
+use crate::runner::command::CommandRunner;
+use serde::{Deserialize, Serialize};
+
+/// A `helm install` of a repository or OCI chart, as sent by the frontend.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallRequest {
+    /// Chart reference in `repo/chart` or `oci://registry/path/chart` form.
+    pub chart: String,
+    /// Chart version or version constraint; the latest version when omitted.
+    #[serde(default)]
+    pub version: Option<String>,
+    pub release_name: String,
+    pub namespace: String,
+    /// Create the namespace if it does not exist (`--create-namespace`).
+    #[serde(default)]
+    pub create_namespace: bool,
+    pub values_json: String,
+}
+
+impl InstallRequest {
+    fn options(&self) -> UpgradeOptions {
+        UpgradeOptions {
+            namespace: Some(self.namespace.clone()),
+            create_namespace: self.create_namespace,
+            version: self.version.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Check the request, so helm is never started with arguments it would reject.
+    pub fn validate(&self) -> Result<(), RudderError> {
+        ChartRef::parse(&self.chart)?;
+        validate_release_name(&self.release_name)?;
+        self.options().validate()
+    }
+
+    /// Validate the request and convert it to `helm install` arguments.
+    pub fn to_args(&self, values_path: &str) -> Result<Vec<String>, RudderError> {
+        self.validate()?;
+
+        let mut args = vec![
+            "install".to_string(),
+            self.release_name.clone(),
+            self.chart.clone(),
+        ];
+        args.extend(self.options().to_args()?);
+        args.push("-f".to_string());
+        args.push(values_path.to_string());
+        Ok(args)
+    }
+}
+
+/// A published version of a chart.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChartVersion {
+    pub version: String,
+    #[serde(alias = "app_version", default)]
+    pub app_version: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// Everything the install form needs before the release exists.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallPreparation {
+    pub chart: String,
+    /// Versions in the local repository index or the OCI registry, newest first.
+    pub versions: Vec<ChartVersion>,
+    /// Version the schema was fetched for.
+    pub version: String,
+    pub schema: serde_json::Value,
+    /// Whether the requested release name is free; `None` if no name was given.
+    pub release_name_available: Option<bool>,
+}
+
+/// Versions of a chart, newest first: from the local repository index for
+/// `repo/chart`, or from the registry for OCI charts.
+pub async fn list_chart_versions(
+    runner: &dyn CommandRunner,
+    chart: &ChartRef,
+) -> Result<Vec<ChartVersion>, RudderError> {
+    if let ChartRef::Oci { .. } = chart {
+        return super::oci::list_oci_versions(runner, chart).await;
+    }
+
+    let chart = chart.to_string();
+    let output = runner
+        .run(
+            "helm",
+            ["search", "repo", &chart, "--versions", "-o", "json"],
+        )
+        .await
+        .map_err(|e| RudderError::spawn_failed("Helm search command failed", e))?;
+
+    if !output.success() {
+        return Err(RudderError::command_failed(
+            "Helm search command failed",
+            &output,
+        ));
+    }
+
+    #[derive(Deserialize)]
+    struct SearchResult {
+        name: String,
+        #[serde(flatten)]
+        version: ChartVersion,
+    }
+
+    let results: Vec<SearchResult> = serde_json::from_slice(&output.stdout).map_err(|e| {
+        RudderError::new(
+            ErrorKind::CommandFailed,
+            format!("Failed to parse helm search output: {}", e),
+        )
+    })?;
+
+    // `helm search repo` matches substrings, e.g. bitnami/nginx-ingress-controller
+    Ok(results
+        .into_iter()
+        .filter(|result| result.name == chart)
+        .map(|result| result.version)
+        .collect())
+}
+
+/// Status of an existing release with this name, `None` if there is none.
+pub async fn existing_release(
+    runner: &dyn CommandRunner,
+    release_name: &str,
+    namespace: &str,
+) -> Result<Option<ReleaseStatus>, RudderError> {
+    let output = runner
+        .run(
+            "helm",
+            ["status", release_name, "-n", namespace, "-o", "json"],
+        )
+        .await
+        .map_err(|e| RudderError::spawn_failed("Helm status command failed", e))?;
+
+    if !output.success() {
+        let error = RudderError::command_failed("Helm status command failed", &output);
+        return match error.kind {
+            ErrorKind::ReleaseNotFound => Ok(None),
+            _ => Err(error),
+        };
+    }
+
+    let status: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap_or_default();
+    Ok(Some(
+        serde_json::from_value(status["info"]["status"].clone()).unwrap_or(ReleaseStatus::Unknown),
+    ))
+}
+
+/// Fail with a conflict if the release name is already taken in the namespace,
+/// including by a failed or uninstalled release whose history was kept.
+pub async fn ensure_release_name_available(
+    runner: &dyn CommandRunner,
+    release_name: &str,
+    namespace: &str,
+) -> Result<(), RudderError> {
+    match existing_release(runner, release_name, namespace).await? {
+        None => Ok(()),
+        Some(status) => Err(RudderError::new(
+            ErrorKind::Conflict,
+            format!(
+                "Release {} already exists in namespace {} ({})",
+                release_name,
+                namespace,
+                serde_json::to_value(status)
+                    .ok()
+                    .and_then(|s| s.as_str().map(String::from))
+                    .unwrap_or_default()
+            ),
+        )
+        .with_remediation("Choose another release name, or upgrade the existing release instead.")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::fixture::FixtureRunner;
+
+    fn request() -> InstallRequest {
+        InstallRequest {
+            chart: "bitnami/nginx".into(),
+            version: Some("15.4.0".into()),
+            release_name: "web".into(),
+            namespace: "apps".into(),
+            create_namespace: true,
+            values_json: "{}".into(),
+        }
+    }
+
+    #[test]
+    fn converts_the_request_to_helm_install_arguments() {
+        assert_eq!(
+            request().to_args("/tmp/values.json").unwrap(),
+            vec![
+                "install",
+                "web",
+                "bitnami/nginx",
+                "-n",
+                "apps",
+                "--create-namespace",
+                "--version=15.4.0",
+                "-f",
+                "/tmp/values.json",
+            ]
+        );
+        let oci = InstallRequest {
+            chart: "oci://ghcr.io/org/charts/web".into(),
+            ..request()
+        };
+        assert_eq!(
+            oci.to_args("/tmp/values.json").unwrap()[2],
+            "oci://ghcr.io/org/charts/web"
+        );
+
+        let invalid = [
+            InstallRequest {
+                chart: "nginx".into(),
+                ..request()
+            },
+            InstallRequest {
+                chart: "bitnami/--set=x".into(),
+                ..request()
+            },
+            InstallRequest {
+                release_name: "Web".into(),
+                ..request()
+            },
+            InstallRequest {
+                namespace: "-n".into(),
+                ..request()
+            },
+        ];
+        for request in invalid {
+            assert_eq!(
+                request.validate().unwrap_err().kind,
+                ErrorKind::InvalidInput
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn lists_only_versions_of_the_exact_chart() {
+        let runner = FixtureRunner::new().respond(
+            "helm search repo bitnami/nginx --versions -o json",
+            r#"[
+                {"name":"bitnami/nginx","version":"15.4.0","app_version":"1.25.3","description":"NGINX"},
+                {"name":"bitnami/nginx","version":"15.3.5","app_version":"1.25.2","description":"NGINX"},
+                {"name":"bitnami/nginx-ingress-controller","version":"9.9.0","app_version":"1.9.3","description":"Ingress"}
+            ]"#,
+        );
+
+        let chart = ChartRef::parse("bitnami/nginx").unwrap();
+        let versions = list_chart_versions(&runner, &chart).await.unwrap();
+
+        let versions: Vec<(&str, &str)> = versions
+            .iter()
+            .map(|v| (v.version.as_str(), v.app_version.as_str()))
+            .collect();
+        assert_eq!(versions, vec![("15.4.0", "1.25.3"), ("15.3.5", "1.25.2")]);
+    }
+
+    #[tokio::test]
+    async fn rejects_a_release_name_that_is_taken() {
+        let runner = FixtureRunner::new()
+            .respond(
+                "helm status web -n apps -o json",
+                r#"{"name":"web","info":{"status":"failed"},"version":2}"#,
+            )
+            .fail(
+                "helm status api -n apps -o json",
+                "Error: release: not found",
+                1,
+            );
+
+        let error = ensure_release_name_available(&runner, "web", "apps")
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Conflict);
+        assert_eq!(
+            error.message,
+            "Release web already exists in namespace apps (failed)"
+        );
+
+        assert!(ensure_release_name_available(&runner, "api", "apps")
+            .await
+            .is_ok());
+    }
+}
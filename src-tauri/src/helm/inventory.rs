@@ -0,0 +1,417 @@
+//! Release inventory module
+//!
+//! Typed list of the Kubernetes objects a release manages, read from its
+//! manifest and optionally enriched with their live state from `kubectl get`.
+
+use super::manifest::{parse_manifest, ManifestResource};
+use crate::error::{ErrorKind, RudderError};
+use crate::runner::command::CommandRunner;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// Kinds that are never namespaced, so the release namespace does not apply.
+const CLUSTER_SCOPED_KINDS: &[&str] = &[
+    "APIService",
+    "ClusterIssuer",
+    "ClusterRole",
+    "ClusterRoleBinding",
+    "CustomResourceDefinition",
+    "IngressClass",
+    "MutatingWebhookConfiguration",
+    "Namespace",
+    "Node",
+    "PersistentVolume",
+    "PriorityClass",
+    "StorageClass",
+    "ValidatingWebhookConfiguration",
+];
+
+/// A Kubernetes object managed by a release.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseResource {
+    pub api_version: String,
+    pub kind: String,
+    pub name: String,
+    /// Namespace the object lives in, `None` for cluster-scoped objects.
+    pub namespace: Option<String>,
+    pub labels: BTreeMap<String, String>,
+    /// Template that rendered the object, e.g. `nginx/templates/deployment.yaml`.
+    pub source: Option<String>,
+    /// Live state, only filled in when requested.
+    pub status: Option<ResourceStatus>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ResourceState {
+    Ready,
+    Progressing,
+    Failed,
+    /// In the manifest but not in the cluster.
+    Missing,
+    /// The live object could not be fetched.
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceStatus {
+    pub state: ResourceState,
+    /// Short description, e.g. `2/3 ready` or `Bound`.
+    pub message: String,
+}
+
+impl ReleaseResource {
+    /// Inventory entry for a manifest object; namespaced objects without an
+    /// explicit namespace live in the release namespace.
+    pub fn from_manifest(resource: ManifestResource, release_namespace: &str) -> Self {
+        let namespace = if CLUSTER_SCOPED_KINDS.contains(&resource.key.kind.as_str()) {
+            None
+        } else {
+            Some(
+                resource
+                    .key
+                    .namespace
+                    .unwrap_or_else(|| release_namespace.to_string()),
+            )
+        };
+        let labels = resource.object["metadata"]["labels"]
+            .as_object()
+            .map(|labels| {
+                labels
+                    .iter()
+                    .map(|(k, v)| {
+                        (
+                            k.clone(),
+                            v.as_str()
+                                .map(String::from)
+                                .unwrap_or_else(|| v.to_string()),
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            api_version: resource.api_version,
+            kind: resource.key.kind,
+            name: resource.key.name,
+            namespace,
+            labels,
+            source: resource.source,
+            status: None,
+        }
+    }
+
+    /// Reference accepted by `kubectl get`, qualified with the API group,
+    /// e.g. `deployment.apps/web`.
+    fn kubectl_ref(&self) -> String {
+        match self.api_version.split_once('/') {
+            Some((group, _)) => format!("{}.{}/{}", self.kind.to_lowercase(), group, self.name),
+            None => format!("{}/{}", self.kind.to_lowercase(), self.name),
+        }
+    }
+}
+
+/// Build the inventory from a rendered release manifest.
+pub fn inventory_from_manifest(
+    manifest: &str,
+    release_namespace: &str,
+) -> Result<Vec<ReleaseResource>, RudderError> {
+    let resources =
+        parse_manifest(manifest).map_err(|e| RudderError::new(ErrorKind::CommandFailed, e))?;
+    Ok(resources
+        .into_iter()
+        .map(|r| ReleaseResource::from_manifest(r, release_namespace))
+        .collect())
+}
+
+/// Objects managed by a release, with their live status if `include_status` is set.
+pub async fn get_release_resources(
+    runner: &dyn CommandRunner,
+    release_name: &str,
+    namespace: &str,
+    include_status: bool,
+) -> Result<Vec<ReleaseResource>, RudderError> {
+    let output = runner
+        .run("helm", ["get", "manifest", release_name, "-n", namespace])
+        .await
+        .map_err(|e| RudderError::spawn_failed("Helm get manifest command failed", e))?;
+
+    if !output.success() {
+        return Err(RudderError::command_failed(
+            "Helm get manifest command failed",
+            &output,
+        ));
+    }
+
+    let mut resources =
+        inventory_from_manifest(&String::from_utf8_lossy(&output.stdout), namespace)?;
+    if include_status {
+        attach_live_status(runner, &mut resources, namespace).await;
+    }
+    Ok(resources)
+}
+
+/// Fetch every object with one `kubectl get` per namespace and attach its status.
+///
+/// A failing `kubectl get` marks the affected objects `Unknown` instead of
+/// failing the whole inventory.
+async fn attach_live_status(
+    runner: &dyn CommandRunner,
+    resources: &mut [ReleaseResource],
+    release_namespace: &str,
+) {
+    let mut by_namespace: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (i, resource) in resources.iter().enumerate() {
+        let namespace = resource
+            .namespace
+            .clone()
+            .unwrap_or_else(|| release_namespace.to_string());
+        by_namespace.entry(namespace).or_default().push(i);
+    }
+
+    for (namespace, indices) in by_namespace {
+        let mut args = vec!["get".to_string()];
+        args.extend(indices.iter().map(|&i| resources[i].kubectl_ref()));
+        args.extend(["-n", &namespace, "-o", "json", "--ignore-not-found"].map(String::from));
+
+        let live = match runner.output("kubectl", &args).await {
+            Ok(output) if output.success() => {
+                Ok(live_objects(&String::from_utf8_lossy(&output.stdout)))
+            }
+            Ok(output) => Err(RudderError::command_failed("kubectl get failed", &output).message),
+            Err(e) => Err(e),
+        };
+
+        for i in indices {
+            let resource = &mut resources[i];
+            resource.status = Some(match &live {
+                Ok(objects) => match objects.get(&(resource.kind.clone(), resource.name.clone())) {
+                    Some(object) => status_of(&resource.kind, object),
+                    None => ResourceStatus {
+                        state: ResourceState::Missing,
+                        message: "Not found in the cluster".to_string(),
+                    },
+                },
+                Err(message) => ResourceStatus {
+                    state: ResourceState::Unknown,
+                    message: message.clone(),
+                },
+            });
+        }
+    }
+}
+
+/// Objects printed by `kubectl get -o json`, keyed by kind and name.
+fn live_objects(json: &str) -> HashMap<(String, String), Value> {
+    let parsed: Value = serde_json::from_str(json).unwrap_or(Value::Null);
+    // A single object is printed as is, several as a List
+    let items = match parsed["kind"].as_str() {
+        Some("List") => parsed["items"].as_array().cloned().unwrap_or_default(),
+        Some(_) => vec![parsed],
+        None => Vec::new(),
+    };
+
+    items
+        .into_iter()
+        .map(|item| {
+            let kind = item["kind"].as_str().unwrap_or_default().to_string();
+            let name = item["metadata"]["name"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string();
+            ((kind, name), item)
+        })
+        .collect()
+}
+
+/// Derive a readiness summary from a live object.
+fn status_of(kind: &str, object: &Value) -> ResourceStatus {
+    let status = &object["status"];
+    let count = |value: &Value| value.as_u64().unwrap_or(0);
+    let replicas = |ready: u64, desired: u64| ResourceStatus {
+        state: if ready >= desired {
+            ResourceState::Ready
+        } else {
+            ResourceState::Progressing
+        },
+        message: format!("{}/{} ready", ready, desired),
+    };
+    let phase = |ready_phases: &[&str], failed_phases: &[&str]| {
+        let phase = status["phase"].as_str().unwrap_or("Unknown");
+        ResourceStatus {
+            state: if ready_phases.contains(&phase) {
+                ResourceState::Ready
+            } else if failed_phases.contains(&phase) {
+                ResourceState::Failed
+            } else {
+                ResourceState::Progressing
+            },
+            message: phase.to_string(),
+        }
+    };
+
+    match kind {
+        "Deployment" | "StatefulSet" | "ReplicaSet" => replicas(
+            count(&status["readyReplicas"]),
+            object["spec"]["replicas"].as_u64().unwrap_or(1),
+        ),
+        "DaemonSet" => replicas(
+            count(&status["numberReady"]),
+            count(&status["desiredNumberScheduled"]),
+        ),
+        "Pod" => phase(&["Running", "Succeeded"], &["Failed"]),
+        "PersistentVolumeClaim" => phase(&["Bound"], &["Lost"]),
+        "Job" => {
+            let completions = object["spec"]["completions"].as_u64().unwrap_or(1);
+            if count(&status["succeeded"]) >= completions {
+                ResourceStatus {
+                    state: ResourceState::Ready,
+                    message: "Complete".to_string(),
+                }
+            } else if count(&status["failed"]) > 0 {
+                ResourceStatus {
+                    state: ResourceState::Failed,
+                    message: format!("{} failed", count(&status["failed"])),
+                }
+            } else {
+                ResourceStatus {
+                    state: ResourceState::Progressing,
+                    message: "Running".to_string(),
+                }
+            }
+        }
+        _ => {
+            // Objects with a Ready/Available condition (e.g. certificates), else existence is enough
+            let condition = status["conditions"].as_array().and_then(|conditions| {
+                conditions
+                    .iter()
+                    .find(|c| matches!(c["type"].as_str(), Some("Ready") | Some("Available")))
+            });
+            match condition {
+                Some(c) if c["status"] == "True" => ResourceStatus {
+                    state: ResourceState::Ready,
+                    message: c["type"].as_str().unwrap_or_default().to_string(),
+                },
+                Some(c) => ResourceStatus {
+                    state: ResourceState::Progressing,
+                    message: c["reason"].as_str().unwrap_or("Not ready").to_string(),
+                },
+                None => ResourceStatus {
+                    state: ResourceState::Ready,
+                    message: "Exists".to_string(),
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::fixture::FixtureRunner;
+
+    const MANIFEST: &str = r#"---
+# Source: web/templates/clusterrole.yaml
+apiVersion: rbac.authorization.k8s.io/v1
+kind: ClusterRole
+metadata:
+  name: web-reader
+---
+# Source: web/templates/service.yaml
+apiVersion: v1
+kind: Service
+metadata:
+  name: web
+  labels:
+    app.kubernetes.io/instance: web
+    app.kubernetes.io/name: nginx
+---
+# Source: web/templates/deployment.yaml
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+  namespace: edge
+spec:
+  replicas: 3
+"#;
+
+    #[test]
+    fn builds_inventory_from_manifest() {
+        let resources = inventory_from_manifest(MANIFEST, "apps").unwrap();
+
+        assert_eq!(resources.len(), 3);
+        assert_eq!(resources[0].namespace, None);
+        assert_eq!(resources[1].namespace.as_deref(), Some("apps"));
+        assert_eq!(resources[1].labels["app.kubernetes.io/name"], "nginx");
+        assert_eq!(
+            resources[1].source.as_deref(),
+            Some("web/templates/service.yaml")
+        );
+        assert_eq!(resources[2].namespace.as_deref(), Some("edge"));
+        assert_eq!(resources[2].kubectl_ref(), "deployment.apps/web");
+        assert_eq!(resources[1].kubectl_ref(), "service/web");
+    }
+
+    #[tokio::test]
+    async fn attaches_live_status_per_namespace() {
+        let runner = FixtureRunner::new()
+            .respond("helm get manifest web -n apps", MANIFEST)
+            .respond(
+                "kubectl get clusterrole.rbac.authorization.k8s.io/web-reader service/web -n apps -o json --ignore-not-found",
+                r#"{"kind": "List", "items": [{"kind": "ClusterRole", "metadata": {"name": "web-reader"}}]}"#,
+            )
+            .respond(
+                "kubectl get deployment.apps/web -n edge -o json --ignore-not-found",
+                r#"{"kind": "Deployment", "metadata": {"name": "web"}, "spec": {"replicas": 3}, "status": {"readyReplicas": 2}}"#,
+            );
+
+        let resources = get_release_resources(&runner, "web", "apps", true)
+            .await
+            .unwrap();
+
+        let states: Vec<(ResourceState, &str)> = resources
+            .iter()
+            .map(|r| {
+                let status = r.status.as_ref().unwrap();
+                (status.state, status.message.as_str())
+            })
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                (ResourceState::Ready, "Exists"),
+                (ResourceState::Missing, "Not found in the cluster"),
+                (ResourceState::Progressing, "2/3 ready"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn kubectl_failures_mark_status_unknown() {
+        let runner = FixtureRunner::new()
+            .respond("helm get manifest web -n apps", MANIFEST)
+            .fail(
+                "kubectl get *",
+                "Error from server (Forbidden): deployments.apps is forbidden",
+                1,
+            );
+
+        let resources = get_release_resources(&runner, "web", "apps", true)
+            .await
+            .unwrap();
+
+        assert!(resources
+            .iter()
+            .all(|r| r.status.as_ref().unwrap().state == ResourceState::Unknown));
+        assert!(get_release_resources(&runner, "web", "apps", false)
+            .await
+            .unwrap()
+            .iter()
+            .all(|r| r.status.is_none()));
+    }
+}
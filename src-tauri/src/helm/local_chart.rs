@@ -0,0 +1,392 @@
+//! Local chart module
+//!
+//! Charts under development are used straight from disk, either as a chart
+//! directory or as a packaged `.tgz`. `Chart.yaml`, `values.yaml` and
+//! `values.schema.json` are read without helm, and the schema cache is keyed
+//! on a hash of the chart's contents so that editing the chart invalidates
+//! its cached schema.
+
+use super::catalog::{optional_scalar_string, scalar_string};
+use super::chart_ref::is_oci;
+use super::chart_tree::read_chart;
+use crate::db::connection::DbConnection;
+use crate::db::local_charts::{self, LocalChartEntry};
+use crate::db::schemas;
+use crate::error::RudderError;
+use crate::schema::chart_schema::build_chart_schema;
+use crate::schema::get_schema_for_chart::check_cached_schema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+/// Prefix of the schema cache's repository column for local charts.
+const CACHE_REPO_PREFIX: &str = "local:";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChartMetadata {
+    name: String,
+    #[serde(deserialize_with = "scalar_string")]
+    version: String,
+    #[serde(default, deserialize_with = "optional_scalar_string")]
+    app_version: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+/// A chart read from disk.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalChart {
+    /// Absolute path of the chart directory or archive.
+    pub path: String,
+    pub name: String,
+    pub version: String,
+    pub app_version: Option<String>,
+    pub description: Option<String>,
+    /// SHA-256 of the chart contents.
+    pub content_hash: String,
+    /// Default values from `values.yaml`, with those of the subcharts in
+    /// charts/ nested under their keys.
+    pub values: Value,
+    /// `values.schema.json`, or a schema inferred from `values.yaml` if the
+    /// chart has none.
+    pub schema: Value,
+    /// Whether the schema was inferred.
+    pub schema_inferred: bool,
+}
+
+impl LocalChart {
+    /// Read a chart directory or `.tgz` archive.
+    pub fn load(path: &str) -> Result<Self, RudderError> {
+        let resolved = expand_home(path);
+        let resolved = fs::canonicalize(&resolved).map_err(|e| {
+            RudderError::invalid_input(format!("Chart path '{}' is not readable: {}", path, e))
+        })?;
+        let (tree, content_hash) = read_chart(&resolved)?;
+        let display = resolved.display();
+
+        let chart_yaml = tree.chart_yaml.as_deref().ok_or_else(|| {
+            RudderError::invalid_input(format!(
+                "'{}' is not a chart: Chart.yaml is missing",
+                display
+            ))
+        })?;
+        let metadata: ChartMetadata = serde_yaml::from_str(chart_yaml).map_err(|e| {
+            RudderError::invalid_input(format!("Invalid Chart.yaml in '{}': {}", display, e))
+        })?;
+        if let Some(yaml) = &tree.values_yaml {
+            serde_yaml::from_str::<Value>(yaml).map_err(|e| {
+                RudderError::invalid_values(format!("Invalid values.yaml in '{}': {}", display, e))
+            })?;
+        }
+        // Includes the schemas of the subcharts in charts/
+        let values_schema = build_chart_schema(&tree)
+            .map_err(|e| RudderError::invalid_values(format!("{} in '{}'", e, display)))?;
+
+        Ok(LocalChart {
+            path: resolved.to_string_lossy().into_owned(),
+            name: metadata.name,
+            version: metadata.version,
+            app_version: metadata.app_version,
+            description: metadata.description,
+            content_hash,
+            values: values_schema.defaults,
+            schema: values_schema.schema,
+            schema_inferred: values_schema.inferred,
+        })
+    }
+
+    /// Repository column of the schema cache for these contents.
+    pub fn cache_repo(&self) -> String {
+        cache_repo(&self.content_hash)
+    }
+}
+
+fn cache_repo(content_hash: &str) -> String {
+    format!("{}{}", CACHE_REPO_PREFIX, content_hash)
+}
+
+/// Whether a chart argument is a path on disk rather than a repository chart
+/// or an OCI reference.
+pub fn is_local_chart(chart: &str) -> bool {
+    let path = Path::new(chart);
+    !is_oci(chart)
+        && !chart.contains("://")
+        && (path.is_absolute()
+            || chart.starts_with('.')
+            || chart.starts_with('~')
+            || chart.ends_with(".tgz")
+            || path.exists())
+}
+
+/// The chart argument to pass to helm: local charts are resolved to an
+/// absolute path, other references are returned unchanged.
+pub fn resolve_chart_path(chart: &str) -> String {
+    if !is_local_chart(chart) {
+        return chart.to_string();
+    }
+    // A path that does not exist is left to helm to report
+    let expanded = expand_home(chart);
+    fs::canonicalize(&expanded)
+        .unwrap_or(expanded)
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    let rest = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => rest,
+        _ => return PathBuf::from(path),
+    };
+    match dirs::home_dir() {
+        Some(home) => home.join(rest.trim_start_matches(['/', '\\'])),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Read a local chart and register it as a chart source.
+///
+/// The schema is served from the cache while the chart is unchanged. When
+/// its contents changed since it was last read, the schema cached for the
+/// previous contents is dropped and the schema is read again.
+pub fn register_local_chart(
+    db: &State<DbConnection>,
+    path: &str,
+) -> Result<LocalChart, RudderError> {
+    let chart = LocalChart::load(path)?;
+    refresh_registration(db, chart)
+}
+
+/// Read a local chart to install it, refreshing its registration if it is
+/// registered, so that its cached schema follows the chart on disk.
+pub fn load_local_chart(db: &State<DbConnection>, path: &str) -> Result<LocalChart, RudderError> {
+    let chart = LocalChart::load(path)?;
+    let registered = local_charts::get_local_chart(db, &chart.path)
+        .map_err(RudderError::database)?
+        .is_some();
+    if registered {
+        refresh_registration(db, chart)
+    } else {
+        Ok(chart)
+    }
+}
+
+/// Store the schema of a chart just read from disk and record its contents
+/// hash, replacing the schema cached for its previous contents.
+fn refresh_registration(
+    db: &State<DbConnection>,
+    mut chart: LocalChart,
+) -> Result<LocalChart, RudderError> {
+    let cache_repo = chart.cache_repo();
+
+    let previous = local_charts::get_local_chart(db, &chart.path).map_err(RudderError::database)?;
+    if let Some(previous) = previous.filter(|p| p.content_hash != chart.content_hash) {
+        schemas::delete_chart_schema(
+            db,
+            &previous.chart_name,
+            &previous.chart_version,
+            &self::cache_repo(&previous.content_hash),
+        )
+        .map_err(RudderError::database)?;
+    }
+
+    match check_cached_schema(db, &chart.name, &chart.version, &cache_repo) {
+        Some(Ok(cached)) => {
+            // Only returned for the same contents, so the flag is unchanged
+            chart.schema = serde_json::from_str(&cached).map_err(|e| {
+                RudderError::database(format!("Failed to parse cached schema: {}", e))
+            })?;
+        }
+        _ => schemas::store_chart_schema(
+            db,
+            &chart.name,
+            &chart.version,
+            &cache_repo,
+            None,
+            &chart.schema,
+            chart.schema_inferred,
+            Some(&chart.values),
+        )
+        .map_err(RudderError::database)?,
+    }
+
+    local_charts::upsert_local_chart(
+        db,
+        &LocalChartEntry {
+            path: chart.path.clone(),
+            chart_name: chart.name.clone(),
+            chart_version: chart.version.clone(),
+            content_hash: chart.content_hash.clone(),
+            registered_at: None,
+        },
+    )
+    .map_err(RudderError::database)?;
+
+    Ok(chart)
+}
+
+/// Unregister a local chart and drop the schema cached for it.
+pub fn unregister_local_chart(db: &State<DbConnection>, path: &str) -> Result<(), RudderError> {
+    // The chart may already be gone from disk, so only resolve it if it exists
+    let expanded = expand_home(path);
+    let resolved = fs::canonicalize(&expanded).unwrap_or(expanded);
+    let resolved = resolved.to_string_lossy();
+
+    let Some(entry) =
+        local_charts::get_local_chart(db, &resolved).map_err(RudderError::database)?
+    else {
+        return Err(RudderError::invalid_input(format!(
+            "Chart path '{}' is not registered",
+            path
+        )));
+    };
+    schemas::delete_chart_schema(
+        db,
+        &entry.chart_name,
+        &entry.chart_version,
+        &cache_repo(&entry.content_hash),
+    )
+    .map_err(RudderError::database)?;
+    local_charts::remove_local_chart(db, &entry.path).map_err(RudderError::database)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helm::chart_tree::archive::{gzip, tar_entry};
+    use std::sync::{Arc, Mutex};
+    use tauri::Manager;
+
+    const CHART_YAML: &str = "apiVersion: v2\nname: web\nversion: 0.1.0\nappVersion: 1.16\n";
+    const SCHEMA: &str = r#"{"type":"object","properties":{"replicaCount":{"type":"integer"}}}"#;
+
+    fn write_chart(dir: &Path) {
+        fs::create_dir_all(dir.join("templates")).unwrap();
+        fs::write(dir.join("Chart.yaml"), CHART_YAML).unwrap();
+        fs::write(dir.join("values.yaml"), "replicaCount: 1\n").unwrap();
+        fs::write(dir.join("values.schema.json"), SCHEMA).unwrap();
+        fs::write(dir.join("templates/deployment.yaml"), "kind: Deployment\n").unwrap();
+    }
+
+    fn mock_db() -> tauri::App<tauri::test::MockRuntime> {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::connection::create_tables(&conn).unwrap();
+        let db: DbConnection = Arc::new(Mutex::new(conn));
+        let app = tauri::test::mock_app();
+        app.manage(db);
+        app
+    }
+
+    #[test]
+    fn reads_chart_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        write_chart(dir.path());
+
+        let chart = LocalChart::load(dir.path().to_str().unwrap()).unwrap();
+
+        assert_eq!(chart.name, "web");
+        assert_eq!(chart.version, "0.1.0");
+        assert_eq!(chart.app_version.as_deref(), Some("1.16"));
+        assert_eq!(chart.values, serde_json::json!({"replicaCount": 1}));
+        assert_eq!(
+            chart.schema["properties"]["replicaCount"]["type"],
+            "integer"
+        );
+
+        fs::write(dir.path().join("templates/service.yaml"), "kind: Service\n").unwrap();
+        let edited = LocalChart::load(dir.path().to_str().unwrap()).unwrap();
+        assert_ne!(edited.content_hash, chart.content_hash);
+
+        let missing = LocalChart::load(dir.path().join("templates").to_str().unwrap());
+        assert!(missing
+            .unwrap_err()
+            .message
+            .contains("Chart.yaml is missing"));
+    }
+
+    #[test]
+    fn reads_packaged_charts() {
+        let mut tar = Vec::new();
+        tar_entry(&mut tar, "web/Chart.yaml", CHART_YAML.as_bytes(), b'0');
+        tar_entry(
+            &mut tar,
+            "PaxHeaders/values",
+            b"24 path=web/values.yaml\n",
+            b'x',
+        );
+        tar_entry(&mut tar, "values", b"replicaCount: 3\n", b'0');
+        tar_entry(
+            &mut tar,
+            "web/charts/db/values.yaml",
+            b"ignored: true\n",
+            b'0',
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("web-0.1.0.tgz");
+        fs::write(&archive, gzip(tar)).unwrap();
+
+        let chart = LocalChart::load(archive.to_str().unwrap()).unwrap();
+
+        assert_eq!(chart.name, "web");
+        assert_eq!(chart.values, serde_json::json!({"replicaCount": 3}));
+        assert!(chart.schema_inferred);
+        assert_eq!(chart.schema["properties"]["replicaCount"]["default"], 3);
+        assert!(chart.schema["properties"].get("db").is_none());
+    }
+
+    #[test]
+    fn invalidates_the_cached_schema_when_the_chart_changes() {
+        let app = mock_db();
+        let dir = tempfile::tempdir().unwrap();
+        write_chart(dir.path());
+        let path = dir.path().to_str().unwrap();
+
+        let first = register_local_chart(&app.state(), path).unwrap();
+        fs::write(
+            dir.path().join("values.schema.json"),
+            r#"{"type":"object","properties":{"image":{"type":"string"}}}"#,
+        )
+        .unwrap();
+        let second = register_local_chart(&app.state(), path).unwrap();
+
+        assert_eq!(second.schema["properties"]["image"]["type"], "string");
+        let cached = schemas::list_cached_schemas(&app.state()).unwrap();
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached[0].repo_name, second.cache_repo());
+        assert_ne!(first.cache_repo(), second.cache_repo());
+
+        let registered = local_charts::list_local_charts(&app.state()).unwrap();
+        assert_eq!(registered.len(), 1);
+        assert_eq!(registered[0].content_hash, second.content_hash);
+
+        unregister_local_chart(&app.state(), path).unwrap();
+        assert!(schemas::list_cached_schemas(&app.state())
+            .unwrap()
+            .is_empty());
+        assert!(local_charts::list_local_charts(&app.state())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn resolves_only_local_chart_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let canonical = fs::canonicalize(dir.path()).unwrap();
+
+        assert_eq!(
+            resolve_chart_path(dir.path().to_str().unwrap()),
+            canonical.to_string_lossy()
+        );
+        assert_eq!(resolve_chart_path("./missing-chart"), "./missing-chart");
+        assert_eq!(resolve_chart_path("bitnami/nginx"), "bitnami/nginx");
+        assert_eq!(
+            resolve_chart_path("oci://ghcr.io/acme/charts/web"),
+            "oci://ghcr.io/acme/charts/web"
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Helm manifest module
+//!
+//! Splits the multi-document YAML rendered by helm (`helm get manifest`,
+//! `helm upgrade --dry-run`) into individual Kubernetes resources.
+
+use serde::Serialize;
+use std::fmt;
+
+/// Identity of a Kubernetes object within a release.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceKey {
+    pub kind: String,
+    pub name: String,
+    /// Namespace from the manifest; unset for cluster-scoped objects and for
+    /// objects created in the release namespace.
+    pub namespace: Option<String>,
+}
+
+impl fmt::Display for ResourceKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.namespace {
+            Some(namespace) => write!(f, "{}/{} ({})", self.kind, self.name, namespace),
+            None => write!(f, "{}/{}", self.kind, self.name),
+        }
+    }
+}
+
+/// One document of a rendered manifest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestResource {
+    pub api_version: String,
+    pub key: ResourceKey,
+    /// Template that rendered the resource, from helm's `# Source:` comment.
+    pub source: Option<String>,
+    /// The document as rendered, without the `# Source:` comment.
+    pub yaml: String,
+    /// The document parsed into JSON.
+    pub object: serde_json::Value,
+}
+
+/// Parse a rendered manifest into its resources, skipping empty documents.
+pub fn parse_manifest(manifest: &str) -> Result<Vec<ManifestResource>, String> {
+    split_documents(manifest)
+        .into_iter()
+        .filter_map(|document| parse_document(&document).transpose())
+        .collect()
+}
+
+/// Split a multi-document YAML stream on `---` separator lines.
+fn split_documents(manifest: &str) -> Vec<String> {
+    let mut documents = vec![String::new()];
+    for line in manifest.lines() {
+        if line.trim_end() == "---" {
+            documents.push(String::new());
+        } else if let Some(current) = documents.last_mut() {
+            current.push_str(line);
+            current.push('\n');
+        }
+    }
+    documents
+}
+
+fn parse_document(document: &str) -> Result<Option<ManifestResource>, String> {
+    let mut source = None;
+    let mut yaml = String::new();
+    for line in document.lines() {
+        match line.strip_prefix("# Source: ") {
+            Some(path) if source.is_none() => source = Some(path.trim().to_string()),
+            _ => {
+                yaml.push_str(line);
+                yaml.push('\n');
+            }
+        }
+    }
+
+    let object: serde_json::Value = serde_yaml::from_str(&yaml).map_err(|e| {
+        format!(
+            "Failed to parse manifest document{}: {}",
+            source.as_deref().map(|s| format!(" {}", s)).unwrap_or_default(),
+            e
+        )
+    })?;
+    // Templates that render to nothing (e.g. disabled by a condition)
+    if !object.is_object() {
+        return Ok(None);
+    }
+
+    let text = |value: &serde_json::Value| value.as_str().unwrap_or_default().to_string();
+    Ok(Some(ManifestResource {
+        api_version: text(&object["apiVersion"]),
+        key: ResourceKey {
+            kind: text(&object["kind"]),
+            name: text(&object["metadata"]["name"]),
+            namespace: object["metadata"]["namespace"].as_str().map(String::from),
+        },
+        source,
+        yaml: yaml.trim().to_string() + "\n",
+        object,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"---
+# Source: nginx/templates/serviceaccount.yaml
+apiVersion: v1
+kind: ServiceAccount
+metadata:
+  name: web
+  namespace: apps
+---
+# Source: nginx/templates/disabled.yaml
+---
+# Source: nginx/templates/deployment.yaml
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+spec:
+  replicas: 2
+  template:
+    metadata:
+      annotations:
+        checksum/config: "abc---def"
+"#;
+
+    #[test]
+    fn splits_documents_and_reads_identity() {
+        let resources = parse_manifest(MANIFEST).unwrap();
+
+        assert_eq!(resources.len(), 2);
+        assert_eq!(resources[0].key.to_string(), "ServiceAccount/web (apps)");
+        assert_eq!(
+            resources[0].source.as_deref(),
+            Some("nginx/templates/serviceaccount.yaml")
+        );
+        assert_eq!(resources[1].api_version, "apps/v1");
+        assert_eq!(resources[1].key.to_string(), "Deployment/web");
+        assert_eq!(resources[1].object["spec"]["replicas"], 2);
+        assert!(resources[1].yaml.starts_with("apiVersion: apps/v1\n"));
+        assert!(!resources[1].yaml.contains("# Source"));
+    }
+
+    #[test]
+    fn reports_invalid_documents() {
+        let error = parse_manifest("# Source: x/templates/bad.yaml\nkind: [\n").unwrap_err();
+        assert!(error.contains("x/templates/bad.yaml"));
+    }
+}
@@ -0,0 +1,399 @@
+//! OCI registry module
+//!
+//! Logs in to OCI registries and lists the versions of charts stored in them.
+//! Registries have no index for `helm search`, so tags are read from the
+//! registry's tag list API with `curl`, falling back to the latest version
+//! helm itself resolves for the reference.
+
+use super::catalog::compare_versions;
+use super::chart_ref::{is_valid_registry, ChartRef, OCI_SCHEME};
+use super::install::ChartVersion;
+use crate::error::{ErrorKind, RudderError};
+use crate::runner::command::CommandRunner;
+use serde::Deserialize;
+
+/// Credentials for `helm registry login`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RegistryLogin {
+    /// Registry host, e.g. `ghcr.io` or `localhost:5000`; an `oci://` prefix is accepted.
+    pub registry: String,
+    pub username: String,
+    /// Password or access token, passed on stdin (`--password-stdin`) so
+    /// that it never shows on the command line.
+    pub password: String,
+    /// Allow plain HTTP and self-signed certificates (`--insecure`).
+    pub insecure: bool,
+}
+
+impl RegistryLogin {
+    /// Validate the credentials and convert them to helm arguments.
+    pub fn to_args(&self) -> Result<Vec<String>, RudderError> {
+        let registry = self
+            .registry
+            .trim_start_matches(OCI_SCHEME)
+            .trim_end_matches('/');
+        if !is_valid_registry(registry) {
+            return Err(RudderError::invalid_input(format!(
+                "Invalid registry '{}': expected a host such as ghcr.io or localhost:5000",
+                self.registry
+            )));
+        }
+        if self.username.is_empty() || self.password.is_empty() {
+            return Err(RudderError::invalid_input(
+                "A registry login requires a username and a password",
+            ));
+        }
+
+        let mut args = vec![
+            "registry".to_string(),
+            "login".to_string(),
+            registry.to_string(),
+            format!("--username={}", self.username),
+            "--password-stdin".to_string(),
+        ];
+        if self.insecure {
+            args.push("--insecure".to_string());
+        }
+        Ok(args)
+    }
+}
+
+/// Log in to an OCI registry; helm stores the credentials for later pulls.
+pub async fn registry_login(
+    runner: &dyn CommandRunner,
+    login: &RegistryLogin,
+) -> Result<(), RudderError> {
+    let args = login.to_args()?;
+    let output = runner
+        .run_with_stdin("helm", &args, &login.password)
+        .await
+        .map_err(|e| RudderError::spawn_failed("Helm registry login command failed", e))?;
+
+    if !output.success() {
+        return Err(RudderError::command_failed(
+            "Helm registry login command failed",
+            &output,
+        ));
+    }
+    Ok(())
+}
+
+/// Versions of an OCI chart, newest first.
+pub async fn list_oci_versions(
+    runner: &dyn CommandRunner,
+    chart: &ChartRef,
+) -> Result<Vec<ChartVersion>, RudderError> {
+    let Some((registry, repository)) = chart.oci_location() else {
+        return Err(RudderError::invalid_input(format!(
+            "{} is not an OCI chart reference",
+            chart
+        )));
+    };
+
+    match list_registry_tags(runner, registry, repository).await {
+        Ok(tags) if !tags.is_empty() => Ok(tags_to_versions(tags)),
+        Ok(_) => latest_version(runner, chart).await.map(|v| vec![v]),
+        Err(e) => {
+            // Private registries reject anonymous tag listing; helm uses the stored login
+            println!("Falling back to helm show chart for {}: {}", chart, e);
+            latest_version(runner, chart).await.map(|v| vec![v])
+        }
+    }
+}
+
+/// Chart versions from registry tags. OCI tags cannot contain `+`, so helm
+/// stores build metadata with `_`; tags that are not versions, such as
+/// `latest` or signatures, are skipped.
+fn tags_to_versions(tags: Vec<String>) -> Vec<ChartVersion> {
+    let mut versions: Vec<String> = tags
+        .into_iter()
+        .filter(|tag| {
+            tag.trim_start_matches('v')
+                .starts_with(|c: char| c.is_ascii_digit())
+        })
+        .map(|tag| tag.replace('_', "+"))
+        .collect();
+    versions.sort_by(|a, b| compare_versions(b, a));
+    versions.dedup();
+
+    versions
+        .into_iter()
+        .map(|version| ChartVersion {
+            version,
+            app_version: String::new(),
+            description: String::new(),
+        })
+        .collect()
+}
+
+/// Latest version as resolved by `helm show chart`, which uses stored credentials.
+async fn latest_version(
+    runner: &dyn CommandRunner,
+    chart: &ChartRef,
+) -> Result<ChartVersion, RudderError> {
+    let reference = chart.to_string();
+    let output = runner
+        .run("helm", ["show", "chart", &reference])
+        .await
+        .map_err(|e| RudderError::spawn_failed("Helm show chart command failed", e))?;
+
+    if !output.success() {
+        return Err(RudderError::command_failed(
+            "Helm show chart command failed",
+            &output,
+        ));
+    }
+
+    let metadata: serde_yaml::Value = serde_yaml::from_slice(&output.stdout).map_err(|e| {
+        RudderError::new(
+            ErrorKind::CommandFailed,
+            format!("Failed to parse Chart.yaml of {}: {}", reference, e),
+        )
+    })?;
+    let field = |name: &str| match &metadata[name] {
+        serde_yaml::Value::String(s) => s.clone(),
+        serde_yaml::Value::Number(n) => n.to_string(),
+        _ => String::new(),
+    };
+
+    Ok(ChartVersion {
+        version: field("version"),
+        app_version: field("appVersion"),
+        description: field("description"),
+    })
+}
+
+/// Tags of a registry repository via the OCI distribution API, requesting an
+/// anonymous bearer token if the registry asks for one.
+async fn list_registry_tags(
+    runner: &dyn CommandRunner,
+    registry: &str,
+    repository: &str,
+) -> Result<Vec<String>, String> {
+    let url = format!("https://{}/v2/{}/tags/list", registry, repository);
+    let response = curl(runner, &["-sS", "-i", "--proto", "=https", &url]).await?;
+    let (status, challenge, body) = parse_http_response(&response)?;
+
+    let body = match status {
+        200 => body,
+        401 => {
+            let token_url = challenge
+                .as_deref()
+                .and_then(|challenge| bearer_token_url(challenge, repository))
+                .ok_or("Registry requires authentication")?;
+            let token: serde_json::Value = serde_json::from_str(
+                &curl(runner, &["-sS", "-f", "--proto", "=https", &token_url]).await?,
+            )
+            .map_err(|e| format!("Invalid token response: {}", e))?;
+            let token = token["token"]
+                .as_str()
+                .or_else(|| token["access_token"].as_str())
+                .ok_or("Token response contained no token")?;
+
+            let authorization = format!("Authorization: Bearer {}", token);
+            curl(
+                runner,
+                &["-sS", "-f", "--proto", "=https", "-H", &authorization, &url],
+            )
+            .await?
+        }
+        status => return Err(format!("Registry answered with HTTP {}", status)),
+    };
+
+    #[derive(Deserialize)]
+    struct TagList {
+        #[serde(default)]
+        tags: Option<Vec<String>>,
+    }
+    let tags: TagList =
+        serde_json::from_str(&body).map_err(|e| format!("Invalid tag list: {}", e))?;
+    Ok(tags.tags.unwrap_or_default())
+}
+
+async fn curl(runner: &dyn CommandRunner, args: &[&str]) -> Result<String, String> {
+    let output = runner.run("curl", args).await?;
+    if !output.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Status code, `WWW-Authenticate` header and body of a response printed by `curl -i`.
+fn parse_http_response(response: &str) -> Result<(u16, Option<String>, String), String> {
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .or_else(|| response.split_once("\n\n"))
+        .unwrap_or((response, ""));
+    let mut lines = head.lines();
+
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or("Registry returned no HTTP status")?;
+    let challenge = lines.find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("www-authenticate")
+            .then(|| value.trim().to_string())
+    });
+
+    Ok((status, challenge, body.to_string()))
+}
+
+/// Token endpoint URL from a `Bearer realm="...",service="...",scope="..."` challenge.
+fn bearer_token_url(challenge: &str, repository: &str) -> Option<String> {
+    let params = challenge.strip_prefix("Bearer ")?;
+    let mut realm = None;
+    let mut query = Vec::new();
+    let mut has_scope = false;
+
+    // key="value" pairs; values may contain commas, e.g. multiple scopes
+    let mut rest = params.trim();
+    while !rest.is_empty() {
+        let (key, after) = rest.split_once('=')?;
+        let after = after.strip_prefix('"')?;
+        let (value, after) = after.split_once('"')?;
+        match key.trim() {
+            "realm" => realm = Some(value.to_string()),
+            key => {
+                has_scope |= key == "scope";
+                query.push(format!("{}={}", key, percent_encode(value)));
+            }
+        }
+        rest = after.trim_start_matches(',').trim();
+    }
+    if !has_scope {
+        let scope = format!("repository:{}:pull", repository);
+        query.push(format!("scope={}", percent_encode(&scope)));
+    }
+
+    let realm = realm.filter(|realm| realm.starts_with("https://"))?;
+    Some(format!("{}?{}", realm, query.join("&")))
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::fixture::FixtureRunner;
+
+    const TAGS_URL: &str = "https://registry-1.docker.io/v2/bitnamicharts/nginx/tags/list";
+
+    fn chart() -> ChartRef {
+        ChartRef::parse("oci://registry-1.docker.io/bitnamicharts/nginx").unwrap()
+    }
+
+    #[tokio::test]
+    async fn lists_tags_with_an_anonymous_token() {
+        let runner = FixtureRunner::new()
+            .respond(
+                &format!("curl -sS -i --proto =https {}", TAGS_URL),
+                "HTTP/2 401\r\ncontent-type: application/json\r\nwww-authenticate: Bearer realm=\"https://auth.docker.io/token\",service=\"registry.docker.io\",scope=\"repository:bitnamicharts/nginx:pull\"\r\n\r\n{\"errors\":[]}",
+            )
+            .respond(
+                "curl -sS -f --proto =https https://auth.docker.io/token?service=registry.docker.io&scope=repository%3Abitnamicharts%2Fnginx%3Apull",
+                r#"{"token":"abc"}"#,
+            )
+            .respond(
+                &format!("curl -sS -f --proto =https -H Authorization: Bearer abc {}", TAGS_URL),
+                r#"{"name":"bitnamicharts/nginx","tags":["15.3.5","15.4.0","latest","sha256-1f2e.sig","16.0.0-rc.1","15.4.0_build.7"]}"#,
+            );
+
+        let versions = list_oci_versions(&runner, &chart()).await.unwrap();
+
+        let versions: Vec<&str> = versions.iter().map(|v| v.version.as_str()).collect();
+        assert_eq!(
+            versions,
+            vec!["16.0.0-rc.1", "15.4.0", "15.4.0+build.7", "15.3.5"]
+        );
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_the_latest_version_helm_resolves() {
+        let runner = FixtureRunner::new()
+            .respond(
+                &format!("curl -sS -i --proto =https {}", TAGS_URL),
+                "HTTP/1.1 403 Forbidden\r\n\r\n",
+            )
+            .respond(
+                "helm show chart oci://registry-1.docker.io/bitnamicharts/nginx",
+                "apiVersion: v2\nname: nginx\nversion: 15.4.0\nappVersion: 1.25.3\ndescription: NGINX\n",
+            );
+
+        let versions = list_oci_versions(&runner, &chart()).await.unwrap();
+
+        assert_eq!(
+            versions,
+            vec![ChartVersion {
+                version: "15.4.0".into(),
+                app_version: "1.25.3".into(),
+                description: "NGINX".into(),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn passes_the_registry_token_on_stdin() {
+        let runner = FixtureRunner::new().respond(
+            "helm registry login ghcr.io --username=ci --password-stdin",
+            "Login Succeeded\n",
+        );
+        let login = RegistryLogin {
+            registry: "ghcr.io".into(),
+            username: "ci".into(),
+            password: "ghp_token".into(),
+            insecure: false,
+        };
+
+        registry_login(&runner, &login).await.unwrap();
+
+        assert_eq!(runner.inputs(), vec!["ghp_token"]);
+        assert!(runner.calls().iter().all(|call| !call.contains("ghp_token")));
+    }
+
+    #[test]
+    fn builds_registry_login_arguments() {
+        let login = RegistryLogin {
+            registry: "oci://ghcr.io/".into(),
+            username: "ci".into(),
+            password: "token".into(),
+            insecure: false,
+        };
+
+        assert_eq!(
+            login.to_args().unwrap(),
+            vec![
+                "registry",
+                "login",
+                "ghcr.io",
+                "--username=ci",
+                "--password-stdin"
+            ]
+        );
+        assert!(RegistryLogin {
+            registry: "ghcr.io/org".into(),
+            ..login.clone()
+        }
+        .to_args()
+        .is_err());
+        assert!(RegistryLogin {
+            password: String::new(),
+            ..login
+        }
+        .to_args()
+        .is_err());
+    }
+}
@@ -0,0 +1,247 @@
+//! Helm upgrade preview module
+//!
+//! helm-diff style preview of an upgrade: the dry-run manifest is compared
+//! object by object with the manifest of the deployed release.
+
+use super::diff::{diff_resources, hide_secret_values, DiffSummary, ResourceDiff, SECRET_HIDDEN};
+use super::manifest::{parse_manifest, ManifestResource};
+use super::upgrade::{dry_run, UpgradeOptions};
+use crate::error::{ErrorKind, RudderError};
+use crate::runner::command::CommandRunner;
+use serde::Serialize;
+
+/// Kubernetes objects an upgrade would add, remove or change.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpgradePreview {
+    pub summary: DiffSummary,
+    pub resources: Vec<ResourceDiff>,
+    /// Manifest of the deployed release, empty for a new release, with the
+    /// values of its Secrets hidden.
+    pub current_manifest: String,
+    /// Manifest the upgrade would apply, without hooks and notes, and with
+    /// the values of its Secrets hidden.
+    pub manifest: String,
+}
+
+/// Extract the `MANIFEST:` section from the output of `helm upgrade --dry-run`.
+pub fn dry_run_manifest(output: &str) -> Option<String> {
+    let mut lines = output.lines().skip_while(|line| *line != "MANIFEST:");
+    lines.next()?;
+
+    let manifest: Vec<&str> = lines.take_while(|line| *line != "NOTES:").collect();
+    Some(manifest.join("\n").trim_end().to_string() + "\n")
+}
+
+/// Render the upgrade and diff it against the deployed release, which counts
+/// as empty when the release does not exist yet.
+pub async fn preview_upgrade(
+    runner: &dyn CommandRunner,
+    release_name: &str,
+    chart_path: &str,
+    values_json: &str,
+    options: &UpgradeOptions,
+) -> Result<UpgradePreview, RudderError> {
+    let output = dry_run(runner, release_name, chart_path, values_json, options).await?;
+    let manifest = dry_run_manifest(&output).ok_or_else(|| {
+        RudderError::new(
+            ErrorKind::CommandFailed,
+            "Helm dry-run output did not contain a MANIFEST section",
+        )
+    })?;
+
+    let current = match current_manifest(runner, release_name, options.namespace.as_deref()).await {
+        Ok(current) => current,
+        Err(e) if e.kind == ErrorKind::ReleaseNotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+
+    let parse = |manifest: &str| {
+        parse_manifest(manifest).map_err(|e| RudderError::new(ErrorKind::CommandFailed, e))
+    };
+    let current = parse(&current)?;
+    let upgraded = parse(&manifest)?;
+    let resources = diff_resources(&current, &upgraded, "current", "upgrade");
+
+    Ok(UpgradePreview {
+        summary: DiffSummary::of(&resources),
+        resources,
+        current_manifest: hidden_secrets_manifest(&current),
+        manifest: hidden_secrets_manifest(&upgraded),
+    })
+}
+
+/// Manifest of the resources, with the values of Secrets hidden.
+fn hidden_secrets_manifest(resources: &[ManifestResource]) -> String {
+    resources
+        .iter()
+        .map(|resource| {
+            let resource = hide_secret_values(resource, None, SECRET_HIDDEN);
+            let source = resource
+                .source
+                .as_deref()
+                .map(|source| format!("# Source: {}\n", source))
+                .unwrap_or_default();
+            format!("---\n{}{}", source, resource.yaml)
+        })
+        .collect()
+}
+
+async fn current_manifest(
+    runner: &dyn CommandRunner,
+    release_name: &str,
+    namespace: Option<&str>,
+) -> Result<String, RudderError> {
+    let mut args = vec!["get", "manifest", release_name];
+    if let Some(namespace) = namespace {
+        args.extend(["-n", namespace]);
+    }
+
+    let output = runner
+        .run("helm", args)
+        .await
+        .map_err(|e| RudderError::spawn_failed("Helm get manifest command failed", e))?;
+
+    if !output.success() {
+        return Err(RudderError::command_failed("Helm get manifest command failed", &output));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helm::diff::DiffKind;
+    use crate::runner::fixture::FixtureRunner;
+
+    const DRY_RUN: &str = r#"Release "web" has been upgraded. Happy Helming!
+NAME: web
+LAST DEPLOYED: Wed May  1 10:00:00 2024
+NAMESPACE: apps
+STATUS: pending-upgrade
+REVISION: 4
+HOOKS:
+---
+# Source: nginx/templates/tests/test-connection.yaml
+apiVersion: v1
+kind: Pod
+metadata:
+  name: web-test
+MANIFEST:
+---
+# Source: nginx/templates/service.yaml
+apiVersion: v1
+kind: Service
+metadata:
+  name: web
+spec:
+  ports:
+    - port: 80
+---
+# Source: nginx/templates/deployment.yaml
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+spec:
+  replicas: 3
+
+NOTES:
+Get the application URL by running these commands:
+"#;
+
+    const CURRENT: &str = r#"---
+# Source: nginx/templates/deployment.yaml
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+spec:
+  replicas: 1
+---
+# Source: nginx/templates/configmap.yaml
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: web
+"#;
+
+    fn options() -> UpgradeOptions {
+        UpgradeOptions {
+            namespace: Some("apps".into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn extracts_manifest_without_hooks_or_notes() {
+        let manifest = dry_run_manifest(DRY_RUN).unwrap();
+
+        assert!(manifest.starts_with("---\n# Source: nginx/templates/service.yaml"));
+        assert!(manifest.ends_with("  replicas: 3\n"));
+        assert!(!manifest.contains("web-test"));
+        assert_eq!(dry_run_manifest("NAME: web\n"), None);
+    }
+
+    #[tokio::test]
+    async fn previews_changes_against_the_deployed_release() {
+        let runner = FixtureRunner::new()
+            .respond("helm upgrade --install web bitnami/nginx -n apps --dry-run -f *", DRY_RUN)
+            .respond("helm get manifest web -n apps", CURRENT);
+
+        let preview = preview_upgrade(&runner, "web", "bitnami/nginx", "{}", &options())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            preview.summary,
+            DiffSummary { added: 1, removed: 1, changed: 1 }
+        );
+        let deployment = preview
+            .resources
+            .iter()
+            .find(|r| r.key.kind == "Deployment")
+            .unwrap();
+        assert_eq!(deployment.change, DiffKind::Changed);
+        assert_eq!(deployment.changes[0].path, "spec.replicas");
+        assert_eq!(deployment.changes[0].new, Some(serde_json::json!(3)));
+        assert!(preview.current_manifest.contains("  replicas: 1\n"));
+    }
+
+    #[tokio::test]
+    async fn treats_a_new_release_as_all_added() {
+        let runner = FixtureRunner::new()
+            .respond("helm upgrade --install web bitnami/nginx -n apps --dry-run -f *", DRY_RUN)
+            .fail("helm get manifest web -n apps", "Error: release: not found", 1);
+
+        let preview = preview_upgrade(&runner, "web", "bitnami/nginx", "{}", &options())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            preview.summary,
+            DiffSummary { added: 2, removed: 0, changed: 0 }
+        );
+        assert_eq!(preview.current_manifest, "");
+    }
+
+    #[tokio::test]
+    async fn hides_secret_values_in_the_deployed_manifest() {
+        let current = format!(
+            "{}---\n# Source: nginx/templates/secret.yaml\napiVersion: v1\nkind: Secret\nmetadata:\n  name: web\ndata:\n  password: aHVudGVyMg==\n",
+            CURRENT
+        );
+        let runner = FixtureRunner::new()
+            .respond("helm upgrade --install web bitnami/nginx -n apps --dry-run -f *", DRY_RUN)
+            .respond("helm get manifest web -n apps", &current);
+
+        let preview = preview_upgrade(&runner, "web", "bitnami/nginx", "{}", &options())
+            .await
+            .unwrap();
+
+        assert!(preview.current_manifest.contains("kind: Secret"));
+        assert!(preview.current_manifest.contains(SECRET_HIDDEN));
+        assert!(!preview.current_manifest.contains("aHVudGVyMg=="));
+    }
+}
@@ -0,0 +1,336 @@
+//! Helm release module
+//!
+//! Typed model of the releases reported by `helm ls`, with the filtering and
+//! sorting the release list needs on clusters with hundreds of releases.
+
+use crate::error::{ErrorKind, RudderError};
+use crate::runner::command::CommandRunner;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// Status of a release, as reported by helm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReleaseStatus {
+    Deployed,
+    Failed,
+    PendingInstall,
+    PendingUpgrade,
+    PendingRollback,
+    Uninstalling,
+    Uninstalled,
+    Superseded,
+    #[serde(other)]
+    Unknown,
+}
+
+impl ReleaseStatus {
+    pub fn is_pending(self) -> bool {
+        matches!(
+            self,
+            ReleaseStatus::PendingInstall | ReleaseStatus::PendingUpgrade | ReleaseStatus::PendingRollback
+        )
+    }
+
+    /// `helm ls` flag selecting releases in this status.
+    fn list_flag(self) -> &'static str {
+        match self {
+            ReleaseStatus::Deployed => "--deployed",
+            ReleaseStatus::Failed => "--failed",
+            ReleaseStatus::Uninstalling => "--uninstalling",
+            ReleaseStatus::Uninstalled => "--uninstalled",
+            ReleaseStatus::Superseded => "--superseded",
+            s if s.is_pending() => "--pending",
+            _ => "--all",
+        }
+    }
+}
+
+/// A Helm release, built from one entry of `helm ls -o json`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HelmRelease {
+    pub name: String,
+    pub namespace: String,
+    pub revision: u32,
+    /// Time of the last deployment, `None` if helm's timestamp could not be parsed.
+    pub updated: Option<DateTime<Utc>>,
+    pub status: ReleaseStatus,
+    /// Chart reference as printed by helm, e.g. `nginx-15.4.0`.
+    pub chart: String,
+    pub chart_name: String,
+    pub chart_version: String,
+    pub app_version: String,
+}
+
+/// Entry of `helm ls -o json`; every field is a string.
+#[derive(Deserialize)]
+struct RawRelease {
+    name: String,
+    namespace: String,
+    revision: String,
+    updated: String,
+    status: ReleaseStatus,
+    chart: String,
+    #[serde(default)]
+    app_version: String,
+}
+
+impl From<RawRelease> for HelmRelease {
+    fn from(raw: RawRelease) -> Self {
+        let (chart_name, chart_version) = split_chart(&raw.chart);
+        Self {
+            revision: raw.revision.parse().unwrap_or_default(),
+            updated: parse_helm_time(&raw.updated),
+            chart_name: chart_name.to_string(),
+            chart_version: chart_version.to_string(),
+            name: raw.name,
+            namespace: raw.namespace,
+            status: raw.status,
+            chart: raw.chart,
+            app_version: raw.app_version,
+        }
+    }
+}
+
+/// Split `name-version` as printed by helm, e.g. `kube-prometheus-stack-55.5.0`.
+///
+/// The version starts at the first dash followed by something version-like,
+/// so dashes in both the chart name and pre-release versions are kept.
+pub fn split_chart(chart: &str) -> (&str, &str) {
+    chart
+        .match_indices('-')
+        .map(|(i, _)| (&chart[..i], &chart[i + 1..]))
+        .find(|(_, version)| {
+            let digits = version.strip_prefix('v').unwrap_or(version);
+            let major_len = digits.chars().take_while(char::is_ascii_digit).count();
+            major_len > 0 && digits[major_len..].starts_with('.')
+        })
+        .unwrap_or((chart, ""))
+}
+
+/// Parse helm's Go-formatted timestamp, e.g. `2024-05-01 10:00:00.123456789 +0000 UTC`.
+pub fn parse_helm_time(updated: &str) -> Option<DateTime<Utc>> {
+    // Drop the trailing zone abbreviation, the offset carries the same information
+    let trimmed = match updated.trim().rsplit_once(' ') {
+        Some((rest, zone)) if zone.chars().all(|c| c.is_ascii_alphabetic()) => rest,
+        _ => updated.trim(),
+    };
+
+    DateTime::parse_from_str(trimmed, "%Y-%m-%d %H:%M:%S%.f %z")
+        .or_else(|_| DateTime::parse_from_rfc3339(trimmed))
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// Field to sort the release list by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReleaseSortField {
+    #[default]
+    Name,
+    Namespace,
+    Status,
+    Updated,
+    Chart,
+    Revision,
+}
+
+/// Filters, sorting and limit applied to the release list.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReleaseQuery {
+    /// Only list releases in this namespace.
+    pub namespace: Option<String>,
+    pub status: Option<ReleaseStatus>,
+    /// Exact chart name, e.g. `nginx`.
+    pub chart: Option<String>,
+    /// Case-insensitive substring of the release name.
+    pub name: Option<String>,
+    pub sort_by: ReleaseSortField,
+    pub descending: bool,
+    /// Maximum number of releases to return after sorting.
+    pub limit: Option<usize>,
+}
+
+impl ReleaseQuery {
+    /// `helm ls` arguments; filters helm supports itself are pushed down.
+    fn helm_args(&self) -> Vec<String> {
+        let mut args = vec!["ls".to_string()];
+        match &self.namespace {
+            Some(namespace) => args.extend(["-n".to_string(), namespace.clone()]),
+            None => args.push("-A".to_string()),
+        }
+        // helm ls only shows deployed and failed releases by default
+        if let Some(status) = self.status {
+            args.push(status.list_flag().to_string());
+        }
+        // Without --max 0 helm stops at 256 releases
+        args.extend(["--max", "0", "-o", "json"].map(String::from));
+        args
+    }
+
+    pub fn matches(&self, release: &HelmRelease) -> bool {
+        self.namespace.as_ref().is_none_or(|ns| &release.namespace == ns)
+            && self.status.is_none_or(|status| release.status == status)
+            && self
+                .chart
+                .as_ref()
+                .is_none_or(|chart| release.chart_name.eq_ignore_ascii_case(chart))
+            && self
+                .name
+                .as_ref()
+                .is_none_or(|name| release.name.to_lowercase().contains(&name.to_lowercase()))
+    }
+
+    fn compare(&self, a: &HelmRelease, b: &HelmRelease) -> Ordering {
+        let ordering = match self.sort_by {
+            ReleaseSortField::Name => a.name.cmp(&b.name),
+            ReleaseSortField::Namespace => a.namespace.cmp(&b.namespace),
+            ReleaseSortField::Status => a.status.cmp(&b.status),
+            ReleaseSortField::Updated => a.updated.cmp(&b.updated),
+            ReleaseSortField::Chart => a.chart_name.cmp(&b.chart_name),
+            ReleaseSortField::Revision => a.revision.cmp(&b.revision),
+        }
+        .then_with(|| a.namespace.cmp(&b.namespace))
+        .then_with(|| a.name.cmp(&b.name));
+
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    /// Filter, sort and truncate releases.
+    pub fn apply(&self, releases: Vec<HelmRelease>) -> Vec<HelmRelease> {
+        let mut releases: Vec<HelmRelease> =
+            releases.into_iter().filter(|r| self.matches(r)).collect();
+        releases.sort_by(|a, b| self.compare(a, b));
+        if let Some(limit) = self.limit {
+            releases.truncate(limit);
+        }
+        releases
+    }
+}
+
+/// Parse the output of `helm ls -o json`.
+pub fn parse_releases(json: &str) -> Result<Vec<HelmRelease>, RudderError> {
+    // helm prints nothing at all when there are no releases in some versions
+    if json.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let raw: Vec<RawRelease> = serde_json::from_str(json).map_err(|e| {
+        RudderError::new(ErrorKind::CommandFailed, format!("Failed to parse helm releases: {}", e))
+    })?;
+    Ok(raw.into_iter().map(HelmRelease::from).collect())
+}
+
+/// List the releases matching `query`.
+pub async fn list_releases(
+    runner: &dyn CommandRunner,
+    query: &ReleaseQuery,
+) -> Result<Vec<HelmRelease>, RudderError> {
+    let output = runner
+        .run("helm", query.helm_args())
+        .await
+        .map_err(|e| RudderError::spawn_failed("Helm command failed", e))?;
+
+    if !output.success() {
+        return Err(RudderError::command_failed("Helm command failed", &output));
+    }
+
+    let releases = parse_releases(&String::from_utf8_lossy(&output.stdout))?;
+    Ok(query.apply(releases))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RELEASES: &str = r#"[
+        {"name":"web","namespace":"apps","revision":"3","updated":"2024-05-01 10:00:00.123456789 +0000 UTC","status":"deployed","chart":"nginx-15.4.0","app_version":"1.25.3"},
+        {"name":"metrics","namespace":"monitoring","revision":"12","updated":"2024-06-11 08:30:00.5 +0200 CEST","status":"failed","chart":"kube-prometheus-stack-55.5.0","app_version":"v0.70.0"},
+        {"name":"web-canary","namespace":"apps","revision":"1","updated":"2024-04-01 09:00:00 +0000 UTC","status":"pending-upgrade","chart":"nginx-16.0.0-rc.1","app_version":"1.25.4"}
+    ]"#;
+
+    #[test]
+    fn parses_helm_ls_output() {
+        let releases = parse_releases(RELEASES).unwrap();
+
+        assert_eq!(releases[0].revision, 3);
+        assert_eq!(releases[0].chart_name, "nginx");
+        assert_eq!(releases[0].chart_version, "15.4.0");
+        assert_eq!(
+            releases[0].updated.unwrap().to_rfc3339(),
+            "2024-05-01T10:00:00.123456789+00:00"
+        );
+        assert_eq!(releases[1].status, ReleaseStatus::Failed);
+        assert_eq!(releases[1].chart_name, "kube-prometheus-stack");
+        assert_eq!(
+            releases[1].updated.unwrap().to_rfc3339(),
+            "2024-06-11T06:30:00.500+00:00"
+        );
+        assert_eq!(releases[2].status, ReleaseStatus::PendingUpgrade);
+        assert_eq!(releases[2].chart_version, "16.0.0-rc.1");
+        assert!(parse_releases("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn splits_chart_names_and_versions() {
+        assert_eq!(split_chart("cert-manager-v1.13.0"), ("cert-manager", "v1.13.0"));
+        assert_eq!(split_chart("redis-18.1.2"), ("redis", "18.1.2"));
+        assert_eq!(split_chart("my-chart"), ("my-chart", ""));
+        assert_eq!(split_chart("app-2fa-0.1.0"), ("app-2fa", "0.1.0"));
+    }
+
+    #[test]
+    fn filters_sorts_and_limits() {
+        let releases = parse_releases(RELEASES).unwrap();
+
+        let query = ReleaseQuery {
+            chart: Some("NGINX".into()),
+            sort_by: ReleaseSortField::Updated,
+            descending: true,
+            ..Default::default()
+        };
+        let names: Vec<_> = query.apply(releases.clone()).into_iter().map(|r| r.name).collect();
+        assert_eq!(names, vec!["web", "web-canary"]);
+
+        let query = ReleaseQuery {
+            name: Some("CANARY".into()),
+            ..Default::default()
+        };
+        assert_eq!(query.apply(releases.clone())[0].name, "web-canary");
+
+        let query = ReleaseQuery {
+            sort_by: ReleaseSortField::Revision,
+            descending: true,
+            limit: Some(1),
+            ..Default::default()
+        };
+        let top = query.apply(releases);
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].name, "metrics");
+    }
+
+    #[test]
+    fn pushes_namespace_and_status_down_to_helm() {
+        let query = ReleaseQuery {
+            namespace: Some("apps".into()),
+            status: Some(ReleaseStatus::PendingInstall),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            query.helm_args(),
+            vec!["ls", "-n", "apps", "--pending", "--max", "0", "-o", "json"]
+        );
+        assert_eq!(
+            ReleaseQuery::default().helm_args(),
+            vec!["ls", "-A", "--max", "0", "-o", "json"]
+        );
+    }
+}
@@ -0,0 +1,323 @@
+//! Release test module
+//!
+//! Runs a release's test hooks with `helm test` and reports one result per
+//! test pod. Phases and timings come from the hooks recorded in
+//! `helm status -o json`, logs from the `POD LOGS:` sections that
+//! `helm test --logs` prints.
+
+use super::status::{get_release_status, HookPhase, ReleaseHook};
+use super::upgrade::{parse_duration_secs, validate_namespace, validate_release_name};
+use crate::error::{ErrorKind, RudderError};
+use crate::runner::command::CommandRunner;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const POD_LOGS_MARKER: &str = "POD LOGS: ";
+
+/// Allowed difference between the local and the cluster clock when telling
+/// the hooks of this run from those of earlier runs.
+const CLOCK_SKEW: TimeDelta = TimeDelta::seconds(10);
+
+/// A `helm test` run requested by the frontend.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReleaseTestRequest {
+    pub release_name: String,
+    pub namespace: String,
+    /// How long helm waits for each test pod, as a Go duration (`--timeout`).
+    pub timeout: Option<String>,
+    /// Collect the logs of the test pods (`--logs`).
+    pub logs: bool,
+}
+
+impl ReleaseTestRequest {
+    /// Validate the request and convert it to `helm test` arguments.
+    pub fn to_args(&self) -> Result<Vec<String>, RudderError> {
+        validate_release_name(&self.release_name)?;
+        validate_namespace(&self.namespace)?;
+
+        let mut args = vec![
+            "test".to_string(),
+            self.release_name.clone(),
+            "-n".to_string(),
+            self.namespace.clone(),
+        ];
+        if let Some(timeout) = &self.timeout {
+            parse_duration_secs(timeout)?;
+            args.push(format!("--timeout={}", timeout));
+        }
+        if self.logs {
+            args.push("--logs".to_string());
+        }
+        Ok(args)
+    }
+}
+
+/// Result of one test pod.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestPodResult {
+    pub name: String,
+    /// `Unknown`, without timings, for a test that did not run this time.
+    pub phase: HookPhase,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub duration_secs: Option<f64>,
+    /// Pod logs, if they were requested.
+    pub logs: Option<String>,
+}
+
+/// One `helm test` run of a release.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseTestRun {
+    /// ID in the test run history, `None` until stored.
+    pub id: Option<i64>,
+    pub release_name: String,
+    pub namespace: String,
+    /// Release revision the tests ran against.
+    pub revision: Option<u32>,
+    /// Whether helm succeeded and every test pod passed.
+    pub success: bool,
+    pub started_at: DateTime<Utc>,
+    pub duration_secs: f64,
+    pub results: Vec<TestPodResult>,
+    /// helm's error output if the run failed.
+    pub error: Option<String>,
+}
+
+/// Run the test hooks of a release.
+///
+/// A failing test is reported in the returned run rather than as an error;
+/// errors are returned only when the tests could not run at all.
+pub async fn run_release_tests(
+    runner: &dyn CommandRunner,
+    request: &ReleaseTestRequest,
+) -> Result<ReleaseTestRun, RudderError> {
+    let args = request.to_args()?;
+    let started_at = Utc::now();
+
+    let output = runner
+        .run("helm", &args)
+        .await
+        .map_err(|e| RudderError::spawn_failed("Helm test command failed", e))?;
+    let duration_secs = (Utc::now() - started_at).num_milliseconds() as f64 / 1000.0;
+
+    let error = if output.success() {
+        None
+    } else {
+        let error = RudderError::command_failed("Helm test command failed", &output);
+        // Failed and timed out tests are still recorded on the release's hooks
+        if !matches!(error.kind, ErrorKind::CommandFailed | ErrorKind::Timeout) {
+            return Err(error);
+        }
+        Some(error)
+    };
+
+    let status = match get_release_status(runner, &request.release_name, &request.namespace).await {
+        Ok(status) => status,
+        Err(status_error) => return Err(error.unwrap_or(status_error)),
+    };
+
+    let mut logs = parse_pod_logs(&String::from_utf8_lossy(&output.stdout));
+    let results: Vec<TestPodResult> = status
+        .hooks
+        .into_iter()
+        .filter(ReleaseHook::is_test)
+        .map(|hook| {
+            // Hooks keep their last run, which may be from an earlier `helm test`
+            let ran = hook
+                .started_at
+                .is_some_and(|hook_started| hook_started >= started_at - CLOCK_SKEW);
+            if !ran {
+                return TestPodResult {
+                    name: hook.name,
+                    phase: HookPhase::Unknown,
+                    started_at: None,
+                    completed_at: None,
+                    duration_secs: None,
+                    logs: None,
+                };
+            }
+            TestPodResult {
+                logs: logs.remove(&hook.name),
+                duration_secs: hook.duration_secs(),
+                name: hook.name,
+                phase: hook.phase,
+                started_at: hook.started_at,
+                completed_at: hook.completed_at,
+            }
+        })
+        .collect();
+
+    Ok(ReleaseTestRun {
+        id: None,
+        release_name: request.release_name.clone(),
+        namespace: request.namespace.clone(),
+        revision: Some(status.revision),
+        success: error.is_none() && results.iter().all(|r| r.phase == HookPhase::Succeeded),
+        started_at,
+        duration_secs,
+        results,
+        // The full output lists every failed pod, the message only the first line
+        error: error.map(|e| e.stderr.unwrap_or(e.message)),
+    })
+}
+
+/// Split the output of `helm test --logs` into the logs of each pod.
+fn parse_pod_logs(output: &str) -> HashMap<String, String> {
+    let mut logs = HashMap::new();
+    let mut current: Option<(String, Vec<&str>)> = None;
+    for line in output.lines() {
+        if let Some(pod) = line.strip_prefix(POD_LOGS_MARKER) {
+            if let Some((pod, lines)) = current.take() {
+                logs.insert(pod, lines.join("\n").trim_end().to_string());
+            }
+            current = Some((pod.trim().to_string(), Vec::new()));
+        } else if let Some((_, lines)) = current.as_mut() {
+            lines.push(line);
+        }
+    }
+    if let Some((pod, lines)) = current {
+        logs.insert(pod, lines.join("\n").trim_end().to_string());
+    }
+    logs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::command::CommandOutput;
+    use crate::runner::fixture::FixtureRunner;
+
+    /// `helm status` with test hooks that last ran from `started` on.
+    fn status(started: DateTime<Utc>) -> String {
+        let at = |millis: i64| (started + TimeDelta::milliseconds(millis)).to_rfc3339();
+        format!(
+            r#"{{
+                "name": "web",
+                "version": 4,
+                "info": {{"status": "deployed"}},
+                "hooks": [
+                    {{"name": "web-migrate", "events": ["pre-upgrade"], "last_run": {{"started_at": "{}", "completed_at": "{}", "phase": "Succeeded"}}}},
+                    {{"name": "web-test-connection", "events": ["test"], "last_run": {{"started_at": "{}", "completed_at": "{}", "phase": "Succeeded"}}}},
+                    {{"name": "web-test-api", "events": ["test"], "last_run": {{"started_at": "{}", "completed_at": "{}", "phase": "Failed"}}}},
+                    {{"name": "web-test-legacy", "events": ["test-success"], "last_run": {{"started_at": "0001-01-01T00:00:00Z", "completed_at": "0001-01-01T00:00:00Z", "phase": ""}}}}
+                ]
+            }}"#,
+            at(-60_000),
+            at(-50_000),
+            at(0),
+            at(4_500),
+            at(5_000),
+            at(35_000)
+        )
+    }
+
+    const TEST_OUTPUT: &str = "NAME: web\nSTATUS: deployed\nREVISION: 4\nTEST SUITE:     web-test-connection\nPhase:          Succeeded\n\nPOD LOGS: web-test-connection\nConnecting to web:80\nOK\n\nPOD LOGS: web-test-api\ncurl: (7) Failed to connect\n";
+
+    fn request() -> ReleaseTestRequest {
+        ReleaseTestRequest {
+            release_name: "web".into(),
+            namespace: "apps".into(),
+            timeout: Some("2m".into()),
+            logs: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_each_test_pod_of_a_failed_run() {
+        let runner = FixtureRunner::new()
+            .fixture(
+                "helm test web -n apps --timeout=2m --logs",
+                CommandOutput {
+                    code: Some(1),
+                    stdout: TEST_OUTPUT.as_bytes().to_vec(),
+                    stderr: b"Error: 1 error occurred:\n\t* pod web-test-api failed".to_vec(),
+                },
+            )
+            .respond("helm status web -n apps -o json", &status(Utc::now()));
+
+        let run = run_release_tests(&runner, &request()).await.unwrap();
+
+        assert!(!run.success);
+        assert_eq!(run.revision, Some(4));
+        assert!(run.error.unwrap().contains("pod web-test-api failed"));
+
+        let names: Vec<&str> = run.results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["web-test-connection", "web-test-api", "web-test-legacy"]
+        );
+        assert_eq!(run.results[0].phase, HookPhase::Succeeded);
+        assert_eq!(run.results[0].duration_secs, Some(4.5));
+        assert_eq!(
+            run.results[0].logs.as_deref(),
+            Some("Connecting to web:80\nOK")
+        );
+        assert_eq!(run.results[1].phase, HookPhase::Failed);
+        assert_eq!(
+            run.results[1].logs.as_deref(),
+            Some("curl: (7) Failed to connect")
+        );
+        assert_eq!(run.results[2].phase, HookPhase::Unknown);
+        assert_eq!(run.results[2].started_at, None);
+        assert_eq!(run.results[2].duration_secs, None);
+    }
+
+    #[tokio::test]
+    async fn does_not_report_earlier_runs_as_results() {
+        let runner = FixtureRunner::new()
+            .fail(
+                "helm test web -n apps --timeout=2m --logs",
+                "Error: unable to get pod web-test-connection: context deadline exceeded",
+                1,
+            )
+            .respond(
+                "helm status web -n apps -o json",
+                &status(Utc::now() - TimeDelta::hours(1)),
+            );
+
+        let run = run_release_tests(&runner, &request()).await.unwrap();
+
+        assert!(!run.success);
+        assert_eq!(run.results.len(), 3);
+        for result in &run.results {
+            assert_eq!(result.phase, HookPhase::Unknown);
+            assert_eq!(result.started_at, None);
+            assert_eq!(result.duration_secs, None);
+        }
+    }
+
+    #[tokio::test]
+    async fn returns_errors_that_prevent_tests_from_running() {
+        let runner = FixtureRunner::new().fail(
+            "helm test web -n apps --timeout=2m --logs",
+            "Error: release: not found",
+            1,
+        );
+
+        let error = run_release_tests(&runner, &request()).await.unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::ReleaseNotFound);
+        assert_eq!(runner.calls().len(), 1);
+    }
+
+    #[test]
+    fn rejects_invalid_requests() {
+        let invalid = [
+            ReleaseTestRequest {
+                release_name: "Web".into(),
+                ..request()
+            },
+            ReleaseTestRequest {
+                timeout: Some("soon".into()),
+                ..request()
+            },
+        ];
+        for request in invalid {
+            assert_eq!(request.to_args().unwrap_err().kind, ErrorKind::InvalidInput);
+        }
+    }
+}
@@ -0,0 +1,346 @@
+//! Helm repository module
+//!
+//! Lists, adds, removes and updates the chart repositories configured in
+//! helm, using helm's JSON output instead of its human-readable tables.
+
+use crate::error::{ErrorKind, RudderError};
+use crate::runner::command::CommandRunner;
+use serde::{Deserialize, Serialize};
+
+/// A chart repository configured in helm.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HelmRepo {
+    pub name: String,
+    pub url: String,
+}
+
+/// A repository to add with `helm repo add`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AddRepoRequest {
+    pub name: String,
+    pub url: String,
+    /// Basic auth user name (`--username`).
+    pub username: Option<String>,
+    /// Basic auth password, passed on stdin (`--password-stdin`) so that it
+    /// never shows on the command line.
+    pub password: Option<String>,
+    /// CA bundle used to verify the repository's certificate (`--ca-file`).
+    pub ca_file: Option<String>,
+    /// Replace an existing repository with the same name (`--force-update`).
+    pub force_update: bool,
+}
+
+impl AddRepoRequest {
+    /// Validate the request and convert it to `helm repo add` arguments.
+    pub fn to_args(&self) -> Result<Vec<String>, RudderError> {
+        validate_repo_name(&self.name)?;
+        if !(self.url.starts_with("https://") || self.url.starts_with("http://"))
+            || self.url.chars().any(char::is_whitespace)
+        {
+            return Err(RudderError::invalid_input(format!(
+                "Invalid repository URL '{}': expected an http:// or https:// URL",
+                self.url
+            )));
+        }
+        if self.password.is_some() && self.username.is_none() {
+            return Err(RudderError::invalid_input(
+                "A repository password requires a username",
+            ));
+        }
+        if let Some(ca_file) = &self.ca_file {
+            if !std::path::Path::new(ca_file).is_file() {
+                return Err(RudderError::invalid_input(format!(
+                    "CA file '{}' does not exist",
+                    ca_file
+                )));
+            }
+        }
+
+        let mut args = vec![
+            "repo".to_string(),
+            "add".to_string(),
+            self.name.clone(),
+            self.url.clone(),
+        ];
+        // `--flag=value` so values starting with a dash are never read as flags
+        if let Some(username) = &self.username {
+            args.push(format!("--username={}", username));
+        }
+        if self.password.is_some() {
+            args.push("--password-stdin".to_string());
+        }
+        if let Some(ca_file) = &self.ca_file {
+            args.push(format!("--ca-file={}", ca_file));
+        }
+        if self.force_update {
+            args.push("--force-update".to_string());
+        }
+        Ok(args)
+    }
+}
+
+/// Outcome of updating the index of one repository.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoUpdateResult {
+    pub name: String,
+    pub success: bool,
+    /// helm's error message if the update failed.
+    pub error: Option<String>,
+}
+
+/// Check a repository name as accepted by `helm repo add`.
+pub fn validate_repo_name(name: &str) -> Result<(), RudderError> {
+    let valid_char = |c: char| c.is_ascii_alphanumeric() || "-_.".contains(c);
+    if name.is_empty() || name.starts_with('-') || !name.chars().all(valid_char) {
+        return Err(RudderError::invalid_input(format!(
+            "Invalid repository name '{}': use letters, digits, '-', '_' or '.'",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Repositories configured in helm, in the order helm lists them.
+pub async fn list_repos(runner: &dyn CommandRunner) -> Result<Vec<HelmRepo>, RudderError> {
+    let output = runner
+        .run("helm", ["repo", "list", "-o", "json"])
+        .await
+        .map_err(|e| RudderError::spawn_failed("Helm repo list command failed", e))?;
+
+    if !output.success() {
+        // Older helm versions fail instead of printing an empty list
+        if String::from_utf8_lossy(&output.stderr).contains("no repositories to show") {
+            return Ok(Vec::new());
+        }
+        return Err(RudderError::command_failed(
+            "Helm repo list command failed",
+            &output,
+        ));
+    }
+
+    serde_json::from_slice(&output.stdout).map_err(|e| {
+        RudderError::new(
+            ErrorKind::CommandFailed,
+            format!("Failed to parse helm repo list: {}", e),
+        )
+    })
+}
+
+/// Add a repository and download its index.
+pub async fn add_repo(
+    runner: &dyn CommandRunner,
+    request: &AddRepoRequest,
+) -> Result<HelmRepo, RudderError> {
+    let args = request.to_args()?;
+    let output = match &request.password {
+        Some(password) => runner.run_with_stdin("helm", &args, password).await,
+        None => runner.run("helm", &args).await,
+    }
+    .map_err(|e| RudderError::spawn_failed("Helm repo add command failed", e))?;
+
+    if !output.success() {
+        return Err(RudderError::command_failed(
+            "Helm repo add command failed",
+            &output,
+        ));
+    }
+
+    Ok(HelmRepo {
+        name: request.name.clone(),
+        url: request.url.clone(),
+    })
+}
+
+/// Remove a repository and its cached index.
+pub async fn remove_repo(runner: &dyn CommandRunner, name: &str) -> Result<(), RudderError> {
+    validate_repo_name(name)?;
+    let output = runner
+        .run("helm", ["repo", "remove", name])
+        .await
+        .map_err(|e| RudderError::spawn_failed("Helm repo remove command failed", e))?;
+
+    if !output.success() {
+        return Err(RudderError::command_failed(
+            "Helm repo remove command failed",
+            &output,
+        ));
+    }
+    Ok(())
+}
+
+/// Update the indexes of the named repositories, or of all repositories if
+/// `names` is empty. Each repository is updated on its own so that one
+/// unreachable repository does not hide the results of the others.
+pub async fn update_repos(
+    runner: &dyn CommandRunner,
+    names: &[String],
+) -> Result<Vec<RepoUpdateResult>, RudderError> {
+    let names = if names.is_empty() {
+        list_repos(runner)
+            .await?
+            .into_iter()
+            .map(|repo| repo.name)
+            .collect()
+    } else {
+        for name in names {
+            validate_repo_name(name)?;
+        }
+        names.to_vec()
+    };
+
+    let mut results = Vec::with_capacity(names.len());
+    for name in names {
+        let error = match runner.run("helm", ["repo", "update", &name]).await {
+            Ok(output) if output.success() => None,
+            Ok(output) => Some(
+                RudderError::command_failed("Helm repo update command failed", &output).message,
+            ),
+            Err(e) => return Err(RudderError::spawn_failed("Helm repo update command failed", e)),
+        };
+        results.push(RepoUpdateResult {
+            name,
+            success: error.is_none(),
+            error,
+        });
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::fixture::FixtureRunner;
+
+    const REPOS: &str = r#"[{"name":"bitnami","url":"https://charts.bitnami.com/bitnami"},{"name":"internal","url":"https://charts.example.com"}]"#;
+
+    #[tokio::test]
+    async fn lists_repos_from_json() {
+        let runner = FixtureRunner::new().respond("helm repo list -o json", REPOS);
+
+        let repos = list_repos(&runner).await.unwrap();
+
+        assert_eq!(repos.len(), 2);
+        assert_eq!(repos[0].name, "bitnami");
+        assert_eq!(repos[1].url, "https://charts.example.com");
+    }
+
+    #[tokio::test]
+    async fn treats_no_repositories_as_an_empty_list() {
+        let runner = FixtureRunner::new().fail(
+            "helm repo list -o json",
+            "Error: no repositories to show",
+            1,
+        );
+
+        assert!(list_repos(&runner).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn builds_repo_add_arguments() {
+        let request = AddRepoRequest {
+            name: "internal".into(),
+            url: "https://charts.example.com".into(),
+            username: Some("ci".into()),
+            password: Some("s3cret".into()),
+            force_update: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            request.to_args().unwrap(),
+            vec![
+                "repo",
+                "add",
+                "internal",
+                "https://charts.example.com",
+                "--username=ci",
+                "--password-stdin",
+                "--force-update",
+            ]
+        );
+
+        let invalid = [
+            AddRepoRequest {
+                name: "-x".into(),
+                ..request.clone()
+            },
+            AddRepoRequest {
+                url: "charts.example.com".into(),
+                ..request.clone()
+            },
+            AddRepoRequest {
+                username: None,
+                ..request.clone()
+            },
+            AddRepoRequest {
+                ca_file: Some("/nonexistent/ca.pem".into()),
+                ..request
+            },
+        ];
+        for request in invalid {
+            assert_eq!(
+                request.to_args().unwrap_err().kind,
+                ErrorKind::InvalidInput
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn passes_the_repo_password_on_stdin() {
+        let runner = FixtureRunner::new().respond(
+            "helm repo add internal https://charts.example.com --username=ci --password-stdin",
+            "\"internal\" has been added to your repositories\n",
+        );
+        let request = AddRepoRequest {
+            name: "internal".into(),
+            url: "https://charts.example.com".into(),
+            username: Some("ci".into()),
+            password: Some("s3cret".into()),
+            ..Default::default()
+        };
+
+        add_repo(&runner, &request).await.unwrap();
+
+        assert_eq!(runner.inputs(), vec!["s3cret"]);
+        assert!(runner.calls().iter().all(|call| !call.contains("s3cret")));
+    }
+
+    #[tokio::test]
+    async fn reports_update_results_per_repo() {
+        let runner = FixtureRunner::new()
+            .respond("helm repo list -o json", REPOS)
+            .respond(
+                "helm repo update bitnami",
+                "...Successfully got an update from the \"bitnami\" chart repository\n",
+            )
+            .fail(
+                "helm repo update internal",
+                "Error: failed to fetch https://charts.example.com/index.yaml : 401 Unauthorized",
+                1,
+            );
+
+        let results = update_repos(&runner, &[]).await.unwrap();
+
+        assert_eq!(
+            results,
+            vec![
+                RepoUpdateResult {
+                    name: "bitnami".into(),
+                    success: true,
+                    error: None,
+                },
+                RepoUpdateResult {
+                    name: "internal".into(),
+                    success: false,
+                    error: Some(
+                        "failed to fetch https://charts.example.com/index.yaml : 401 Unauthorized"
+                            .into()
+                    ),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,214 @@
+//! Helm revision module
+//!
+//! Compares the values and manifests of two revisions of a release, which
+//! doubles as a preview of what a rollback would change.
+
+use super::diff::{diff_resources, diff_values, ResourceDiff, ValueChange};
+use super::manifest::parse_manifest;
+use crate::error::{ErrorKind, RudderError};
+use crate::runner::command::CommandRunner;
+use serde::Serialize;
+
+/// Differences between two revisions of a release.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevisionComparison {
+    pub release_name: String,
+    pub namespace: String,
+    pub from: u32,
+    pub to: u32,
+    /// User-supplied values that differ.
+    pub values: Vec<ValueChange>,
+    /// Rendered resources that differ.
+    pub resources: Vec<ResourceDiff>,
+}
+
+/// Compare revision `from` with revision `to`, where `from` defaults to the
+/// current revision so that the result previews a rollback to `to`.
+pub async fn compare_revisions(
+    runner: &dyn CommandRunner,
+    release_name: &str,
+    namespace: &str,
+    from: Option<u32>,
+    to: u32,
+) -> Result<RevisionComparison, RudderError> {
+    let from = match from {
+        Some(revision) => revision,
+        None => current_revision(runner, release_name, namespace).await?,
+    };
+
+    let (old_values, new_values, old_manifest, new_manifest) = tokio::try_join!(
+        get_values(runner, release_name, namespace, from),
+        get_values(runner, release_name, namespace, to),
+        get_manifest(runner, release_name, namespace, from),
+        get_manifest(runner, release_name, namespace, to),
+    )?;
+
+    let parse = |manifest: &str| {
+        parse_manifest(manifest).map_err(|e| RudderError::new(ErrorKind::CommandFailed, e))
+    };
+    let resources = diff_resources(
+        &parse(&old_manifest)?,
+        &parse(&new_manifest)?,
+        &format!("revision {}", from),
+        &format!("revision {}", to),
+    );
+
+    Ok(RevisionComparison {
+        release_name: release_name.to_string(),
+        namespace: namespace.to_string(),
+        from,
+        to,
+        values: diff_values(&old_values, &new_values),
+        resources,
+    })
+}
+
+/// Number of the latest revision of a release.
+pub async fn current_revision(
+    runner: &dyn CommandRunner,
+    release_name: &str,
+    namespace: &str,
+) -> Result<u32, RudderError> {
+    let stdout = run_helm(
+        runner,
+        "Helm history command failed",
+        &["history", release_name, "-n", namespace, "--max", "1", "-o", "json"],
+    )
+    .await?;
+
+    let history: serde_json::Value = serde_json::from_str(&stdout).map_err(|e| {
+        RudderError::new(ErrorKind::CommandFailed, format!("Failed to parse helm history: {}", e))
+    })?;
+    history[0]["revision"]
+        .as_u64()
+        .map(|revision| revision as u32)
+        .ok_or_else(|| {
+            RudderError::new(
+                ErrorKind::ReleaseNotFound,
+                format!("Release {} has no revisions", release_name),
+            )
+        })
+}
+
+/// User-supplied values of a revision, `{}` when there are none.
+async fn get_values(
+    runner: &dyn CommandRunner,
+    release_name: &str,
+    namespace: &str,
+    revision: u32,
+) -> Result<serde_json::Value, RudderError> {
+    let revision = revision.to_string();
+    let stdout = run_helm(
+        runner,
+        "Helm get values command failed",
+        &["get", "values", release_name, "-n", namespace, "--revision", &revision, "-o", "json"],
+    )
+    .await?;
+
+    // helm prints `null` for a revision without user-supplied values
+    match serde_json::from_str(&stdout) {
+        Ok(serde_json::Value::Null) => Ok(serde_json::json!({})),
+        Ok(values) => Ok(values),
+        Err(e) => Err(RudderError::new(
+            ErrorKind::CommandFailed,
+            format!("Failed to parse helm values: {}", e),
+        )),
+    }
+}
+
+async fn get_manifest(
+    runner: &dyn CommandRunner,
+    release_name: &str,
+    namespace: &str,
+    revision: u32,
+) -> Result<String, RudderError> {
+    let revision = revision.to_string();
+    run_helm(
+        runner,
+        "Helm get manifest command failed",
+        &["get", "manifest", release_name, "-n", namespace, "--revision", &revision],
+    )
+    .await
+}
+
+async fn run_helm(runner: &dyn CommandRunner, context: &str, args: &[&str]) -> Result<String, RudderError> {
+    let output = runner
+        .run("helm", args)
+        .await
+        .map_err(|e| RudderError::spawn_failed(context, e))?;
+
+    if !output.success() {
+        return Err(RudderError::command_failed(context, &output));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helm::diff::DiffKind;
+    use crate::runner::fixture::FixtureRunner;
+
+    fn fixture() -> FixtureRunner {
+        FixtureRunner::new()
+            .respond(
+                "helm history web -n apps --max 1 -o json",
+                r#"[{"revision":3,"status":"deployed","chart":"nginx-15.4.0"}]"#,
+            )
+            .respond(
+                "helm get values web -n apps --revision 3 -o json",
+                r#"{"replicaCount": 3, "image": {"tag": "1.26"}}"#,
+            )
+            .respond("helm get values web -n apps --revision 1 -o json", "null")
+            .respond(
+                "helm get manifest web -n apps --revision 3",
+                "---\n# Source: nginx/templates/deployment.yaml\nkind: Deployment\nmetadata:\n  name: web\nspec:\n  replicas: 3\n",
+            )
+            .respond(
+                "helm get manifest web -n apps --revision 1",
+                "---\n# Source: nginx/templates/deployment.yaml\nkind: Deployment\nmetadata:\n  name: web\nspec:\n  replicas: 1\n",
+            )
+    }
+
+    #[tokio::test]
+    async fn previews_a_rollback_from_the_current_revision() {
+        let runner = fixture();
+
+        let comparison = compare_revisions(&runner, "web", "apps", None, 1).await.unwrap();
+
+        assert_eq!((comparison.from, comparison.to), (3, 1));
+        let values: Vec<(&str, DiffKind)> = comparison
+            .values
+            .iter()
+            .map(|c| (c.path.as_str(), c.change))
+            .collect();
+        assert_eq!(
+            values,
+            vec![("image", DiffKind::Removed), ("replicaCount", DiffKind::Removed)]
+        );
+        assert_eq!(comparison.resources.len(), 1);
+        assert_eq!(comparison.resources[0].change, DiffKind::Changed);
+        assert!(comparison.resources[0].diff.contains("+  replicas: 1"));
+    }
+
+    #[tokio::test]
+    async fn reports_missing_revisions() {
+        let runner = fixture()
+            .fail(
+                "helm get values web -n apps --revision 9 -o json",
+                "Error: release: not found",
+                1,
+            )
+            .fail(
+                "helm get manifest web -n apps --revision 9",
+                "Error: release: not found",
+                1,
+            );
+
+        let error = compare_revisions(&runner, "web", "apps", Some(3), 9).await.unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::ReleaseNotFound);
+    }
+}
@@ -0,0 +1,311 @@
+//! Release status module
+//!
+//! Detail view of a release built from `helm status -o json`: the rendered
+//! NOTES.txt, deployment times, chart metadata and the state of each hook.
+
+use super::release::ReleaseStatus;
+use super::upgrade::{validate_namespace, validate_release_name};
+use crate::error::{ErrorKind, RudderError};
+use crate::runner::command::CommandRunner;
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Phase of a hook's last run, as reported by helm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HookPhase {
+    Running,
+    Succeeded,
+    Failed,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Metadata from the release's `Chart.yaml`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ChartMetadata {
+    pub name: String,
+    pub version: String,
+    pub app_version: Option<String>,
+    pub description: Option<String>,
+    pub home: Option<String>,
+    pub icon: Option<String>,
+    pub sources: Vec<String>,
+    pub keywords: Vec<String>,
+    pub maintainers: Vec<ChartMaintainer>,
+    pub kube_version: Option<String>,
+    pub deprecated: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ChartMaintainer {
+    pub name: String,
+    pub email: Option<String>,
+    pub url: Option<String>,
+}
+
+/// A hook of the release and the outcome of its last run.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseHook {
+    pub name: String,
+    /// Kind of the hook resource, e.g. `Job` or `Pod`.
+    pub kind: String,
+    /// Events the hook runs on, e.g. `pre-upgrade` or `test`.
+    pub events: Vec<String>,
+    pub weight: i32,
+    pub delete_policies: Vec<String>,
+    pub phase: HookPhase,
+    /// `None` if the hook never ran.
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl ReleaseHook {
+    /// Whether the hook is run by `helm test`.
+    pub fn is_test(&self) -> bool {
+        // `test-success` is the pre-helm-3 name of the `test` hook
+        self.events
+            .iter()
+            .any(|e| e == "test" || e == "test-success")
+    }
+
+    /// How long the last run took, if it completed.
+    pub fn duration_secs(&self) -> Option<f64> {
+        let (start, end) = self.started_at.zip(self.completed_at)?;
+        Some((end - start).num_milliseconds() as f64 / 1000.0)
+    }
+}
+
+/// Status detail of a release.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseStatusDetail {
+    pub name: String,
+    pub namespace: String,
+    pub revision: u32,
+    pub status: ReleaseStatus,
+    /// Description of the last operation, e.g. `Upgrade complete`.
+    pub description: String,
+    pub first_deployed: Option<DateTime<Utc>>,
+    pub last_deployed: Option<DateTime<Utc>>,
+    /// Rendered NOTES.txt, empty if the chart has none.
+    pub notes: String,
+    pub chart: ChartMetadata,
+    pub hooks: Vec<ReleaseHook>,
+}
+
+#[derive(Deserialize)]
+struct RawStatus {
+    name: String,
+    #[serde(default)]
+    namespace: String,
+    #[serde(default)]
+    version: u32,
+    info: RawInfo,
+    #[serde(default)]
+    chart: Option<RawChart>,
+    #[serde(default)]
+    hooks: Vec<RawHook>,
+}
+
+#[derive(Deserialize)]
+struct RawInfo {
+    #[serde(default)]
+    first_deployed: Option<DateTime<Utc>>,
+    #[serde(default)]
+    last_deployed: Option<DateTime<Utc>>,
+    #[serde(default)]
+    description: String,
+    status: ReleaseStatus,
+    #[serde(default)]
+    notes: String,
+}
+
+#[derive(Deserialize)]
+struct RawChart {
+    #[serde(default)]
+    metadata: ChartMetadata,
+}
+
+#[derive(Deserialize)]
+struct RawHook {
+    name: String,
+    #[serde(default)]
+    kind: String,
+    #[serde(default)]
+    events: Vec<String>,
+    #[serde(default)]
+    weight: i32,
+    #[serde(default)]
+    delete_policies: Vec<String>,
+    #[serde(default)]
+    last_run: Option<RawHookRun>,
+}
+
+#[derive(Default, Deserialize)]
+struct RawHookRun {
+    #[serde(default)]
+    started_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    completed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    phase: Option<HookPhase>,
+}
+
+/// Times that were never set are Go's zero time in helm's JSON.
+fn known_time(time: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+    time.filter(|t| t.year() > 1)
+}
+
+impl From<RawHook> for ReleaseHook {
+    fn from(hook: RawHook) -> Self {
+        let run = hook.last_run.unwrap_or_default();
+        ReleaseHook {
+            name: hook.name,
+            kind: hook.kind,
+            events: hook.events,
+            weight: hook.weight,
+            delete_policies: hook.delete_policies,
+            phase: run.phase.unwrap_or(HookPhase::Unknown),
+            started_at: known_time(run.started_at),
+            completed_at: known_time(run.completed_at),
+        }
+    }
+}
+
+impl From<RawStatus> for ReleaseStatusDetail {
+    fn from(raw: RawStatus) -> Self {
+        ReleaseStatusDetail {
+            name: raw.name,
+            namespace: raw.namespace,
+            revision: raw.version,
+            status: raw.info.status,
+            description: raw.info.description,
+            first_deployed: known_time(raw.info.first_deployed),
+            last_deployed: known_time(raw.info.last_deployed),
+            notes: raw.info.notes,
+            chart: raw.chart.map(|c| c.metadata).unwrap_or_default(),
+            hooks: raw.hooks.into_iter().map(ReleaseHook::from).collect(),
+        }
+    }
+}
+
+/// Fetch the status detail of a release.
+pub async fn get_release_status(
+    runner: &dyn CommandRunner,
+    release_name: &str,
+    namespace: &str,
+) -> Result<ReleaseStatusDetail, RudderError> {
+    validate_release_name(release_name)?;
+    validate_namespace(namespace)?;
+
+    let output = runner
+        .run(
+            "helm",
+            ["status", release_name, "-n", namespace, "-o", "json"],
+        )
+        .await
+        .map_err(|e| RudderError::spawn_failed("Helm status command failed", e))?;
+
+    if !output.success() {
+        return Err(RudderError::command_failed(
+            "Helm status command failed",
+            &output,
+        ));
+    }
+
+    let raw: RawStatus = serde_json::from_slice(&output.stdout).map_err(|e| {
+        RudderError::new(
+            ErrorKind::CommandFailed,
+            format!("Failed to parse helm status: {}", e),
+        )
+    })?;
+    Ok(raw.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::fixture::FixtureRunner;
+
+    const STATUS: &str = r#"{
+        "name": "web",
+        "namespace": "apps",
+        "version": 3,
+        "info": {
+            "first_deployed": "2026-09-01T08:00:00.123456789+02:00",
+            "last_deployed": "2026-10-16T10:00:00Z",
+            "deleted": "",
+            "description": "Upgrade complete",
+            "status": "deployed",
+            "notes": "Get the application URL by running:\n  kubectl port-forward svc/web 8080:80\n"
+        },
+        "chart": {
+            "metadata": {
+                "name": "nginx",
+                "version": "15.4.0",
+                "appVersion": "1.25.3",
+                "description": "NGINX Open Source",
+                "home": "https://bitnami.com",
+                "keywords": ["nginx", "http"],
+                "maintainers": [{"name": "Broadcom", "url": "https://github.com/bitnami/charts"}],
+                "apiVersion": "v2"
+            },
+            "templates": []
+        },
+        "hooks": [
+            {"name": "web-migrate", "kind": "Job", "path": "nginx/templates/migrate.yaml", "events": ["pre-upgrade"], "weight": -5, "delete_policies": ["before-hook-creation"], "last_run": {"started_at": "2026-10-16T09:59:00Z", "completed_at": "2026-10-16T09:59:30Z", "phase": "Succeeded"}},
+            {"name": "web-test-connection", "kind": "Pod", "events": ["test"], "last_run": {"started_at": "0001-01-01T00:00:00Z", "completed_at": "0001-01-01T00:00:00Z", "phase": ""}}
+        ]
+    }"#;
+
+    #[tokio::test]
+    async fn parses_notes_chart_metadata_and_hooks() {
+        let runner = FixtureRunner::new().respond("helm status web -n apps -o json", STATUS);
+
+        let status = get_release_status(&runner, "web", "apps").await.unwrap();
+
+        assert_eq!(status.revision, 3);
+        assert_eq!(status.status, ReleaseStatus::Deployed);
+        assert_eq!(status.description, "Upgrade complete");
+        assert!(status
+            .notes
+            .contains("kubectl port-forward svc/web 8080:80"));
+        assert_eq!(
+            status.first_deployed.unwrap().to_rfc3339(),
+            "2026-09-01T06:00:00.123456789+00:00"
+        );
+        assert_eq!(status.chart.app_version.as_deref(), Some("1.25.3"));
+        assert_eq!(status.chart.maintainers[0].name, "Broadcom");
+
+        let migrate = &status.hooks[0];
+        assert_eq!(migrate.kind, "Job");
+        assert_eq!(migrate.weight, -5);
+        assert_eq!(migrate.phase, HookPhase::Succeeded);
+        assert_eq!(migrate.duration_secs(), Some(30.0));
+        assert!(!migrate.is_test());
+
+        let test = &status.hooks[1];
+        assert!(test.is_test());
+        assert_eq!(test.phase, HookPhase::Unknown);
+        assert_eq!(test.started_at, None);
+        assert_eq!(test.duration_secs(), None);
+    }
+
+    #[tokio::test]
+    async fn reports_missing_releases() {
+        let runner = FixtureRunner::new().fail(
+            "helm status web -n apps -o json",
+            "Error: release: not found",
+            1,
+        );
+
+        let error = get_release_status(&runner, "web", "apps")
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::ReleaseNotFound);
+    }
+}
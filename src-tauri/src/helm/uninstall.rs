@@ -0,0 +1,357 @@
+//! Helm uninstall module
+//!
+//! Uninstalling is a two-step handshake: the first call previews the objects
+//! helm will delete and hands out a single-use confirmation token, and only a
+//! second call presenting that token runs `helm uninstall`.
+
+use super::inventory::ReleaseResource;
+use super::manifest::parse_manifest;
+use super::upgrade::{parse_duration_secs, validate_namespace, validate_release_name};
+use crate::error::{ErrorKind, RudderError};
+use crate::runner::command::CommandRunner;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a confirmation token stays valid.
+pub const CONFIRMATION_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Optional `helm uninstall` flags sent by the frontend.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UninstallOptions {
+    /// Keep the release history so the release can be rolled back (`--keep-history`).
+    pub keep_history: bool,
+    /// Wait until all resources are deleted (`--wait`).
+    pub wait: bool,
+    /// How long helm waits for Kubernetes operations, as a Go duration (`--timeout`).
+    pub timeout: Option<String>,
+}
+
+impl UninstallOptions {
+    /// Validate the options and convert them to helm arguments.
+    pub fn to_args(&self) -> Result<Vec<String>, RudderError> {
+        let mut args = Vec::new();
+        if self.keep_history {
+            args.push("--keep-history".to_string());
+        }
+        if self.wait {
+            args.push("--wait".to_string());
+        }
+        if let Some(timeout) = &self.timeout {
+            parse_duration_secs(timeout)?;
+            args.push(format!("--timeout={}", timeout));
+        }
+        Ok(args)
+    }
+}
+
+/// An uninstall request; without a confirmation token it only previews.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UninstallRequest {
+    pub release_name: String,
+    pub namespace: String,
+    #[serde(default)]
+    pub options: UninstallOptions,
+    /// Token from the preview of this exact request.
+    #[serde(default)]
+    pub confirmation_token: Option<String>,
+}
+
+impl UninstallRequest {
+    pub fn validate(&self) -> Result<(), RudderError> {
+        validate_release_name(&self.release_name)?;
+        validate_namespace(&self.namespace)?;
+        self.options.to_args().map(|_| ())
+    }
+
+    /// Validate the request and convert it to `helm uninstall` arguments.
+    pub fn to_args(&self) -> Result<Vec<String>, RudderError> {
+        self.validate()?;
+
+        let mut args = vec![
+            "uninstall".to_string(),
+            self.release_name.clone(),
+            "-n".to_string(),
+            self.namespace.clone(),
+        ];
+        args.extend(self.options.to_args()?);
+        Ok(args)
+    }
+}
+
+/// What an uninstall would delete, with the token that confirms it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UninstallPreview {
+    pub release_name: String,
+    pub namespace: String,
+    /// Objects helm will delete.
+    pub resources: Vec<ReleaseResource>,
+    /// Objects annotated with `helm.sh/resource-policy: keep`, which helm leaves behind.
+    pub kept: Vec<ReleaseResource>,
+    pub keep_history: bool,
+    pub confirmation_token: String,
+    pub expires_in_secs: u64,
+}
+
+/// Result of `uninstall_release`: a preview awaiting confirmation, or the
+/// output of the uninstall that was carried out.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum UninstallOutcome {
+    PendingConfirmation(UninstallPreview),
+    Uninstalled { output: String },
+}
+
+/// Confirmation store shared as Tauri managed state.
+pub type SharedConfirmations = Arc<UninstallConfirmations>;
+
+struct PendingUninstall {
+    release_name: String,
+    namespace: String,
+    options: UninstallOptions,
+    issued: Instant,
+}
+
+/// Confirmation tokens handed out by uninstall previews.
+///
+/// A token is bound to the release, namespace and options it was issued
+/// for, expires after a few minutes, and is consumed by its first use.
+pub struct UninstallConfirmations {
+    ttl: Duration,
+    pending: Mutex<HashMap<String, PendingUninstall>>,
+}
+
+impl Default for UninstallConfirmations {
+    fn default() -> Self {
+        Self::with_ttl(CONFIRMATION_TTL)
+    }
+}
+
+impl UninstallConfirmations {
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Issue a token for uninstalling a release with the given options.
+    pub fn issue(&self, request: &UninstallRequest) -> String {
+        let token = new_token(request);
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        pending.retain(|_, p| p.issued.elapsed() <= self.ttl);
+        pending.insert(
+            token.clone(),
+            PendingUninstall {
+                release_name: request.release_name.clone(),
+                namespace: request.namespace.clone(),
+                options: request.options.clone(),
+                issued: Instant::now(),
+            },
+        );
+        token
+    }
+
+    /// Consume a token, failing unless it was issued for exactly this request
+    /// and has not expired.
+    pub fn redeem(&self, token: &str, request: &UninstallRequest) -> Result<(), RudderError> {
+        let pending = self
+            .pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(token);
+
+        let rejected = |reason: &str| {
+            Err(RudderError::invalid_input(format!(
+                "Uninstall of {} in {} was not confirmed: {}",
+                request.release_name, request.namespace, reason
+            ))
+            .with_remediation("Preview the uninstall again and confirm the new preview."))
+        };
+        match pending {
+            None => rejected("the confirmation token is unknown or was already used"),
+            Some(p) if p.issued.elapsed() > self.ttl => {
+                rejected("the confirmation token has expired")
+            }
+            Some(p)
+                if p.release_name != request.release_name
+                    || p.namespace != request.namespace
+                    || p.options != request.options =>
+            {
+                rejected("the confirmation token was issued for a different request")
+            }
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+/// Hard-to-guess token; it guards against mistakes, not attackers.
+fn new_token(request: &UninstallRequest) -> String {
+    static NEXT_TOKEN: AtomicU64 = AtomicU64::new(1);
+    let mut hasher = RandomState::new().build_hasher();
+    request.release_name.hash(&mut hasher);
+    request.namespace.hash(&mut hasher);
+    NEXT_TOKEN.fetch_add(1, Ordering::Relaxed).hash(&mut hasher);
+    std::time::SystemTime::now().hash(&mut hasher);
+    format!("uninstall-{:016x}", hasher.finish())
+}
+
+/// Preview an uninstall from the release manifest and issue its confirmation token.
+pub async fn preview_uninstall(
+    runner: &dyn CommandRunner,
+    confirmations: &UninstallConfirmations,
+    request: &UninstallRequest,
+) -> Result<UninstallPreview, RudderError> {
+    request.validate()?;
+
+    let output = runner
+        .run(
+            "helm",
+            [
+                "get",
+                "manifest",
+                &request.release_name,
+                "-n",
+                &request.namespace,
+            ],
+        )
+        .await
+        .map_err(|e| RudderError::spawn_failed("Helm get manifest command failed", e))?;
+
+    if !output.success() {
+        return Err(RudderError::command_failed(
+            "Helm get manifest command failed",
+            &output,
+        ));
+    }
+
+    let manifest = parse_manifest(&String::from_utf8_lossy(&output.stdout))
+        .map_err(|e| RudderError::new(ErrorKind::CommandFailed, e))?;
+    let (kept, resources): (Vec<_>, Vec<_>) = manifest
+        .into_iter()
+        .partition(|r| r.object["metadata"]["annotations"]["helm.sh/resource-policy"] == "keep");
+    let inventory = |resources: Vec<_>| {
+        resources
+            .into_iter()
+            .map(|r| ReleaseResource::from_manifest(r, &request.namespace))
+            .collect()
+    };
+
+    Ok(UninstallPreview {
+        release_name: request.release_name.clone(),
+        namespace: request.namespace.clone(),
+        resources: inventory(resources),
+        kept: inventory(kept),
+        keep_history: request.options.keep_history,
+        confirmation_token: confirmations.issue(request),
+        expires_in_secs: confirmations.ttl.as_secs(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::fixture::FixtureRunner;
+
+    fn request() -> UninstallRequest {
+        UninstallRequest {
+            release_name: "web".into(),
+            namespace: "apps".into(),
+            options: UninstallOptions {
+                keep_history: true,
+                wait: true,
+                timeout: Some("5m".into()),
+            },
+            confirmation_token: None,
+        }
+    }
+
+    #[test]
+    fn converts_the_request_to_helm_uninstall_arguments() {
+        assert_eq!(
+            request().to_args().unwrap(),
+            vec![
+                "uninstall",
+                "web",
+                "-n",
+                "apps",
+                "--keep-history",
+                "--wait",
+                "--timeout=5m"
+            ]
+        );
+
+        let invalid = UninstallRequest {
+            options: UninstallOptions {
+                timeout: Some("later".into()),
+                ..Default::default()
+            },
+            ..request()
+        };
+        assert_eq!(invalid.to_args().unwrap_err().kind, ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn previews_deleted_and_kept_objects() {
+        let runner = FixtureRunner::new().respond(
+            "helm get manifest web -n apps",
+            "---\n# Source: nginx/templates/deployment.yaml\napiVersion: apps/v1\nkind: Deployment\nmetadata:\n  name: web\n---\n# Source: nginx/templates/pvc.yaml\napiVersion: v1\nkind: PersistentVolumeClaim\nmetadata:\n  name: data\n  annotations:\n    helm.sh/resource-policy: keep\n",
+        );
+        let confirmations = UninstallConfirmations::default();
+
+        let preview = preview_uninstall(&runner, &confirmations, &request())
+            .await
+            .unwrap();
+
+        assert_eq!(preview.resources.len(), 1);
+        assert_eq!(preview.resources[0].kind, "Deployment");
+        assert_eq!(preview.kept[0].name, "data");
+        assert!(preview.keep_history);
+        assert!(confirmations
+            .redeem(&preview.confirmation_token, &request())
+            .is_ok());
+    }
+
+    #[test]
+    fn tokens_are_single_use_and_bound_to_the_request() {
+        let confirmations = UninstallConfirmations::default();
+
+        let token = confirmations.issue(&request());
+        assert!(confirmations.redeem(&token, &request()).is_ok());
+        assert!(confirmations.redeem(&token, &request()).is_err());
+
+        let other_release = UninstallRequest {
+            release_name: "api".into(),
+            ..request()
+        };
+        let token = confirmations.issue(&request());
+        assert!(confirmations.redeem(&token, &other_release).is_err());
+
+        let without_history = UninstallRequest {
+            options: UninstallOptions::default(),
+            ..request()
+        };
+        let token = confirmations.issue(&request());
+        assert!(confirmations.redeem(&token, &without_history).is_err());
+    }
+
+    #[test]
+    fn expired_tokens_are_rejected() {
+        let confirmations = UninstallConfirmations::with_ttl(Duration::ZERO);
+
+        let token = confirmations.issue(&request());
+        std::thread::sleep(Duration::from_millis(5));
+
+        let error = confirmations.redeem(&token, &request()).unwrap_err();
+        assert!(error
+            .message
+            .ends_with("the confirmation token has expired"));
+    }
+}
@@ -0,0 +1,353 @@
+//! Helm upgrade options module
+//!
+//! Flags shared by `helm_upgrade` and `helm_dry_run`, validated before they
+//! are turned into helm arguments.
+
+use super::local_chart::resolve_chart_path;
+use super::values_file::{parse_values, ValuesFile};
+use crate::error::RudderError;
+use crate::runner::command::CommandRunner;
+use serde::Deserialize;
+
+/// Optional `helm upgrade --install` flags sent by the frontend.
+///
+/// Every field may be omitted; omitted flags are not passed to helm.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UpgradeOptions {
+    /// Target namespace (`-n`).
+    pub namespace: Option<String>,
+    /// Create the namespace if it does not exist (`--create-namespace`).
+    pub create_namespace: bool,
+    /// Roll back automatically if the upgrade fails (`--atomic`).
+    pub atomic: bool,
+    /// Wait until all resources are ready (`--wait`).
+    pub wait: bool,
+    /// How long helm waits for Kubernetes operations, as a Go duration such as `5m0s` (`--timeout`).
+    pub timeout: Option<String>,
+    /// Chart version or version constraint (`--version`).
+    pub version: Option<String>,
+    /// Merge with the values of the last release (`--reuse-values`).
+    pub reuse_values: bool,
+    /// Reset values to the chart defaults (`--reset-values`).
+    pub reset_values: bool,
+    /// Replace resources instead of patching them (`--force`).
+    pub force: bool,
+    /// Description stored with the new revision (`--description`).
+    pub description: Option<String>,
+    /// Delete resources created by a failed upgrade (`--cleanup-on-fail`).
+    pub cleanup_on_fail: bool,
+}
+
+impl UpgradeOptions {
+    /// Check every option, so helm is never started with a flag it would reject.
+    pub fn validate(&self) -> Result<(), RudderError> {
+        if let Some(namespace) = &self.namespace {
+            validate_namespace(namespace)?;
+        }
+        if let Some(timeout) = &self.timeout {
+            parse_duration_secs(timeout)?;
+        }
+        if let Some(version) = &self.version {
+            validate_version(version)?;
+        }
+        if self.reuse_values && self.reset_values {
+            return Err(RudderError::invalid_input(
+                "reuseValues and resetValues cannot be used together",
+            ));
+        }
+        if let Some(description) = &self.description {
+            if description.chars().any(char::is_control) {
+                return Err(RudderError::invalid_input(
+                    "Description must not contain control characters or line breaks",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate the options and convert them to helm arguments.
+    pub fn to_args(&self) -> Result<Vec<String>, RudderError> {
+        self.validate()?;
+
+        let mut args = Vec::new();
+        if let Some(namespace) = &self.namespace {
+            args.push("-n".to_string());
+            args.push(namespace.clone());
+        }
+        let flags = [
+            (self.create_namespace, "--create-namespace"),
+            (self.atomic, "--atomic"),
+            (self.wait, "--wait"),
+            (self.reuse_values, "--reuse-values"),
+            (self.reset_values, "--reset-values"),
+            (self.force, "--force"),
+            (self.cleanup_on_fail, "--cleanup-on-fail"),
+        ];
+        args.extend(flags.iter().filter(|(set, _)| *set).map(|(_, flag)| flag.to_string()));
+        // `--flag=value` so values starting with a dash are never read as flags
+        if let Some(timeout) = &self.timeout {
+            args.push(format!("--timeout={}", timeout));
+        }
+        if let Some(version) = &self.version {
+            args.push(format!("--version={}", version));
+        }
+        if let Some(description) = &self.description {
+            args.push(format!("--description={}", description));
+        }
+
+        Ok(args)
+    }
+}
+
+/// Render an upgrade with `helm upgrade --install --dry-run` and return helm's output.
+pub async fn dry_run(
+    runner: &dyn CommandRunner,
+    release_name: &str,
+    chart_path: &str,
+    values_json: &str,
+    options: &UpgradeOptions,
+) -> Result<String, RudderError> {
+    validate_release_name(release_name)?;
+    let option_args = options.to_args()?;
+
+    // Parse the form data and hand it to helm as a values file
+    let values = parse_values(values_json).map_err(RudderError::invalid_values)?;
+    let values_file = ValuesFile::write(&values).map_err(RudderError::io)?;
+
+    let mut args = vec![
+        "upgrade".to_string(),
+        "--install".to_string(),
+        release_name.to_string(),
+        resolve_chart_path(chart_path),
+    ];
+    args.extend(option_args);
+    args.push("--dry-run".to_string());
+    args.push("-f".to_string());
+    args.push(values_file.path_arg());
+
+    let output = runner
+        .run("helm", &args)
+        .await
+        .map_err(|e| RudderError::spawn_failed("Helm dry-run command failed", e))?;
+
+    if !output.success() {
+        return Err(RudderError::command_failed("Helm dry-run command failed", &output));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Check a release name against helm's rules: a DNS-1123 label of at most 53 characters.
+pub fn validate_release_name(name: &str) -> Result<(), RudderError> {
+    if name.len() > 53 || !is_dns_label(name) {
+        return Err(RudderError::invalid_input(format!(
+            "Invalid release name '{}': must be at most 53 lowercase letters, digits or '-', starting and ending with a letter or digit",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Check a namespace name: a DNS-1123 label of at most 63 characters.
+pub fn validate_namespace(namespace: &str) -> Result<(), RudderError> {
+    if namespace.len() > 63 || !is_dns_label(namespace) {
+        return Err(RudderError::invalid_input(format!(
+            "Invalid namespace '{}': must be at most 63 lowercase letters, digits or '-', starting and ending with a letter or digit",
+            namespace
+        )));
+    }
+    Ok(())
+}
+
+fn is_dns_label(name: &str) -> bool {
+    let valid_char = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-';
+    !name.is_empty()
+        && name.chars().all(valid_char)
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+}
+
+fn validate_version(version: &str) -> Result<(), RudderError> {
+    let valid_char =
+        |c: char| c.is_ascii_alphanumeric() || ".+-~^*<>=!|, ".contains(c);
+    if version.trim().is_empty() || version.starts_with('-') || !version.chars().all(valid_char) {
+        return Err(RudderError::invalid_input(format!(
+            "Invalid chart version '{}': expected a version such as 1.2.3 or a constraint such as ^1.2",
+            version
+        )));
+    }
+    Ok(())
+}
+
+/// Parse a Go duration such as `300s`, `1.5m` or `1h30m` into whole seconds,
+/// rounding up.
+///
+/// Accepts the grammar of Go's `time.ParseDuration`: a sequence of decimal
+/// numbers, each with an optional fraction and a unit of `ns`, `us` (`µs`),
+/// `ms`, `s`, `m` or `h`. Zero and negative durations are rejected.
+pub fn parse_duration_secs(duration: &str) -> Result<u64, RudderError> {
+    let invalid = || {
+        RudderError::invalid_input(format!(
+            "Invalid timeout '{}': expected a duration such as 300s, 1.5m or 1h30m",
+            duration
+        ))
+    };
+    fn split_digits(s: &str) -> (&str, &str) {
+        s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()))
+    }
+
+    let mut total_ns: u128 = 0;
+    let mut rest = duration.strip_prefix('+').unwrap_or(duration);
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+    while !rest.is_empty() {
+        let (whole, after) = split_digits(rest);
+        let (fraction, after) = match after.strip_prefix('.') {
+            Some(after) => split_digits(after),
+            None => ("", after),
+        };
+        if whole.is_empty() && fraction.is_empty() {
+            return Err(invalid());
+        }
+
+        let unit_len = after
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(after.len());
+        let unit_ns: u128 = match &after[..unit_len] {
+            "ns" => 1,
+            "us" | "µs" | "μs" => 1_000,
+            "ms" => 1_000_000,
+            "s" => 1_000_000_000,
+            "m" => 60_000_000_000,
+            "h" => 3_600_000_000_000,
+            _ => return Err(invalid()),
+        };
+        rest = &after[unit_len..];
+
+        let whole: u128 = if whole.is_empty() {
+            0
+        } else {
+            whole.parse().map_err(|_| invalid())?
+        };
+        // Digits past the 18th are worth less than a nanosecond in any unit.
+        let fraction = &fraction[..fraction.len().min(18)];
+        let fraction_ns = if fraction.is_empty() {
+            0
+        } else {
+            let digits: u128 = fraction.parse().map_err(|_| invalid())?;
+            digits * unit_ns / 10u128.pow(fraction.len() as u32)
+        };
+
+        total_ns = whole
+            .checked_mul(unit_ns)
+            .and_then(|ns| ns.checked_add(fraction_ns))
+            .and_then(|ns| total_ns.checked_add(ns))
+            .filter(|ns| *ns <= i64::MAX as u128)
+            .ok_or_else(invalid)?;
+    }
+
+    if total_ns == 0 {
+        return Err(invalid());
+    }
+    Ok(total_ns.div_ceil(1_000_000_000) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn converts_options_to_helm_flags() {
+        let options = UpgradeOptions {
+            namespace: Some("apps".into()),
+            create_namespace: true,
+            atomic: true,
+            wait: true,
+            timeout: Some("10m".into()),
+            version: Some("15.4.0".into()),
+            reuse_values: true,
+            force: true,
+            description: Some("Bump replicas".into()),
+            cleanup_on_fail: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            options.to_args().unwrap(),
+            vec![
+                "-n",
+                "apps",
+                "--create-namespace",
+                "--atomic",
+                "--wait",
+                "--reuse-values",
+                "--force",
+                "--cleanup-on-fail",
+                "--timeout=10m",
+                "--version=15.4.0",
+                "--description=Bump replicas",
+            ]
+        );
+        assert!(UpgradeOptions::default().to_args().unwrap().is_empty());
+    }
+
+    #[test]
+    fn deserializes_partial_options_from_the_frontend() {
+        let options: UpgradeOptions =
+            serde_json::from_str(r#"{"namespace": "apps", "resetValues": true}"#).unwrap();
+
+        assert_eq!(options.namespace.as_deref(), Some("apps"));
+        assert!(options.reset_values);
+        assert!(!options.atomic);
+    }
+
+    #[test]
+    fn rejects_invalid_options() {
+        let invalid = [
+            UpgradeOptions { namespace: Some("Apps".into()), ..Default::default() },
+            UpgradeOptions { namespace: Some("-n".into()), ..Default::default() },
+            UpgradeOptions { timeout: Some("ten minutes".into()), ..Default::default() },
+            UpgradeOptions { timeout: Some("0s".into()), ..Default::default() },
+            UpgradeOptions { version: Some("--set=x".into()), ..Default::default() },
+            UpgradeOptions { version: Some("1.0; rm".into()), ..Default::default() },
+            UpgradeOptions { reuse_values: true, reset_values: true, ..Default::default() },
+            UpgradeOptions { description: Some("line\nbreak".into()), ..Default::default() },
+        ];
+
+        for options in invalid {
+            let error = options.to_args().unwrap_err();
+            assert_eq!(error.kind, ErrorKind::InvalidInput, "{:?}", options);
+        }
+    }
+
+    #[test]
+    fn parses_go_durations() {
+        assert_eq!(parse_duration_secs("300s").unwrap(), 300);
+        assert_eq!(parse_duration_secs("5m0s").unwrap(), 300);
+        assert_eq!(parse_duration_secs("1h30m").unwrap(), 5400);
+        assert_eq!(parse_duration_secs("1500ms").unwrap(), 2);
+        assert_eq!(parse_duration_secs("1.5m").unwrap(), 90);
+        assert_eq!(parse_duration_secs("2.5s").unwrap(), 3);
+        assert_eq!(parse_duration_secs(".5h").unwrap(), 1800);
+        assert_eq!(parse_duration_secs("1h0.5m250ms").unwrap(), 3631);
+        assert_eq!(parse_duration_secs("500µs").unwrap(), 1);
+        assert_eq!(parse_duration_secs("+90s").unwrap(), 90);
+        assert!(parse_duration_secs("5").is_err());
+        assert!(parse_duration_secs("m5").is_err());
+        assert!(parse_duration_secs(".s").is_err());
+        assert!(parse_duration_secs("-5m").is_err());
+        assert!(parse_duration_secs("0s").is_err());
+        assert!(parse_duration_secs("5d").is_err());
+    }
+
+    #[test]
+    fn validates_release_names() {
+        assert!(validate_release_name("web-frontend-2").is_ok());
+        assert!(validate_release_name("Web").is_err());
+        assert!(validate_release_name("web.frontend").is_err());
+        assert!(validate_release_name(&"a".repeat(54)).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! Helm values file module
+//!
+//! Form values are handed to helm through a private temporary file passed
+//! with `-f` rather than flattened into `--set` pairs, which mangles keys
+//! containing dots, commas or brackets and re-types strings like "true".
+
+use std::io::Write;
+use std::path::Path;
+use tempfile::NamedTempFile;
+
+/// Parse the values JSON sent by the frontend, which must be an object.
+pub fn parse_values(values_json: &str) -> Result<serde_json::Value, String> {
+    let values: serde_json::Value =
+        serde_json::from_str(values_json).map_err(|e| format!("Invalid JSON values: {}", e))?;
+
+    if !values.is_object() {
+        return Err("Invalid JSON values: expected an object at the top level".into());
+    }
+
+    Ok(values)
+}
+
+/// Values written to a temporary file readable only by the current user.
+///
+/// The file is written as JSON, which helm reads as YAML with every type
+/// preserved exactly, and is deleted when the `ValuesFile` is dropped.
+pub struct ValuesFile {
+    file: NamedTempFile,
+}
+
+impl ValuesFile {
+    pub fn write(values: &serde_json::Value) -> Result<Self, String> {
+        let mut file = tempfile::Builder::new()
+            .prefix("rudder-values-")
+            .suffix(".json")
+            .tempfile()
+            .map_err(|e| format!("Failed to create values file: {}", e))?;
+
+        serde_json::to_writer_pretty(&mut file, values)
+            .map_err(|e| format!("Failed to write values file: {}", e))?;
+        file.flush()
+            .map_err(|e| format!("Failed to write values file: {}", e))?;
+
+        Ok(Self { file })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.file.path()
+    }
+
+    /// Path formatted for use as the argument of `-f`.
+    pub fn path_arg(&self) -> String {
+        self.path().to_string_lossy().into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(values: serde_json::Value) {
+        let file = ValuesFile::write(&values).unwrap();
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(file.path()).unwrap()).unwrap();
+        assert_eq!(written, values);
+    }
+
+    #[test]
+    fn preserves_keys_with_set_syntax_characters() {
+        round_trip(serde_json::json!({
+            "ingress": {
+                "annotations": {
+                    "kubernetes.io/ingress.class": "nginx",
+                    "nginx.ingress.kubernetes.io/whitelist-source-range": "10.0.0.0/8,192.168.0.0/16"
+                }
+            },
+            "a,b": "comma key",
+            "list[0]": "bracket key",
+            "key=value": "equals key",
+            "with\\backslash": 1
+        }));
+    }
+
+    #[test]
+    fn preserves_scalar_types() {
+        round_trip(serde_json::json!({
+            "stringTrue": "true",
+            "leadingZero": "0123",
+            "numericString": "1e3",
+            "realBool": false,
+            "integer": 9007199254740993u64,
+            "float": 0.25,
+            "nullValue": null,
+            "emptyString": "",
+            "multiline": "line one\nline two",
+            "list": [1, "2", null, {"name": "x.y"}]
+        }));
+    }
+
+    #[test]
+    fn keeps_large_integers_exact() {
+        let values: serde_json::Value = serde_json::from_str(
+            r#"{"maxBytes": 9007199254740993, "minOffset": -9007199254740993, "limits": [18446744073709551615]}"#,
+        )
+        .unwrap();
+
+        let file = ValuesFile::write(&values).unwrap();
+        let written = std::fs::read_to_string(file.path()).unwrap();
+
+        assert!(written.contains("9007199254740993,"));
+        assert!(written.contains("-9007199254740993\n"));
+        assert!(written.contains("18446744073709551615"));
+        round_trip(values);
+    }
+
+    #[test]
+    fn file_is_private_and_removed_on_drop() {
+        let file = ValuesFile::write(&serde_json::json!({"replicaCount": 1})).unwrap();
+        let path = file.path().to_path_buf();
+        assert!(path.exists());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o077, 0);
+        }
+
+        drop(file);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn rejects_non_object_values() {
+        assert!(parse_values("[1, 2]").is_err());
+        assert!(parse_values("null").is_err());
+        assert!(parse_values("{not json").is_err());
+        assert_eq!(
+            parse_values(r#"{"a": "0123"}"#).unwrap(),
+            serde_json::json!({"a": "0123"})
+        );
+    }
+}
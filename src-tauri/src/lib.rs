@@ -1,11 +1,35 @@
 use std::sync::{Arc, Mutex};
-use tauri_plugin_shell::ShellExt;
 
 pub mod db;
+pub mod error;
+pub mod helm;
+pub mod operations;
 pub mod run;
+pub mod runner;
 pub mod schema;
 use db::connection::DbConnection;
+use db::local_charts::LocalChartEntry;
 use db::schemas;
+use error::{ErrorKind, RudderError};
+use helm::catalog::{ChartMatch, ChartSearchQuery};
+use helm::chart_ref::ChartRef;
+use helm::install::{ensure_release_name_available, InstallPreparation, InstallRequest};
+use helm::inventory::ReleaseResource;
+use helm::local_chart::LocalChart;
+use helm::oci::RegistryLogin;
+use helm::preview::UpgradePreview;
+use helm::release::{list_releases, HelmRelease, ReleaseQuery};
+use helm::release_test::{ReleaseTestRequest, ReleaseTestRun};
+use helm::repo::{AddRepoRequest, HelmRepo, RepoUpdateResult};
+use helm::revision::RevisionComparison;
+use helm::status::ReleaseStatusDetail;
+use helm::uninstall::{SharedConfirmations, UninstallOutcome, UninstallRequest};
+use helm::upgrade::{validate_release_name, UpgradeOptions};
+use helm::values_file::{parse_values, ValuesFile};
+use operations::progress::{new_operation_id, run_with_progress};
+use operations::registry::{OperationInfo, SharedRegistry};
+use runner::command::SharedRunner;
+use schema::validate::{validate_upgrade_values, SchemaKey, ValidationError};
 
 pub use run::run;
 pub use schema::main::get_schema_for_chart;
@@ -16,148 +40,254 @@ fn greet(name: &str) -> String {
 }
 
 #[tauri::command]
-async fn list_helm_releases(app: tauri::AppHandle) -> Result<String, String> {
-    // Get the shell plugin
-    let shell = app.shell();
-
-    // Run the helm command
-    let output = shell
-        .command("helm")
-        .args(["ls", "-A", "-o", "json"]) // List all, all-namespaces, output as JSON
-        .output()
-        .await
-        .map_err(|e| format!("Helm command failed: {}", e))?;
-
-    if !output.status.success() {
-        return Err(String::from_utf8(output.stderr)
-            .unwrap_or_else(|_| "Helm command failed with non-UTF8 error".into()));
-    }
-
-    // Return the raw JSON string
-    Ok(String::from_utf8(output.stdout).unwrap_or_default())
-}
-
-
-
-// Helper function to flatten JSON into --set arguments
-fn json_to_set_args(prefix: &str, value: &serde_json::Value, args: &mut Vec<String>) {
-    match value {
-        serde_json::Value::Object(map) => {
-            for (key, val) in map {
-                let new_prefix = if prefix.is_empty() {
-                    key.clone()
-                } else {
-                    format!("{}.{}", prefix, key)
-                };
-                json_to_set_args(&new_prefix, val, args);
-            }
-        }
-        serde_json::Value::Array(arr) => {
-            for (i, val) in arr.iter().enumerate() {
-                let new_prefix = format!("{}[{}]", prefix, i);
-                json_to_set_args(&new_prefix, val, args);
-            }
-        }
-        serde_json::Value::String(s) => {
-            args.push("--set".into());
-            args.push(format!("{}={}", prefix, s));
-        }
-        serde_json::Value::Number(n) => {
-            args.push("--set".into());
-            args.push(format!("{}={}", prefix, n));
-        }
-        serde_json::Value::Bool(b) => {
-            args.push("--set".into());
-            args.push(format!("{}={}", prefix, b));
-        }
-        serde_json::Value::Null => {
-            args.push("--set".into());
-            args.push(format!("{}=null", prefix));
-        }
-    }
+async fn list_helm_releases(
+    runner: tauri::State<'_, SharedRunner>,
+    query: Option<ReleaseQuery>,
+) -> Result<Vec<HelmRelease>, RudderError> {
+    list_releases(runner.inner().as_ref(), &query.unwrap_or_default()).await
 }
 
 #[tauri::command]
-async fn set_kubeconfig(config_text: String) -> Result<(), String> {
+async fn set_kubeconfig(config_text: String) -> Result<(), RudderError> {
     use std::env;
     use std::fs;
 
     // Create kubeconfig directory if it doesn't exist
     let kube_dir = dirs::home_dir()
-        .ok_or_else(|| "Could not find home directory".to_string())?
+        .ok_or_else(|| RudderError::io("Could not find home directory"))?
         .join(".kube");
 
     fs::create_dir_all(&kube_dir)
-        .map_err(|e| format!("Failed to create .kube directory: {}", e))?;
+        .map_err(|e| RudderError::io(format!("Failed to create .kube directory: {}", e)))?;
 
     // Write kubeconfig file
     let kubeconfig_path = kube_dir.join("config");
     fs::write(&kubeconfig_path, config_text)
-        .map_err(|e| format!("Failed to write kubeconfig: {}", e))?;
+        .map_err(|e| RudderError::io(format!("Failed to write kubeconfig: {}", e)))?;
 
     // Set KUBECONFIG environment variable
     env::set_var(
         "KUBECONFIG",
         kubeconfig_path
             .to_str()
-            .ok_or_else(|| "Invalid path".to_string())?,
+            .ok_or_else(|| RudderError::io("Invalid path"))?,
     );
 
     Ok(())
 }
 
 #[tauri::command]
-async fn helm_upgrade(
-    app: tauri::AppHandle,
+#[allow(clippy::too_many_arguments)]
+async fn helm_upgrade<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    runner: tauri::State<'_, SharedRunner>,
+    db: tauri::State<'_, DbConnection>,
     release_name: String,
     chart_path: String,
     values_json: String,
-) -> Result<String, String> {
-    let shell = app.shell();
-
-    // Parse the form data
-    let values: serde_json::Value =
-        serde_json::from_str(&values_json).map_err(|e| format!("Invalid JSON values: {}", e))?;
-
-    // Build the --set arguments
-    let mut set_args: Vec<String> = Vec::new();
-    json_to_set_args("", &values, &mut set_args);
+    options: UpgradeOptions,
+    schema_key: Option<SchemaKey>,
+    operation_id: Option<String>,
+    skip_validation: Option<bool>,
+) -> Result<String, RudderError> {
+    validate_release_name(&release_name)?;
+    let option_args = options.to_args()?;
+
+    // Parse the form data and hand it to helm as a values file
+    let values = parse_values(&values_json).map_err(RudderError::invalid_values)?;
+    if !skip_validation.unwrap_or(false) {
+        validate_upgrade_values(&db, &chart_path, options.version.as_deref(), schema_key.as_ref(), &values)?;
+    }
+    let values_file = ValuesFile::write(&values).map_err(RudderError::io)?;
 
     // Build and run the command
     let mut args = vec![
         "upgrade".to_string(),
         "--install".to_string(),
         release_name,
-        chart_path,
+        // Local chart directories and archives are passed to helm as absolute paths
+        helm::local_chart::resolve_chart_path(&chart_path),
     ];
-    args.extend(set_args);
+    args.extend(option_args);
+    args.push("-f".to_string());
+    args.push(values_file.path_arg());
+
+    // Stream helm's output to the UI while the upgrade runs
+    let operation_id = operation_id.unwrap_or_else(new_operation_id);
+    let output = run_with_progress(&app, runner.inner().as_ref(), &operation_id, "helm", &args)
+        .await
+        .map_err(|e| RudderError::spawn_failed("Helm command failed", e))?;
+
+    if !output.success() {
+        return Err(RudderError::command_failed("Helm command failed", &output));
+    }
+
+    Ok(String::from_utf8(output.stdout).unwrap_or_default())
+}
+
+#[tauri::command]
+async fn prepare_install(
+    runner: tauri::State<'_, SharedRunner>,
+    db: tauri::State<'_, DbConnection>,
+    chart: String,
+    version: Option<String>,
+    release_name: Option<String>,
+    namespace: Option<String>,
+) -> Result<InstallPreparation, RudderError> {
+    let chart_ref = ChartRef::parse(&chart)?;
+    let versions = helm::install::list_chart_versions(runner.inner().as_ref(), &chart_ref).await?;
+
+    let version = match version.or_else(|| versions.first().map(|v| v.version.clone())) {
+        Some(version) => version,
+        None => {
+            return Err(RudderError::new(
+                ErrorKind::ChartNotFound,
+                format!("No versions found for chart {}", chart),
+            ))
+        }
+    };
+
+    // Same pipeline as for deployed releases, so the schema is cached for the upgrade form too
+    let schema = get_schema_for_chart(
+        chart_ref.name().to_string(),
+        version.clone(),
+        chart_ref.cache_repo(),
+        namespace.clone(),
+        None,
+        runner.clone(),
+        db,
+    )
+    .await?;
+    let schema = serde_json::from_str(&schema)
+        .map_err(|e| RudderError::new(ErrorKind::CommandFailed, format!("Invalid chart schema: {}", e)))?;
+
+    let release_name_available = match (&release_name, &namespace) {
+        (Some(release_name), Some(namespace)) => {
+            validate_release_name(release_name)?;
+            let existing = helm::install::existing_release(runner.inner().as_ref(), release_name, namespace).await?;
+            Some(existing.is_none())
+        }
+        _ => None,
+    };
+
+    Ok(InstallPreparation {
+        chart,
+        versions,
+        version,
+        schema,
+        release_name_available,
+    })
+}
 
-    let output = shell
-        .command("helm")
-        .args(args)
-        .output()
+#[tauri::command]
+async fn install_chart<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    runner: tauri::State<'_, SharedRunner>,
+    request: InstallRequest,
+    operation_id: Option<String>,
+) -> Result<String, RudderError> {
+    request.validate()?;
+    ensure_release_name_available(runner.inner().as_ref(), &request.release_name, &request.namespace).await?;
+
+    // Parse the form data and hand it to helm as a values file
+    let values = parse_values(&request.values_json).map_err(RudderError::invalid_values)?;
+    let values_file = ValuesFile::write(&values).map_err(RudderError::io)?;
+    let args = request.to_args(&values_file.path_arg())?;
+
+    // Stream helm's output to the UI while the install runs
+    let operation_id = operation_id.unwrap_or_else(new_operation_id);
+    let output = run_with_progress(&app, runner.inner().as_ref(), &operation_id, "helm", &args)
         .await
-        .map_err(|e| format!("Helm command failed: {}", e))?;
+        .map_err(|e| RudderError::spawn_failed("Helm install command failed", e))?;
 
-    if !output.status.success() {
-        return Err(String::from_utf8(output.stderr)
-            .unwrap_or_else(|_| "Helm command failed with non-UTF8 error".into()));
+    if !output.success() {
+        return Err(RudderError::command_failed("Helm install command failed", &output));
     }
 
     Ok(String::from_utf8(output.stdout).unwrap_or_default())
 }
 
+#[tauri::command]
+async fn list_repos(runner: tauri::State<'_, SharedRunner>) -> Result<Vec<HelmRepo>, RudderError> {
+    helm::repo::list_repos(runner.inner().as_ref()).await
+}
+
+#[tauri::command]
+async fn add_repo(
+    runner: tauri::State<'_, SharedRunner>,
+    request: AddRepoRequest,
+) -> Result<HelmRepo, RudderError> {
+    helm::repo::add_repo(runner.inner().as_ref(), &request).await
+}
 
 #[tauri::command]
-async fn list_cached_schemas(db: tauri::State<'_, DbConnection>) -> Result<Vec<crate::db::schemas::ChartSchema>, String> {
-    schemas::list_cached_schemas(&db)
+async fn remove_repo(runner: tauri::State<'_, SharedRunner>, name: String) -> Result<(), RudderError> {
+    helm::repo::remove_repo(runner.inner().as_ref(), &name).await
 }
 
 #[tauri::command]
-async fn clear_schema_cache(db: tauri::State<'_, DbConnection>) -> Result<String, String> {
+async fn update_repos(
+    runner: tauri::State<'_, SharedRunner>,
+    names: Option<Vec<String>>,
+) -> Result<Vec<RepoUpdateResult>, RudderError> {
+    // Without names every configured repository is updated
+    helm::repo::update_repos(runner.inner().as_ref(), &names.unwrap_or_default()).await
+}
+
+#[tauri::command]
+async fn registry_login(
+    runner: tauri::State<'_, SharedRunner>,
+    login: RegistryLogin,
+) -> Result<(), RudderError> {
+    // Credentials are stored by helm and used for later pulls from the registry
+    helm::oci::registry_login(runner.inner().as_ref(), &login).await
+}
+
+#[tauri::command]
+async fn search_charts(
+    runner: tauri::State<'_, SharedRunner>,
+    db: tauri::State<'_, DbConnection>,
+    query: Option<ChartSearchQuery>,
+) -> Result<Vec<ChartMatch>, RudderError> {
+    // Only helm's local cache is read; repository indexes changed by `helm repo update` are picked up here
+    let cache_dir = helm::catalog::repository_cache_dir(runner.inner().as_ref()).await;
+    helm::catalog::sync_catalog(&db, &cache_dir)?;
+
+    let charts = db::charts::list_catalog_charts(&db).map_err(RudderError::database)?;
+    Ok(helm::catalog::search(charts, &query.unwrap_or_default()))
+}
+
+#[tauri::command]
+async fn register_local_chart(
+    db: tauri::State<'_, DbConnection>,
+    path: String,
+) -> Result<LocalChart, RudderError> {
+    // Registering again re-reads the chart and refreshes its schema if it changed on disk
+    helm::local_chart::register_local_chart(&db, &path)
+}
+
+#[tauri::command]
+async fn list_local_charts(
+    db: tauri::State<'_, DbConnection>,
+) -> Result<Vec<LocalChartEntry>, RudderError> {
+    db::local_charts::list_local_charts(&db).map_err(RudderError::database)
+}
+
+#[tauri::command]
+async fn remove_local_chart(db: tauri::State<'_, DbConnection>, path: String) -> Result<(), RudderError> {
+    helm::local_chart::unregister_local_chart(&db, &path)
+}
+
+#[tauri::command]
+async fn list_cached_schemas(db: tauri::State<'_, DbConnection>) -> Result<Vec<crate::db::schemas::ChartSchema>, RudderError> {
+    schemas::list_cached_schemas(&db).map_err(RudderError::database)
+}
+
+#[tauri::command]
+async fn clear_schema_cache(db: tauri::State<'_, DbConnection>) -> Result<String, RudderError> {
     match schemas::clear_all_schemas(&db) {
         Ok(count) => Ok(format!("Cleared {} schema cache entries", count)),
-        Err(e) => Err(e),
+        Err(e) => Err(RudderError::database(e)),
     }
 }
 
@@ -167,118 +297,172 @@ async fn delete_schema_cache_entry(
     chart_version: String,
     repo_name: String,
     db: tauri::State<'_, DbConnection>,
-) -> Result<String, String> {
+) -> Result<String, RudderError> {
     match schemas::delete_chart_schema(&db, &chart_name, &chart_version, &repo_name) {
         Ok(_) => Ok(format!(
             "Cache entry removed for {}/{} from {}",
             chart_name, chart_version, repo_name
         )),
-        Err(e) => Err(e),
+        Err(e) => Err(RudderError::database(e)),
     }
 }
 
 #[tauri::command]
 async fn get_helm_history(
-    app: tauri::AppHandle,
+    runner: tauri::State<'_, SharedRunner>,
     release_name: String,
     namespace: String,
-) -> Result<String, String> {
-    let shell = app.shell();
-
-    let output = shell
-        .command("helm")
-        .args(["history", &release_name, "-n", &namespace, "-o", "json"])
-        .output()
+) -> Result<String, RudderError> {
+    let output = runner
+        .run("helm", ["history", &release_name, "-n", &namespace, "-o", "json"])
         .await
-        .map_err(|e| format!("Helm history command failed: {}", e))?;
+        .map_err(|e| RudderError::spawn_failed("Helm history command failed", e))?;
 
-    if !output.status.success() {
-        return Err(String::from_utf8(output.stderr)
-            .unwrap_or_else(|_| "Helm history command failed with non-UTF8 error".into()));
+    if !output.success() {
+        return Err(RudderError::command_failed("Helm history command failed", &output));
     }
 
     Ok(String::from_utf8(output.stdout).unwrap_or_default())
 }
 
 #[tauri::command]
-async fn helm_rollback(
-    app: tauri::AppHandle,
+async fn helm_rollback<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    runner: tauri::State<'_, SharedRunner>,
     release_name: String,
     namespace: String,
     revision: u32,
-) -> Result<String, String> {
-    let shell = app.shell();
-
-    let output = shell
-        .command("helm")
-        .args([
-            "rollback",
-            &release_name,
-            &revision.to_string(),
-            "-n",
-            &namespace,
-        ])
-        .output()
+    operation_id: Option<String>,
+) -> Result<String, RudderError> {
+    let args = vec![
+        "rollback".to_string(),
+        release_name,
+        revision.to_string(),
+        "-n".to_string(),
+        namespace,
+    ];
+
+    let operation_id = operation_id.unwrap_or_else(new_operation_id);
+    let output = run_with_progress(&app, runner.inner().as_ref(), &operation_id, "helm", &args)
         .await
-        .map_err(|e| format!("Helm rollback command failed: {}", e))?;
+        .map_err(|e| RudderError::spawn_failed("Helm rollback command failed", e))?;
 
-    if !output.status.success() {
-        return Err(String::from_utf8(output.stderr)
-            .unwrap_or_else(|_| "Helm rollback command failed with non-UTF8 error".into()));
+    if !output.success() {
+        return Err(RudderError::command_failed("Helm rollback command failed", &output));
     }
 
     Ok(String::from_utf8(output.stdout).unwrap_or_default())
 }
 
 #[tauri::command]
-async fn get_kube_context(app: tauri::AppHandle) -> Result<String, String> {
-    let shell = app.shell();
+async fn uninstall_release<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    runner: tauri::State<'_, SharedRunner>,
+    confirmations: tauri::State<'_, SharedConfirmations>,
+    request: UninstallRequest,
+    operation_id: Option<String>,
+) -> Result<UninstallOutcome, RudderError> {
+    // Without a token only preview; nothing is deleted until the preview is confirmed
+    let Some(token) = &request.confirmation_token else {
+        let preview = helm::uninstall::preview_uninstall(runner.inner().as_ref(), &confirmations, &request).await?;
+        return Ok(UninstallOutcome::PendingConfirmation(preview));
+    };
+    let args = request.to_args()?;
+    confirmations.redeem(token, &request)?;
 
-    let output = shell
-        .command("kubectl")
-        .args(["config", "current-context"])
-        .output()
+    let operation_id = operation_id.unwrap_or_else(new_operation_id);
+    let output = run_with_progress(&app, runner.inner().as_ref(), &operation_id, "helm", &args)
         .await
-        .map_err(|e| format!("kubectl command failed: {}", e))?;
+        .map_err(|e| RudderError::spawn_failed("Helm uninstall command failed", e))?;
 
-    if !output.status.success() {
-        return Err(String::from_utf8(output.stderr)
-            .unwrap_or_else(|_| "kubectl command failed with non-UTF8 error".into()));
+    if !output.success() {
+        return Err(RudderError::command_failed("Helm uninstall command failed", &output));
     }
 
-    Ok(String::from_utf8(output.stdout).unwrap_or_default())
+    Ok(UninstallOutcome::Uninstalled {
+        output: String::from_utf8(output.stdout).unwrap_or_default(),
+    })
 }
 
 #[tauri::command]
-async fn list_kube_contexts(app: tauri::AppHandle) -> Result<String, String> {
-    let shell = app.shell();
+async fn get_release_status(
+    runner: tauri::State<'_, SharedRunner>,
+    release_name: String,
+    namespace: String,
+) -> Result<ReleaseStatusDetail, RudderError> {
+    helm::status::get_release_status(runner.inner().as_ref(), &release_name, &namespace).await
+}
 
+#[tauri::command]
+async fn run_release_tests(
+    runner: tauri::State<'_, SharedRunner>,
+    db: tauri::State<'_, DbConnection>,
+    request: ReleaseTestRequest,
+) -> Result<ReleaseTestRun, RudderError> {
+    let mut run = helm::release_test::run_release_tests(runner.inner().as_ref(), &request).await?;
+    run.id = Some(db::test_runs::insert_test_run(&db, &run).map_err(RudderError::database)?);
+    Ok(run)
+}
+
+#[tauri::command]
+async fn list_release_test_runs(
+    db: tauri::State<'_, DbConnection>,
+    release_name: String,
+    namespace: String,
+    limit: Option<usize>,
+) -> Result<Vec<ReleaseTestRun>, RudderError> {
+    db::test_runs::list_test_runs(&db, &release_name, &namespace, limit.unwrap_or(20)).map_err(RudderError::database)
+}
+
+#[tauri::command]
+async fn compare_revisions(
+    runner: tauri::State<'_, SharedRunner>,
+    release_name: String,
+    namespace: String,
+    from: Option<u32>,
+    to: u32,
+) -> Result<RevisionComparison, RudderError> {
+    // Without `from` this compares the current revision with `to`, i.e. previews a rollback
+    helm::revision::compare_revisions(runner.inner().as_ref(), &release_name, &namespace, from, to).await
+}
+
+#[tauri::command]
+async fn get_kube_context(runner: tauri::State<'_, SharedRunner>) -> Result<String, RudderError> {
+    let output = runner
+        .run("kubectl", ["config", "current-context"])
+        .await
+        .map_err(|e| RudderError::spawn_failed("kubectl command failed", e))?;
+
+    if !output.success() {
+        return Err(RudderError::command_failed("kubectl command failed", &output));
+    }
+
+    Ok(String::from_utf8(output.stdout).unwrap_or_default())
+}
+
+#[tauri::command]
+async fn list_kube_contexts(runner: tauri::State<'_, SharedRunner>) -> Result<String, RudderError> {
     // Get all contexts as JSON-like output
-    let output = shell
-        .command("kubectl")
-        .args(["config", "get-contexts", "-o", "name"])
-        .output()
+    let output = runner
+        .run("kubectl", ["config", "get-contexts", "-o", "name"])
         .await
-        .map_err(|e| format!("kubectl command failed: {}", e))?;
+        .map_err(|e| RudderError::spawn_failed("kubectl command failed", e))?;
 
-    if !output.status.success() {
-        return Err(String::from_utf8(output.stderr)
-            .unwrap_or_else(|_| "kubectl command failed".into()));
+    if !output.success() {
+        return Err(RudderError::command_failed("kubectl command failed", &output));
     }
 
     let contexts_str = String::from_utf8(output.stdout).unwrap_or_default();
     let contexts: Vec<&str> = contexts_str.lines().filter(|s| !s.is_empty()).collect();
     
     // Get current context
-    let current_output = shell
-        .command("kubectl")
-        .args(["config", "current-context"])
-        .output()
+    let current_output = runner
+        .run("kubectl", ["config", "current-context"])
         .await
         .ok();
     
     let current_context = current_output
-        .filter(|o| o.status.success())
+        .filter(|o| o.success())
         .and_then(|o| String::from_utf8(o.stdout).ok())
         .unwrap_or_default()
         .trim()
@@ -294,19 +478,14 @@ async fn list_kube_contexts(app: tauri::AppHandle) -> Result<String, String> {
 }
 
 #[tauri::command]
-async fn switch_kube_context(app: tauri::AppHandle, context_name: String) -> Result<String, String> {
-    let shell = app.shell();
-
-    let output = shell
-        .command("kubectl")
-        .args(["config", "use-context", &context_name])
-        .output()
+async fn switch_kube_context(runner: tauri::State<'_, SharedRunner>, context_name: String) -> Result<String, RudderError> {
+    let output = runner
+        .run("kubectl", ["config", "use-context", &context_name])
         .await
-        .map_err(|e| format!("kubectl command failed: {}", e))?;
+        .map_err(|e| RudderError::spawn_failed("kubectl command failed", e))?;
 
-    if !output.status.success() {
-        return Err(String::from_utf8(output.stderr)
-            .unwrap_or_else(|_| "Failed to switch context".into()));
+    if !output.success() {
+        return Err(RudderError::command_failed("Failed to switch context", &output));
     }
 
     Ok(format!("Switched to context: {}", context_name))
@@ -314,42 +493,35 @@ async fn switch_kube_context(app: tauri::AppHandle, context_name: String) -> Res
 
 #[tauri::command]
 async fn get_release_pods(
-    app: tauri::AppHandle,
+    runner: tauri::State<'_, SharedRunner>,
     release_name: String,
     namespace: String,
-) -> Result<String, String> {
-    let shell = app.shell();
-
+) -> Result<String, RudderError> {
     // Get pods with the helm release label
-    let output = shell
-        .command("kubectl")
-        .args([
+    let output = runner
+        .run("kubectl", [
             "get", "pods",
             "-n", &namespace,
             "-l", &format!("app.kubernetes.io/instance={}", release_name),
             "-o", "json",
         ])
-        .output()
         .await
-        .map_err(|e| format!("kubectl command failed: {}", e))?;
+        .map_err(|e| RudderError::spawn_failed("kubectl command failed", e))?;
 
-    if !output.status.success() {
+    if !output.success() {
         // Try alternative label selector (some charts use different labels)
-        let output2 = shell
-            .command("kubectl")
-            .args([
+        let output2 = runner
+            .run("kubectl", [
                 "get", "pods",
                 "-n", &namespace,
                 "-l", &format!("release={}", release_name),
                 "-o", "json",
             ])
-            .output()
             .await
-            .map_err(|e| format!("kubectl command failed: {}", e))?;
+            .map_err(|e| RudderError::spawn_failed("kubectl command failed", e))?;
 
-        if !output2.status.success() {
-            return Err(String::from_utf8(output.stderr)
-                .unwrap_or_else(|_| "kubectl command failed".into()));
+        if !output2.success() {
+            return Err(RudderError::command_failed("kubectl command failed", &output));
         }
 
         return parse_pods_json(&String::from_utf8(output2.stdout).unwrap_or_default());
@@ -358,9 +530,9 @@ async fn get_release_pods(
     parse_pods_json(&String::from_utf8(output.stdout).unwrap_or_default())
 }
 
-fn parse_pods_json(json_str: &str) -> Result<String, String> {
+fn parse_pods_json(json_str: &str) -> Result<String, RudderError> {
     let pods_data: serde_json::Value = serde_json::from_str(json_str)
-        .map_err(|e| format!("Failed to parse pods JSON: {}", e))?;
+        .map_err(|e| RudderError::new(ErrorKind::CommandFailed, format!("Failed to parse pods JSON: {}", e)))?;
 
     let mut pods = Vec::new();
     
@@ -497,15 +669,13 @@ fn parse_pods_json(json_str: &str) -> Result<String, String> {
 
 #[tauri::command]
 async fn get_pod_logs(
-    app: tauri::AppHandle,
+    runner: tauri::State<'_, SharedRunner>,
     pod_name: String,
     namespace: String,
     container: Option<String>,
     tail_lines: Option<u32>,
     timestamps: Option<bool>,
-) -> Result<String, String> {
-    let shell = app.shell();
-
+) -> Result<String, RudderError> {
     let mut args = vec![
         "logs".to_string(),
         pod_name,
@@ -527,16 +697,13 @@ async fn get_pod_logs(
         args.push("--timestamps".to_string());
     }
 
-    let output = shell
-        .command("kubectl")
-        .args(&args)
-        .output()
+    let output = runner
+        .run("kubectl", &args)
         .await
-        .map_err(|e| format!("kubectl logs command failed: {}", e))?;
+        .map_err(|e| RudderError::spawn_failed("kubectl logs command failed", e))?;
 
-    if !output.status.success() {
-        return Err(String::from_utf8(output.stderr)
-            .unwrap_or_else(|_| "kubectl logs command failed".into()));
+    if !output.success() {
+        return Err(RudderError::command_failed("kubectl logs command failed", &output));
     }
 
     Ok(String::from_utf8(output.stdout).unwrap_or_default())
@@ -544,41 +711,35 @@ async fn get_pod_logs(
 
 #[tauri::command]
 async fn diagnose_release(
-    app: tauri::AppHandle,
+    runner: tauri::State<'_, SharedRunner>,
     release_name: String,
     namespace: String,
-) -> Result<String, String> {
-    let shell = app.shell();
-    
+) -> Result<String, RudderError> {
     // Get pods for the release
-    let pods_output = shell
-        .command("kubectl")
-        .args([
+    let pods_output = runner
+        .run("kubectl", [
             "get", "pods",
             "-n", &namespace,
             "-l", &format!("app.kubernetes.io/instance={}", release_name),
             "-o", "json",
         ])
-        .output()
         .await;
 
     let pods_json = match pods_output {
-        Ok(output) if output.status.success() => {
+        Ok(output) if output.success() => {
             String::from_utf8(output.stdout).unwrap_or_default()
         }
         _ => {
             // Try alternative label
-            let output2 = shell
-                .command("kubectl")
-                .args([
+            let output2 = runner
+                .run("kubectl", [
                     "get", "pods",
                     "-n", &namespace,
                     "-l", &format!("release={}", release_name),
                     "-o", "json",
                 ])
-                .output()
                 .await
-                .map_err(|e| format!("kubectl command failed: {}", e))?;
+                .map_err(|e| RudderError::spawn_failed("kubectl command failed", e))?;
             
             String::from_utf8(output2.stdout).unwrap_or_default()
         }
@@ -682,19 +843,17 @@ async fn diagnose_release(
             // Check pending state
             if phase == "Pending" {
                 // Get events for more context
-                let events_output = shell
-                    .command("kubectl")
-                    .args([
+                let events_output = runner
+                    .run("kubectl", [
                         "get", "events",
                         "-n", &namespace,
                         "--field-selector", &format!("involvedObject.name={}", pod_name),
                         "-o", "json",
                     ])
-                    .output()
                     .await;
 
                 if let Ok(output) = events_output {
-                    if output.status.success() {
+                    if output.success() {
                         let events_json = String::from_utf8(output.stdout).unwrap_or_default();
                         if let Ok(events) = serde_json::from_str::<serde_json::Value>(&events_json) {
                             if let Some(event_items) = events["items"].as_array() {
@@ -885,28 +1044,27 @@ fn calculate_age(timestamp: &str) -> String {
 }
 
 #[tauri::command]
-async fn restart_deployment(
-    app: tauri::AppHandle,
+async fn restart_deployment<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    runner: tauri::State<'_, SharedRunner>,
     release_name: String,
     namespace: String,
-) -> Result<String, String> {
-    let shell = app.shell();
-
-    let output = shell
-        .command("kubectl")
-        .args([
-            "rollout", "restart",
-            "deployment",
-            "-n", &namespace,
-            "-l", &format!("app.kubernetes.io/instance={}", release_name),
-        ])
-        .output()
+    operation_id: Option<String>,
+) -> Result<String, RudderError> {
+    let args = vec![
+        "rollout".to_string(), "restart".to_string(),
+        "deployment".to_string(),
+        "-n".to_string(), namespace,
+        "-l".to_string(), format!("app.kubernetes.io/instance={}", release_name),
+    ];
+
+    let operation_id = operation_id.unwrap_or_else(new_operation_id);
+    let output = run_with_progress(&app, runner.inner().as_ref(), &operation_id, "kubectl", &args)
         .await
-        .map_err(|e| format!("kubectl command failed: {}", e))?;
+        .map_err(|e| RudderError::spawn_failed("kubectl command failed", e))?;
 
-    if !output.status.success() {
-        return Err(String::from_utf8(output.stderr)
-            .unwrap_or_else(|_| "Restart failed".into()));
+    if !output.success() {
+        return Err(RudderError::command_failed("Restart failed", &output));
     }
 
     Ok(String::from_utf8(output.stdout).unwrap_or_else(|_| "Deployment restarted".into()))
@@ -914,25 +1072,20 @@ async fn restart_deployment(
 
 #[tauri::command]
 async fn delete_failed_pods(
-    app: tauri::AppHandle,
+    runner: tauri::State<'_, SharedRunner>,
     namespace: String,
-) -> Result<String, String> {
-    let shell = app.shell();
-
-    let output = shell
-        .command("kubectl")
-        .args([
+) -> Result<String, RudderError> {
+    let output = runner
+        .run("kubectl", [
             "delete", "pods",
             "-n", &namespace,
             "--field-selector=status.phase=Failed",
         ])
-        .output()
         .await
-        .map_err(|e| format!("kubectl command failed: {}", e))?;
+        .map_err(|e| RudderError::spawn_failed("kubectl command failed", e))?;
 
-    if !output.status.success() {
-        return Err(String::from_utf8(output.stderr)
-            .unwrap_or_else(|_| "Delete failed".into()));
+    if !output.success() {
+        return Err(RudderError::command_failed("Delete failed", &output));
     }
 
     Ok(String::from_utf8(output.stdout).unwrap_or_else(|_| "Failed pods deleted".into()))
@@ -940,116 +1093,169 @@ async fn delete_failed_pods(
 
 #[tauri::command]
 async fn describe_pod(
-    app: tauri::AppHandle,
+    runner: tauri::State<'_, SharedRunner>,
     pod_name: String,
     namespace: String,
-) -> Result<String, String> {
-    let shell = app.shell();
-
-    let output = shell
-        .command("kubectl")
-        .args(["describe", "pod", &pod_name, "-n", &namespace])
-        .output()
+) -> Result<String, RudderError> {
+    let output = runner
+        .run("kubectl", ["describe", "pod", &pod_name, "-n", &namespace])
         .await
-        .map_err(|e| format!("kubectl command failed: {}", e))?;
+        .map_err(|e| RudderError::spawn_failed("kubectl command failed", e))?;
 
-    if !output.status.success() {
-        return Err(String::from_utf8(output.stderr)
-            .unwrap_or_else(|_| "Describe failed".into()));
+    if !output.success() {
+        return Err(RudderError::command_failed("Describe failed", &output));
     }
 
     Ok(String::from_utf8(output.stdout).unwrap_or_default())
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn helm_dry_run(
-    app: tauri::AppHandle,
+    runner: tauri::State<'_, SharedRunner>,
+    db: tauri::State<'_, DbConnection>,
     release_name: String,
     chart_path: String,
-    namespace: String,
     values_json: String,
-) -> Result<String, String> {
-    let shell = app.shell();
+    options: UpgradeOptions,
+    schema_key: Option<SchemaKey>,
+    skip_validation: Option<bool>,
+) -> Result<String, RudderError> {
+    if !skip_validation.unwrap_or(false) {
+        let values = parse_values(&values_json).map_err(RudderError::invalid_values)?;
+        validate_upgrade_values(&db, &chart_path, options.version.as_deref(), schema_key.as_ref(), &values)?;
+    }
+    helm::upgrade::dry_run(runner.inner().as_ref(), &release_name, &chart_path, &values_json, &options).await
+}
 
-    // Parse the form data
-    let values: serde_json::Value =
-        serde_json::from_str(&values_json).map_err(|e| format!("Invalid JSON values: {}", e))?;
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn preview_upgrade(
+    runner: tauri::State<'_, SharedRunner>,
+    db: tauri::State<'_, DbConnection>,
+    release_name: String,
+    chart_path: String,
+    values_json: String,
+    options: UpgradeOptions,
+    schema_key: Option<SchemaKey>,
+    skip_validation: Option<bool>,
+) -> Result<UpgradePreview, RudderError> {
+    // The preview is a dry-run, so the values are checked the same way
+    if !skip_validation.unwrap_or(false) {
+        let values = parse_values(&values_json).map_err(RudderError::invalid_values)?;
+        validate_upgrade_values(&db, &chart_path, options.version.as_deref(), schema_key.as_ref(), &values)?;
+    }
+    helm::preview::preview_upgrade(runner.inner().as_ref(), &release_name, &chart_path, &values_json, &options).await
+}
 
-    // Build the --set arguments
-    let mut set_args: Vec<String> = Vec::new();
-    json_to_set_args("", &values, &mut set_args);
+/// Check form values against the cached schema of a chart, for feedback while editing.
+///
+/// Returns no errors if no declared schema is cached for the chart.
+#[tauri::command]
+async fn validate_values(
+    db: tauri::State<'_, DbConnection>,
+    chart_name: String,
+    chart_version: String,
+    repo_name: String,
+    values_json: String,
+) -> Result<Vec<ValidationError>, RudderError> {
+    let values = parse_values(&values_json).map_err(RudderError::invalid_values)?;
+    let schema_key = SchemaKey { chart_name, chart_version, repo_name };
+    Ok(schema_key
+        .cached_schema(&db)?
+        .map(|c| schema::validate::validate_chart_values(&c, &values))
+        .unwrap_or_default())
+}
 
-    // Build and run the command with --dry-run flag
-    let mut args = vec![
-        "upgrade".to_string(),
-        "--install".to_string(),
-        release_name,
-        chart_path,
-        "-n".to_string(),
-        namespace,
-        "--dry-run".to_string(),
-    ];
-    args.extend(set_args);
+/// Values set on a release, as YAML unless `output` is `json`.
+#[tauri::command]
+async fn get_release_values(
+    runner: tauri::State<'_, SharedRunner>,
+    release_name: String,
+    namespace: String,
+    output: Option<String>,
+) -> Result<String, RudderError> {
+    let format = match output.as_deref() {
+        None | Some("yaml") => "yaml",
+        Some("json") => "json",
+        Some(other) => {
+            return Err(RudderError::invalid_input(format!(
+                "Unsupported values output '{}': expected yaml or json",
+                other
+            )))
+        }
+    };
 
-    let output = shell
-        .command("helm")
-        .args(&args)
-        .output()
+    let output = runner
+        .run("helm", ["get", "values", &release_name, "-n", &namespace, "-o", format])
         .await
-        .map_err(|e| format!("Helm dry-run command failed: {}", e))?;
+        .map_err(|e| RudderError::spawn_failed("Helm get values command failed", e))?;
 
-    if !output.status.success() {
-        return Err(String::from_utf8(output.stderr)
-            .unwrap_or_else(|_| "Helm dry-run command failed".into()));
+    if !output.success() {
+        return Err(RudderError::command_failed("Helm get values command failed", &output));
     }
 
     Ok(String::from_utf8(output.stdout).unwrap_or_default())
 }
 
 #[tauri::command]
-async fn get_release_values(
-    app: tauri::AppHandle,
+async fn get_release_manifest(
+    runner: tauri::State<'_, SharedRunner>,
     release_name: String,
     namespace: String,
-) -> Result<String, String> {
-    let shell = app.shell();
-
-    let output = shell
-        .command("helm")
-        .args(["get", "values", &release_name, "-n", &namespace, "-o", "yaml"])
-        .output()
+) -> Result<String, RudderError> {
+    let output = runner
+        .run("helm", ["get", "manifest", &release_name, "-n", &namespace])
         .await
-        .map_err(|e| format!("Helm get values command failed: {}", e))?;
+        .map_err(|e| RudderError::spawn_failed("Helm get manifest command failed", e))?;
 
-    if !output.status.success() {
-        return Err(String::from_utf8(output.stderr)
-            .unwrap_or_else(|_| "Helm get values command failed".into()));
+    if !output.success() {
+        return Err(RudderError::command_failed("Helm get manifest command failed", &output));
     }
 
     Ok(String::from_utf8(output.stdout).unwrap_or_default())
 }
 
 #[tauri::command]
-async fn get_release_manifest(
-    app: tauri::AppHandle,
+async fn get_release_resources(
+    runner: tauri::State<'_, SharedRunner>,
     release_name: String,
     namespace: String,
-) -> Result<String, String> {
-    let shell = app.shell();
+    include_status: Option<bool>,
+) -> Result<Vec<ReleaseResource>, RudderError> {
+    helm::inventory::get_release_resources(
+        runner.inner().as_ref(),
+        &release_name,
+        &namespace,
+        include_status.unwrap_or(false),
+    )
+    .await
+}
 
-    let output = shell
-        .command("helm")
-        .args(["get", "manifest", &release_name, "-n", &namespace])
-        .output()
-        .await
-        .map_err(|e| format!("Helm get manifest command failed: {}", e))?;
+#[tauri::command]
+async fn cancel_operation(
+    registry: tauri::State<'_, SharedRegistry>,
+    operation_id: String,
+) -> Result<(), RudderError> {
+    registry.cancel(&operation_id).map_err(RudderError::invalid_input)
+}
 
-    if !output.status.success() {
-        return Err(String::from_utf8(output.stderr)
-            .unwrap_or_else(|_| "Helm get manifest command failed".into()));
-    }
+#[tauri::command]
+async fn list_running_operations(
+    registry: tauri::State<'_, SharedRegistry>,
+) -> Result<Vec<OperationInfo>, RudderError> {
+    Ok(registry.list())
+}
 
-    Ok(String::from_utf8(output.stdout).unwrap_or_default())
+#[tauri::command]
+async fn set_operation_timeout(
+    registry: tauri::State<'_, SharedRegistry>,
+    command: String,
+    timeout_secs: Option<u64>,
+) -> Result<(), RudderError> {
+    registry
+        .set_timeout(&command, timeout_secs.map(std::time::Duration::from_secs))
+        .map_err(|e| RudderError::new(ErrorKind::CommandFailed, e))
 }
 
 #[derive(serde::Serialize)]
@@ -1060,22 +1266,20 @@ struct ShellCommandResult {
 
 #[tauri::command]
 async fn run_shell_command(
-    app: tauri::AppHandle,
+    runner: tauri::State<'_, SharedRunner>,
     command: String,
     context: Option<String>,
-) -> Result<ShellCommandResult, String> {
-    let shell = app.shell();
-
+) -> Result<ShellCommandResult, RudderError> {
     // Security: Only allow kubectl and helm commands
     let trimmed = command.trim();
     if !trimmed.starts_with("kubectl") && !trimmed.starts_with("helm") {
-        return Err("Only kubectl and helm commands are allowed".into());
+        return Err(RudderError::invalid_input("Only kubectl and helm commands are allowed"));
     }
 
     // Parse the command into parts
     let parts: Vec<&str> = trimmed.split_whitespace().collect();
     if parts.is_empty() {
-        return Err("Empty command".into());
+        return Err(RudderError::invalid_input("Empty command"));
     }
 
     let program = parts[0];
@@ -1088,14 +1292,12 @@ async fn run_shell_command(
         }
     }
 
-    let output = shell
-        .command(program)
-        .args(&args)
-        .output()
+    let output = runner
+        .run(program, &args)
         .await
-        .map_err(|e| format!("Command failed: {}", e))?;
+        .map_err(|e| RudderError::spawn_failed("Command failed", e))?;
 
-    let exit_code = output.status.code().unwrap_or(-1);
+    let exit_code = output.code.unwrap_or(-1);
     let stdout = String::from_utf8_lossy(&output.stdout);
     let stderr = String::from_utf8_lossy(&output.stderr);
 
@@ -1116,14 +1318,12 @@ async fn run_shell_command(
 
 #[tauri::command]
 async fn exec_in_pod(
-    app: tauri::AppHandle,
+    runner: tauri::State<'_, SharedRunner>,
     pod_name: String,
     namespace: String,
     container: Option<String>,
     command: String,
-) -> Result<ShellCommandResult, String> {
-    let shell = app.shell();
-
+) -> Result<ShellCommandResult, RudderError> {
     // Build kubectl exec command
     let mut args = vec![
         "exec".to_string(),
@@ -1144,14 +1344,12 @@ async fn exec_in_pod(
     args.push("-c".to_string());
     args.push(command);
 
-    let output = shell
-        .command("kubectl")
-        .args(&args)
-        .output()
+    let output = runner
+        .run("kubectl", &args)
         .await
-        .map_err(|e| format!("kubectl exec failed: {}", e))?;
+        .map_err(|e| RudderError::spawn_failed("kubectl exec failed", e))?;
 
-    let exit_code = output.status.code().unwrap_or(-1);
+    let exit_code = output.code.unwrap_or(-1);
     let stdout = String::from_utf8_lossy(&output.stdout);
     let stderr = String::from_utf8_lossy(&output.stderr);
 
@@ -1168,3 +1366,695 @@ async fn exec_in_pod(
         exit_code,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use runner::fixture::FixtureRunner;
+    use tauri::Manager;
+
+    fn mock_app(fixture: &Arc<FixtureRunner>) -> tauri::App<tauri::test::MockRuntime> {
+        let app = tauri::test::mock_app();
+        let runner: SharedRunner = fixture.clone();
+        app.manage(runner);
+        app
+    }
+
+    fn manage_db(app: &tauri::App<tauri::test::MockRuntime>) {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        db::connection::create_tables(&conn).unwrap();
+        let db: DbConnection = Arc::new(Mutex::new(conn));
+        app.manage(db);
+    }
+
+    const CRASHING_PODS: &str = r#"{
+        "items": [{
+            "metadata": {"name": "web-0", "namespace": "apps", "creationTimestamp": "2024-05-01T10:00:00Z"},
+            "spec": {"containers": [{"name": "web"}], "nodeName": "node-a"},
+            "status": {
+                "phase": "Running",
+                "podIP": "10.0.0.7",
+                "containerStatuses": [{
+                    "name": "web",
+                    "ready": false,
+                    "restartCount": 7,
+                    "state": {"waiting": {"reason": "CrashLoopBackOff", "message": "back-off 5m0s"}}
+                }]
+            }
+        }]
+    }"#;
+
+    #[tokio::test]
+    async fn list_helm_releases_returns_typed_releases() {
+        let releases = r#"[
+            {"name":"web","namespace":"apps","revision":"3","updated":"2024-05-01 10:00:00 +0000 UTC","status":"deployed","chart":"nginx-15.4.0","app_version":"1.25.3"},
+            {"name":"api","namespace":"apps","revision":"7","updated":"2024-05-02 10:00:00 +0000 UTC","status":"deployed","chart":"node-21.0.1","app_version":"20.9.0"}
+        ]"#;
+        let fixture = Arc::new(FixtureRunner::new().respond("helm ls -n apps --max 0 -o json", releases));
+        let app = mock_app(&fixture);
+
+        let query = ReleaseQuery {
+            namespace: Some("apps".into()),
+            chart: Some("nginx".into()),
+            ..Default::default()
+        };
+        let result = list_helm_releases(app.state(), Some(query)).await.unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].name, "web");
+        assert_eq!(result[0].chart_version, "15.4.0");
+        assert_eq!(fixture.calls(), vec!["helm ls -n apps --max 0 -o json"]);
+    }
+
+    #[tokio::test]
+    async fn list_helm_releases_surfaces_helm_stderr() {
+        let fixture = Arc::new(FixtureRunner::new().fail(
+            "helm ls -A --max 0 -o json",
+            "Error: Kubernetes cluster unreachable",
+            1,
+        ));
+        let app = mock_app(&fixture);
+
+        let result = list_helm_releases(app.state(), None).await;
+
+        let error = result.unwrap_err();
+        assert_eq!(error.kind, ErrorKind::ClusterUnreachable);
+        assert_eq!(error.message, "Kubernetes cluster unreachable");
+        assert_eq!(error.stderr.as_deref(), Some("Error: Kubernetes cluster unreachable"));
+    }
+
+    #[tokio::test]
+    async fn get_release_pods_falls_back_to_release_label() {
+        let fixture = Arc::new(
+            FixtureRunner::new()
+                .fail(
+                    "kubectl get pods -n apps -l app.kubernetes.io/instance=web -o json",
+                    "error: selector failed",
+                    1,
+                )
+                .respond("kubectl get pods -n apps -l release=web -o json", CRASHING_PODS),
+        );
+        let app = mock_app(&fixture);
+
+        let pods: serde_json::Value = serde_json::from_str(
+            &get_release_pods(app.state(), "web".into(), "apps".into())
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(pods[0]["name"], "web-0");
+        assert_eq!(pods[0]["status"], "Running (0/1)");
+        assert_eq!(pods[0]["restarts"], 7);
+        assert_eq!(fixture.calls().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn diagnose_release_reports_crash_loops() {
+        let fixture = Arc::new(FixtureRunner::new().respond(
+            "kubectl get pods -n apps -l app.kubernetes.io/instance=web -o json",
+            CRASHING_PODS,
+        ));
+        let app = mock_app(&fixture);
+
+        let report: serde_json::Value = serde_json::from_str(
+            &diagnose_release(app.state(), "web".into(), "apps".into())
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+
+        let titles: Vec<&str> = report["pods"][0]["issues"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|issue| issue["title"].as_str())
+            .collect();
+        assert_eq!(
+            titles,
+            vec!["Container is crash-looping", "High restart count: 7"]
+        );
+        assert!(report["releaseIssues"].as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn helm_rollback_streams_progress_events() {
+        use operations::progress::{COMPLETE_EVENT, PROGRESS_EVENT};
+        use tauri::Listener;
+
+        let fixture = Arc::new(FixtureRunner::new().respond(
+            "helm rollback web 2 -n apps",
+            "Rollback was a success! Happy Helming!\n",
+        ));
+        let app = mock_app(&fixture);
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        for name in [PROGRESS_EVENT, COMPLETE_EVENT] {
+            let events = events.clone();
+            app.listen_any(name, move |event| {
+                let payload: serde_json::Value = serde_json::from_str(event.payload()).unwrap();
+                events.lock().unwrap().push(payload);
+            });
+        }
+
+        let result = helm_rollback(
+            app.handle().clone(),
+            app.state(),
+            "web".into(),
+            "apps".into(),
+            2,
+            Some("op-test".into()),
+        )
+        .await;
+
+        assert!(result.is_ok());
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                serde_json::json!({
+                    "operationId": "op-test",
+                    "stream": "stdout",
+                    "line": "Rollback was a success! Happy Helming!",
+                }),
+                serde_json::json!({
+                    "operationId": "op-test",
+                    "exitCode": 0,
+                    "success": true,
+                    "error": null,
+                }),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn helm_dry_run_passes_values_as_a_temporary_file() {
+        let fixture = Arc::new(FixtureRunner::new().respond(
+            "helm upgrade --install web ./chart -n apps --dry-run -f *",
+            "NAME: web\n",
+        ));
+        let app = mock_app(&fixture);
+        manage_db(&app);
+
+        let result = helm_dry_run(
+            app.state(),
+            app.state(),
+            "web".into(),
+            "./chart".into(),
+            r#"{"podAnnotations": {"prometheus.io/scrape": "true"}}"#.into(),
+            UpgradeOptions {
+                namespace: Some("apps".into()),
+                ..Default::default()
+            },
+            None,
+            None,
+        )
+        .await;
+
+        assert_eq!(result, Ok("NAME: web\n".to_string()));
+        let call = &fixture.calls()[0];
+        let values_path = call.rsplit(' ').next().unwrap();
+        assert!(values_path.ends_with(".json"));
+        assert!(!std::path::Path::new(values_path).exists());
+        assert!(!call.contains("--set"));
+    }
+
+    #[tokio::test]
+    async fn helm_upgrade_passes_options_and_namespace() {
+        let fixture = Arc::new(FixtureRunner::new().respond(
+            "helm upgrade --install web bitnami/nginx -n apps --atomic --timeout=10m --version=15.4.0 -f *",
+            "Release \"web\" has been upgraded. Happy Helming!\n",
+        ));
+        let app = mock_app(&fixture);
+        manage_db(&app);
+
+        let result = helm_upgrade(
+            app.handle().clone(),
+            app.state(),
+            app.state(),
+            "web".into(),
+            "bitnami/nginx".into(),
+            "{}".into(),
+            UpgradeOptions {
+                namespace: Some("apps".into()),
+                atomic: true,
+                timeout: Some("10m".into()),
+                version: Some("15.4.0".into()),
+                ..Default::default()
+            },
+            None,
+            None,
+            Some(true),
+        )
+        .await;
+
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn helm_upgrade_rejects_invalid_options_before_running_helm() {
+        let fixture = Arc::new(FixtureRunner::new());
+        let app = mock_app(&fixture);
+        manage_db(&app);
+
+        let result = helm_upgrade(
+            app.handle().clone(),
+            app.state(),
+            app.state(),
+            "web".into(),
+            "bitnami/nginx".into(),
+            "{}".into(),
+            UpgradeOptions {
+                namespace: Some("apps".into()),
+                timeout: Some("soon".into()),
+                ..Default::default()
+            },
+            None,
+            None,
+            None,
+        )
+        .await;
+
+        assert_eq!(result.unwrap_err().kind, ErrorKind::InvalidInput);
+        assert!(fixture.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn helm_upgrade_validates_values_against_the_cached_schema() {
+        let fixture = Arc::new(FixtureRunner::new().respond(
+            "helm upgrade --install web bitnami/nginx -n apps --version=15.4.0 -f *",
+            "Release \"web\" has been upgraded. Happy Helming!\n",
+        ));
+        let app = mock_app(&fixture);
+        manage_db(&app);
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "replicaCount": {"type": "integer", "minimum": 1},
+                "service": {"type": "object", "properties": {"port": {"type": "integer"}}}
+            }
+        });
+        schemas::store_chart_schema(&app.state(), "nginx", "15.4.0", "bitnami", None, &schema, false, None).unwrap();
+        let options = UpgradeOptions {
+            namespace: Some("apps".into()),
+            version: Some("15.4.0".into()),
+            ..Default::default()
+        };
+        let values = r#"{"replicaCount": 0, "service": {"port": "http"}}"#;
+
+        let error = helm_upgrade(
+            app.handle().clone(),
+            app.state(),
+            app.state(),
+            "web".into(),
+            "bitnami/nginx".into(),
+            values.into(),
+            options.clone(),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::InvalidValues);
+        let paths: Vec<&str> = error.validation_errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["/replicaCount", "/service/port"]);
+        assert!(fixture.calls().is_empty());
+
+        let live = validate_values(
+            app.state(),
+            "nginx".into(),
+            "15.4.0".into(),
+            "bitnami".into(),
+            values.into(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(live, error.validation_errors);
+
+        let skipped = helm_upgrade(
+            app.handle().clone(),
+            app.state(),
+            app.state(),
+            "web".into(),
+            "bitnami/nginx".into(),
+            values.into(),
+            options,
+            None,
+            None,
+            Some(true),
+        )
+        .await;
+        assert!(skipped.is_ok(), "{:?}", skipped);
+    }
+
+    #[tokio::test]
+    async fn helm_upgrade_validates_against_the_schema_cached_for_the_release_editor() {
+        // The release editor passes the release's chart, e.g. `nginx-15.4.0`,
+        // and the key get_schema_for_chart cached the schema under
+        let fixture = Arc::new(FixtureRunner::new().respond(
+            "helm upgrade --install web nginx-15.4.0 -n apps --version=15.4.0 -f *",
+            "Release \"web\" has been upgraded. Happy Helming!\n",
+        ));
+        let app = mock_app(&fixture);
+        manage_db(&app);
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {"replicaCount": {"type": "integer", "minimum": 1}}
+        });
+        schemas::store_chart_schema(&app.state(), "nginx", "15.4.0", "stable", None, &schema, false, None).unwrap();
+        let args: serde_json::Value = serde_json::json!({
+            "releaseName": "web",
+            "chartPath": "nginx-15.4.0",
+            "valuesJson": r#"{"replicaCount": 0}"#,
+            "options": {"namespace": "apps", "version": "15.4.0"},
+            "schemaKey": {"chartName": "nginx", "chartVersion": "15.4.0", "repoName": "stable"},
+        });
+        let options: UpgradeOptions = serde_json::from_value(args["options"].clone()).unwrap();
+        let schema_key: SchemaKey = serde_json::from_value(args["schemaKey"].clone()).unwrap();
+        let upgrade = |schema_key: Option<SchemaKey>, values: &str| {
+            helm_upgrade(
+                app.handle().clone(),
+                app.state(),
+                app.state(),
+                args["releaseName"].as_str().unwrap().into(),
+                args["chartPath"].as_str().unwrap().into(),
+                values.into(),
+                options.clone(),
+                schema_key,
+                None,
+                None,
+            )
+        };
+
+        let error = upgrade(Some(schema_key.clone()), args["valuesJson"].as_str().unwrap())
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidValues);
+        assert_eq!(error.validation_errors[0].path, "/replicaCount");
+
+        let missing = upgrade(None, args["valuesJson"].as_str().unwrap()).await.unwrap_err();
+        assert_eq!(missing.kind, ErrorKind::SchemaNotFound);
+        assert!(fixture.calls().is_empty());
+
+        let upgraded = upgrade(Some(schema_key), r#"{"replicaCount": 2}"#).await;
+        assert!(upgraded.is_ok(), "{:?}", upgraded);
+    }
+
+    #[tokio::test]
+    async fn helm_upgrade_validates_values_merged_with_the_chart_defaults() {
+        let fixture = Arc::new(FixtureRunner::new().respond(
+            "helm upgrade --install web bitnami/nginx -n apps --version=15.4.0 -f *",
+            "Release \"web\" has been upgraded. Happy Helming!\n",
+        ));
+        let app = mock_app(&fixture);
+        manage_db(&app);
+        let schema = serde_json::json!({
+            "type": "object",
+            "required": ["image"],
+            "properties": {
+                "image": {
+                    "type": "object",
+                    "required": ["repository", "tag"],
+                    "properties": {"tag": {"type": "string"}}
+                }
+            }
+        });
+        let defaults = serde_json::json!({"image": {"repository": "nginx", "tag": "1.25"}});
+        schemas::store_chart_schema(&app.state(), "nginx", "15.4.0", "bitnami", None, &schema, false, Some(&defaults)).unwrap();
+        let options = UpgradeOptions {
+            namespace: Some("apps".into()),
+            version: Some("15.4.0".into()),
+            ..Default::default()
+        };
+
+        let upgraded = helm_upgrade(
+            app.handle().clone(),
+            app.state(),
+            app.state(),
+            "web".into(),
+            "bitnami/nginx".into(),
+            r#"{"image": {"tag": "1.26"}}"#.into(),
+            options.clone(),
+            None,
+            None,
+            None,
+        )
+        .await;
+        assert!(upgraded.is_ok(), "{:?}", upgraded);
+
+        // Removing a defaulted key with null makes it missing again
+        let error = helm_upgrade(
+            app.handle().clone(),
+            app.state(),
+            app.state(),
+            "web".into(),
+            "bitnami/nginx".into(),
+            r#"{"image": {"repository": null}}"#.into(),
+            options,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidValues);
+        assert_eq!(error.validation_errors[0].path, "/image/repository");
+        assert_eq!(error.validation_errors[0].keyword, "required");
+    }
+
+    #[tokio::test]
+    async fn helm_upgrade_validates_against_a_local_chart_edited_since_registering() {
+        let dir = tempfile::tempdir().unwrap();
+        let chart_dir = std::fs::canonicalize(dir.path()).unwrap();
+        let path = chart_dir.to_string_lossy().into_owned();
+        std::fs::write(chart_dir.join("Chart.yaml"), "name: web\nversion: 0.1.0\n").unwrap();
+        std::fs::write(chart_dir.join("values.yaml"), "replicaCount: 1\n").unwrap();
+        std::fs::write(
+            chart_dir.join("values.schema.json"),
+            r#"{"type":"object","properties":{"replicaCount":{"type":"integer"}}}"#,
+        )
+        .unwrap();
+        let fixture = Arc::new(FixtureRunner::new().respond(
+            &format!("helm upgrade --install web {} -n apps -f *", path),
+            "Release \"web\" has been upgraded. Happy Helming!\n",
+        ));
+        let app = mock_app(&fixture);
+        manage_db(&app);
+        let registered = helm::local_chart::register_local_chart(&app.state(), &path).unwrap();
+
+        std::fs::write(
+            chart_dir.join("values.schema.json"),
+            r#"{"type":"object","properties":{"replicaCount":{"type":"integer","minimum":2}}}"#,
+        )
+        .unwrap();
+        let error = helm_upgrade(
+            app.handle().clone(),
+            app.state(),
+            app.state(),
+            "web".into(),
+            path.clone(),
+            r#"{"replicaCount": 1}"#.into(),
+            UpgradeOptions {
+                namespace: Some("apps".into()),
+                ..Default::default()
+            },
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(error.kind, ErrorKind::InvalidValues);
+        assert_eq!(error.validation_errors[0].path, "/replicaCount");
+        assert!(fixture.calls().is_empty());
+        // The registration now points at the edited contents
+        let entry = db::local_charts::get_local_chart(&app.state(), &path)
+            .unwrap()
+            .unwrap();
+        assert_ne!(entry.content_hash, registered.content_hash);
+        let cached = schemas::list_cached_schemas(&app.state()).unwrap();
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached[0].schema_content["properties"]["replicaCount"]["minimum"], 2);
+    }
+
+    fn install_request() -> InstallRequest {
+        InstallRequest {
+            chart: "bitnami/nginx".into(),
+            version: Some("15.4.0".into()),
+            release_name: "web".into(),
+            namespace: "apps".into(),
+            create_namespace: true,
+            values_json: r#"{"replicaCount": 2}"#.into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn install_chart_installs_a_new_release() {
+        let fixture = Arc::new(
+            FixtureRunner::new()
+                .fail("helm status web -n apps -o json", "Error: release: not found", 1)
+                .respond(
+                    "helm install web bitnami/nginx -n apps --create-namespace --version=15.4.0 -f *",
+                    "NAME: web\nSTATUS: deployed\n",
+                ),
+        );
+        let app = mock_app(&fixture);
+
+        let result = install_chart(app.handle().clone(), app.state(), install_request(), None).await;
+
+        assert_eq!(result, Ok("NAME: web\nSTATUS: deployed\n".to_string()));
+        assert_eq!(fixture.calls().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn install_chart_refuses_an_existing_release_name() {
+        let fixture = Arc::new(FixtureRunner::new().respond(
+            "helm status web -n apps -o json",
+            r#"{"name":"web","info":{"status":"deployed"},"version":3}"#,
+        ));
+        let app = mock_app(&fixture);
+
+        let result = install_chart(app.handle().clone(), app.state(), install_request(), None).await;
+
+        assert!(matches!(result, Err(e) if e.kind == ErrorKind::Conflict));
+        assert_eq!(fixture.calls(), vec!["helm status web -n apps -o json"]);
+    }
+
+    #[tokio::test]
+    async fn prepare_install_fetches_the_schema_of_the_latest_version() {
+        let fixture = Arc::new(
+            FixtureRunner::new()
+                .respond(
+                    "helm search repo bitnami/nginx --versions -o json",
+                    r#"[{"name":"bitnami/nginx","version":"15.4.0","app_version":"1.25.3","description":"NGINX"}]"#,
+                )
+                .respond("helm repo list -o json", "[]")
+                .fail("helm status web -n apps -o json", "Error: release: not found", 1),
+        );
+        let app = mock_app(&fixture);
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        db::connection::create_tables(&conn).unwrap();
+        let db: DbConnection = Arc::new(Mutex::new(conn));
+        app.manage(db);
+
+        let preparation = prepare_install(
+            app.state(),
+            app.state(),
+            "bitnami/nginx".into(),
+            None,
+            Some("web".into()),
+            Some("apps".into()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(preparation.version, "15.4.0");
+        assert_eq!(preparation.schema["type"], "object");
+        assert_eq!(preparation.release_name_available, Some(true));
+        let cached = schemas::list_cached_schemas(&app.state()).unwrap();
+        assert_eq!(cached[0].chart_version, "15.4.0");
+    }
+
+    #[tokio::test]
+    async fn run_release_tests_records_each_run() {
+        let started = chrono::Utc::now();
+        let completed = started + chrono::TimeDelta::seconds(3);
+        let fixture = Arc::new(
+            FixtureRunner::new()
+                .respond("helm test web -n apps", "TEST SUITE:     web-test-connection\nPhase:          Succeeded\n")
+                .respond(
+                    "helm status web -n apps -o json",
+                    &format!(
+                        r#"{{"name": "web", "version": 2, "info": {{"status": "deployed"}}, "hooks": [{{"name": "web-test-connection", "events": ["test"], "last_run": {{"started_at": "{}", "completed_at": "{}", "phase": "Succeeded"}}}}]}}"#,
+                        started.to_rfc3339(),
+                        completed.to_rfc3339()
+                    ),
+                ),
+        );
+        let app = mock_app(&fixture);
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        db::connection::create_tables(&conn).unwrap();
+        let db: DbConnection = Arc::new(Mutex::new(conn));
+        app.manage(db);
+        let request = ReleaseTestRequest {
+            release_name: "web".into(),
+            namespace: "apps".into(),
+            ..Default::default()
+        };
+
+        let first = run_release_tests(app.state(), app.state(), request.clone()).await.unwrap();
+        let second = run_release_tests(app.state(), app.state(), request).await.unwrap();
+
+        assert!(first.success);
+        assert_eq!(first.results[0].duration_secs, Some(3.0));
+        let history = list_release_test_runs(app.state(), "web".into(), "apps".into(), None)
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].id, second.id);
+        assert_eq!(history[1].results, first.results);
+        assert_eq!(history[1].revision, Some(2));
+    }
+
+    #[tokio::test]
+    async fn uninstall_release_requires_the_previewed_token() {
+        use helm::uninstall::{UninstallConfirmations, UninstallOptions};
+
+        let fixture = Arc::new(
+            FixtureRunner::new()
+                .respond(
+                    "helm get manifest web -n apps",
+                    "---\n# Source: nginx/templates/service.yaml\napiVersion: v1\nkind: Service\nmetadata:\n  name: web\n",
+                )
+                .respond("helm uninstall web -n apps --keep-history", "release \"web\" uninstalled\n"),
+        );
+        let app = mock_app(&fixture);
+        let confirmations: SharedConfirmations = Arc::new(UninstallConfirmations::default());
+        app.manage(confirmations);
+
+        let mut request = UninstallRequest {
+            release_name: "web".into(),
+            namespace: "apps".into(),
+            options: UninstallOptions {
+                keep_history: true,
+                ..Default::default()
+            },
+            confirmation_token: Some("uninstall-guessed".into()),
+        };
+        let guessed = uninstall_release(app.handle().clone(), app.state(), app.state(), request.clone(), None).await;
+        assert!(matches!(guessed, Err(e) if e.kind == ErrorKind::InvalidInput));
+
+        request.confirmation_token = None;
+        let preview = match uninstall_release(app.handle().clone(), app.state(), app.state(), request.clone(), None)
+            .await
+            .unwrap()
+        {
+            UninstallOutcome::PendingConfirmation(preview) => preview,
+            outcome => panic!("expected a preview, got {:?}", outcome),
+        };
+        assert_eq!(preview.resources[0].kind, "Service");
+        assert_eq!(fixture.calls(), vec!["helm get manifest web -n apps"]);
+
+        request.confirmation_token = Some(preview.confirmation_token);
+        let outcome = uninstall_release(app.handle().clone(), app.state(), app.state(), request, None).await;
+        assert_eq!(
+            outcome,
+            Ok(UninstallOutcome::Uninstalled {
+                output: "release \"web\" uninstalled\n".into()
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn run_shell_command_rejects_other_programs() {
+        let fixture = Arc::new(FixtureRunner::new());
+        let app = mock_app(&fixture);
+
+        let result = run_shell_command(app.state(), "rm -rf /".into(), None).await;
+
+        assert!(matches!(result, Err(e) if e.kind == ErrorKind::InvalidInput));
+        assert!(fixture.calls().is_empty());
+    }
+}
@@ -0,0 +1,2 @@
+pub mod progress;
+pub mod registry;
\ No newline at end of file
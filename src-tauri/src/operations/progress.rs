@@ -0,0 +1,89 @@
+//! Operation progress module
+//!
+//! Streams the output of long-running helm/kubectl commands to the frontend
+//! as Tauri events so that e.g. a `--wait` upgrade doesn't look frozen.
+
+use super::registry::with_operation_id;
+use crate::runner::command::{CommandOutput, CommandRunner, OutputStream};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tauri::{Emitter, Runtime};
+
+/// Event carrying a single output line of a running operation.
+pub const PROGRESS_EVENT: &str = "operation://progress";
+/// Event emitted once when an operation has finished.
+pub const COMPLETE_EVENT: &str = "operation://complete";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressPayload {
+    pub operation_id: String,
+    pub stream: OutputStream,
+    pub line: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletePayload {
+    pub operation_id: String,
+    /// Exit code of the process, `None` if it was killed or never started.
+    pub exit_code: Option<i32>,
+    pub success: bool,
+    /// Set when the process could not be run at all.
+    pub error: Option<String>,
+}
+
+/// Generate a process-unique operation ID
+pub fn new_operation_id() -> String {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    format!("op-{}-{}", millis, NEXT_ID.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Run a command, emitting each output line as a progress event and a
+/// completion event with the exit code once it has finished.
+pub async fn run_with_progress<R: Runtime>(
+    app: &tauri::AppHandle<R>,
+    runner: &dyn CommandRunner,
+    operation_id: &str,
+    program: &str,
+    args: &[String],
+) -> Result<CommandOutput, String> {
+    let on_line = |stream: OutputStream, line: &str| {
+        let _ = app.emit(
+            PROGRESS_EVENT,
+            ProgressPayload {
+                operation_id: operation_id.to_string(),
+                stream,
+                line: line.to_string(),
+            },
+        );
+    };
+
+    let result = with_operation_id(
+        operation_id.to_string(),
+        runner.stream(program, args, &on_line),
+    )
+    .await;
+
+    let complete = match &result {
+        Ok(output) => CompletePayload {
+            operation_id: operation_id.to_string(),
+            exit_code: output.code,
+            success: output.success(),
+            error: None,
+        },
+        Err(e) => CompletePayload {
+            operation_id: operation_id.to_string(),
+            exit_code: None,
+            success: false,
+            error: Some(e.clone()),
+        },
+    };
+    let _ = app.emit(COMPLETE_EVENT, complete);
+
+    result
+}
@@ -0,0 +1,352 @@
+//! Operation registry module
+//!
+//! Keeps track of every helm/kubectl process started by the backend so that
+//! it can be listed, cancelled from the UI, and killed once it exceeds the
+//! timeout configured for its command.
+
+use super::progress::new_operation_id;
+use crate::helm::upgrade::parse_duration_secs;
+use crate::runner::command::CommandOutput;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::oneshot;
+
+/// Registry shared as Tauri managed state.
+pub type SharedRegistry = Arc<OperationRegistry>;
+
+tokio::task_local! {
+    static CURRENT_OPERATION: String;
+}
+
+/// Run `fut` with every command it starts registered under `operation_id`
+/// instead of a freshly generated ID.
+pub async fn with_operation_id<F: Future>(operation_id: String, fut: F) -> F::Output {
+    CURRENT_OPERATION.scope(operation_id, fut).await
+}
+
+/// A command currently running on behalf of the frontend.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationInfo {
+    pub id: String,
+    /// Full command line, e.g. `helm upgrade web bitnami/nginx`.
+    pub command: String,
+    /// Start time in milliseconds since the Unix epoch.
+    pub started_at: u64,
+    pub timeout_secs: u64,
+}
+
+struct RunningOperation {
+    info: OperationInfo,
+    seq: u64,
+    cancel: Option<oneshot::Sender<()>>,
+}
+
+/// Extra time given to a command on top of its own `--timeout`, so that it can
+/// report the timeout itself before its process is killed.
+const TIMEOUT_FLAG_MARGIN: Duration = Duration::from_secs(60);
+
+/// Process timeouts keyed by command, e.g. `"helm upgrade"` or `"kubectl"`.
+pub struct TimeoutSettings {
+    pub default: Duration,
+    pub overrides: HashMap<String, Duration>,
+}
+
+impl Default for TimeoutSettings {
+    fn default() -> Self {
+        let overrides = [
+            ("helm upgrade", 15 * 60),
+            ("helm install", 15 * 60),
+            ("helm rollback", 15 * 60),
+            ("helm uninstall", 15 * 60),
+            ("helm test", 15 * 60),
+            ("helm pull", 5 * 60),
+            ("kubectl rollout", 5 * 60),
+            ("kubectl exec", 10 * 60),
+        ]
+        .into_iter()
+        .map(|(command, secs)| (command.to_string(), Duration::from_secs(secs)))
+        .collect();
+
+        Self {
+            default: Duration::from_secs(60),
+            overrides,
+        }
+    }
+}
+
+impl TimeoutSettings {
+    /// Timeout for a command, looked up by `program subcommand`, then `program`.
+    ///
+    /// A command given a longer `--timeout` of its own, such as
+    /// `helm upgrade --timeout=30m`, is allowed that long plus a margin.
+    pub fn timeout_for(&self, program: &str, args: &[String]) -> Duration {
+        let subcommand = args.iter().find(|a| !a.starts_with('-'));
+        let configured = subcommand
+            .and_then(|sub| self.overrides.get(&format!("{} {}", program, sub)))
+            .or_else(|| self.overrides.get(program))
+            .copied()
+            .unwrap_or(self.default);
+        match timeout_flag(args) {
+            Some(own) => configured.max(own + TIMEOUT_FLAG_MARGIN),
+            None => configured,
+        }
+    }
+}
+
+/// The `--timeout` a command was given, as `--timeout=5m` or `--timeout 5m`.
+fn timeout_flag(args: &[String]) -> Option<Duration> {
+    let position = args
+        .iter()
+        .position(|a| a == "--timeout" || a.starts_with("--timeout="))?;
+    let value = match args[position].strip_prefix("--timeout=") {
+        Some(value) => value,
+        None => args.get(position + 1)?,
+    };
+    parse_duration_secs(value).ok().map(Duration::from_secs)
+}
+
+#[derive(Default)]
+pub struct OperationRegistry {
+    operations: Mutex<HashMap<String, RunningOperation>>,
+    timeouts: Mutex<TimeoutSettings>,
+    next_seq: AtomicU64,
+}
+
+impl OperationRegistry {
+    /// Run `fut` as a registered operation for `program args`.
+    ///
+    /// The future is dropped - killing its process - when the operation is
+    /// cancelled or exceeds the timeout configured for the command.
+    pub async fn track<F>(&self, program: &str, args: &[String], fut: F) -> Result<CommandOutput, String>
+    where
+        F: Future<Output = Result<CommandOutput, String>>,
+    {
+        let id = CURRENT_OPERATION
+            .try_with(|id| id.clone())
+            .unwrap_or_else(|_| new_operation_id());
+        let command = std::iter::once(program)
+            .chain(args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let timeout = self
+            .timeouts
+            .lock()
+            .map(|t| t.timeout_for(program, args))
+            .unwrap_or_else(|_| TimeoutSettings::default().timeout_for(program, args));
+
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        if let Ok(mut operations) = self.operations.lock() {
+            operations.insert(
+                id.clone(),
+                RunningOperation {
+                    info: OperationInfo {
+                        id: id.clone(),
+                        command: command.clone(),
+                        started_at,
+                        timeout_secs: timeout.as_secs(),
+                    },
+                    seq,
+                    cancel: Some(cancel_tx),
+                },
+            );
+        }
+        let _registration = Registration {
+            registry: self,
+            id: &id,
+            seq,
+        };
+
+        tokio::select! {
+            result = fut => result,
+            Ok(()) = cancel_rx => Err(format!("Operation {} was cancelled", id)),
+            _ = tokio::time::sleep(timeout) => Err(format!(
+                "Operation timeout: `{}` did not finish within {}s",
+                command,
+                timeout.as_secs()
+            )),
+        }
+    }
+
+    /// Cancel a running operation, killing its process.
+    pub fn cancel(&self, id: &str) -> Result<(), String> {
+        let mut operations = self
+            .operations
+            .lock()
+            .map_err(|e| format!("Failed to acquire operations lock: {}", e))?;
+
+        let cancel = operations
+            .get_mut(id)
+            .ok_or_else(|| format!("No running operation with id {}", id))?
+            .cancel
+            .take();
+        if let Some(cancel) = cancel {
+            let _ = cancel.send(());
+        }
+
+        Ok(())
+    }
+
+    /// Operations that are currently running, oldest first.
+    pub fn list(&self) -> Vec<OperationInfo> {
+        let mut running: Vec<OperationInfo> = self
+            .operations
+            .lock()
+            .map(|ops| ops.values().map(|op| op.info.clone()).collect())
+            .unwrap_or_default();
+        running.sort_by_key(|op| op.started_at);
+        running
+    }
+
+    /// Override the timeout of a command (`"helm upgrade"`, `"kubectl"`, ...),
+    /// or restore its default when `timeout` is `None`.
+    pub fn set_timeout(&self, command: &str, timeout: Option<Duration>) -> Result<(), String> {
+        let mut timeouts = self
+            .timeouts
+            .lock()
+            .map_err(|e| format!("Failed to acquire timeouts lock: {}", e))?;
+
+        match timeout {
+            Some(timeout) => {
+                timeouts.overrides.insert(command.to_string(), timeout);
+            }
+            None => match TimeoutSettings::default().overrides.get(command) {
+                Some(default) => {
+                    timeouts.overrides.insert(command.to_string(), *default);
+                }
+                None => {
+                    timeouts.overrides.remove(command);
+                }
+            },
+        }
+
+        Ok(())
+    }
+}
+
+/// Removes an operation from the registry once its future completes or is dropped
+struct Registration<'a> {
+    registry: &'a OperationRegistry,
+    id: &'a str,
+    seq: u64,
+}
+
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut operations) = self.registry.operations.lock() {
+            if operations.get(self.id).is_some_and(|op| op.seq == self.seq) {
+                operations.remove(self.id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    async fn hang() -> Result<CommandOutput, String> {
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        Ok(CommandOutput::default())
+    }
+
+    #[test]
+    fn timeout_lookup_prefers_subcommand_over_program() {
+        let mut settings = TimeoutSettings::default();
+        settings
+            .overrides
+            .insert("kubectl".into(), Duration::from_secs(30));
+
+        assert_eq!(
+            settings.timeout_for("helm", &args(&["upgrade", "--install", "web", "bitnami/nginx"])),
+            Duration::from_secs(15 * 60)
+        );
+        assert_eq!(
+            settings.timeout_for("kubectl", &args(&["get", "pods"])),
+            Duration::from_secs(30)
+        );
+        assert_eq!(settings.timeout_for("helm", &args(&["ls", "-A"])), settings.default);
+    }
+
+    #[test]
+    fn timeout_covers_the_commands_own_timeout() {
+        let settings = TimeoutSettings::default();
+
+        assert_eq!(
+            settings.timeout_for("helm", &args(&["upgrade", "web", "bitnami/nginx", "--timeout=30m"])),
+            Duration::from_secs(31 * 60)
+        );
+        assert_eq!(
+            settings.timeout_for("helm", &args(&["test", "web", "--timeout", "1h"])),
+            Duration::from_secs(61 * 60)
+        );
+        // A shorter timeout of its own does not cut the configured one
+        assert_eq!(
+            settings.timeout_for("helm", &args(&["rollback", "web", "3", "--timeout=5m"])),
+            Duration::from_secs(15 * 60)
+        );
+    }
+
+    #[tokio::test]
+    async fn track_times_out_hung_commands() {
+        let registry = OperationRegistry::default();
+        registry
+            .set_timeout("kubectl get", Some(Duration::from_millis(20)))
+            .unwrap();
+
+        let result = registry
+            .track("kubectl", &args(&["get", "pods"]), hang())
+            .await;
+
+        assert!(result
+            .unwrap_err()
+            .starts_with("Operation timeout: `kubectl get pods` did not finish"));
+        assert!(registry.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_a_running_operation() {
+        let registry = Arc::new(OperationRegistry::default());
+
+        let task = {
+            let registry = registry.clone();
+            tokio::spawn(async move {
+                with_operation_id(
+                    "op-upgrade".into(),
+                    registry.track("helm", &args(&["upgrade", "web", "bitnami/nginx"]), hang()),
+                )
+                .await
+            })
+        };
+        while registry.list().is_empty() {
+            tokio::task::yield_now().await;
+        }
+
+        let running = registry.list();
+        assert_eq!(running[0].id, "op-upgrade");
+        assert_eq!(running[0].command, "helm upgrade web bitnami/nginx");
+        assert_eq!(running[0].timeout_secs, 15 * 60);
+
+        registry.cancel("op-upgrade").unwrap();
+
+        assert_eq!(
+            task.await.unwrap(),
+            Err("Operation op-upgrade was cancelled".to_string())
+        );
+        assert!(registry.list().is_empty());
+        assert!(registry.cancel("op-upgrade").is_err());
+    }
+}
@@ -3,6 +3,11 @@
 //! This module was automatically extracted by Rusty Refactor.
 
 use crate::*;
+use crate::helm::uninstall::SharedConfirmations;
+use crate::operations::registry::{OperationRegistry, SharedRegistry};
+use crate::runner::shell::ShellRunner;
+use crate::runner::tracked::TrackedRunner;
+use tauri::Manager;
 
 /// Starts and runs the Tauri application.
 ///
@@ -31,17 +36,44 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_shell::init())
         .manage(db_state)
+        .manage(SharedConfirmations::default())
+        .setup(|app| {
+            // Every helm/kubectl call goes through the shell plugin by default,
+            // registered with the operation registry for timeouts and cancellation
+            let registry: SharedRegistry = Arc::new(OperationRegistry::default());
+            let shell: SharedRunner = Arc::new(ShellRunner::new(app.handle().clone()));
+            let runner: SharedRunner = Arc::new(TrackedRunner::new(shell, registry.clone()));
+            app.manage(registry);
+            app.manage(runner);
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             list_helm_releases,
             get_schema_for_chart,
             set_kubeconfig,
             helm_upgrade,
+            prepare_install,
+            install_chart,
+            list_repos,
+            add_repo,
+            remove_repo,
+            update_repos,
+            search_charts,
+            registry_login,
+            register_local_chart,
+            list_local_charts,
+            remove_local_chart,
             list_cached_schemas,
             clear_schema_cache,
             delete_schema_cache_entry,
             get_helm_history,
             helm_rollback,
+            uninstall_release,
+            get_release_status,
+            run_release_tests,
+            list_release_test_runs,
+            compare_revisions,
             get_kube_context,
             list_kube_contexts,
             switch_kube_context,
@@ -52,10 +84,16 @@ pub fn run() {
             delete_failed_pods,
             describe_pod,
             helm_dry_run,
+            preview_upgrade,
+            validate_values,
             get_release_values,
             get_release_manifest,
+            get_release_resources,
             run_shell_command,
-            exec_in_pod
+            exec_in_pod,
+            cancel_operation,
+            list_running_operations,
+            set_operation_timeout
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,4 @@
+pub mod command;
+pub mod shell;
+pub mod fixture;
+pub mod tracked;
\ No newline at end of file
@@ -0,0 +1,97 @@
+//! Command runner module
+//!
+//! Abstraction over the external `helm` and `kubectl` processes so that the
+//! Tauri commands can be exercised without real binaries or a live cluster.
+
+use async_trait::async_trait;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Captured result of a finished process.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandOutput {
+    /// Exit code of the process, `None` if it was terminated by a signal.
+    pub code: Option<i32>,
+    /// The data that the process wrote to stdout.
+    pub stdout: Vec<u8>,
+    /// The data that the process wrote to stderr.
+    pub stderr: Vec<u8>,
+}
+
+impl CommandOutput {
+    /// Returns true if the process exited with a zero exit code.
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+/// Output stream a line was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Callback receiving each output line of a streamed process as it is produced.
+pub type LineHandler<'a> = &'a (dyn Fn(OutputStream, &str) + Send + Sync);
+
+/// Runs external programs on behalf of the backend commands.
+#[async_trait]
+pub trait CommandRunner: Send + Sync {
+    /// Run `program` with `args` to completion and capture its output.
+    ///
+    /// An `Err` means the process could not be started at all; a process that
+    /// ran and failed is reported through `CommandOutput::code`.
+    async fn output(&self, program: &str, args: &[String]) -> Result<CommandOutput, String>;
+
+    /// Like `output`, with `stdin` written to the process's standard input,
+    /// which is then closed. Secrets such as passwords are passed this way
+    /// so that they never appear on the command line.
+    async fn output_with_stdin(
+        &self,
+        program: &str,
+        args: &[String],
+        stdin: &[u8],
+    ) -> Result<CommandOutput, String>;
+
+    /// Like `output`, but hands every stdout/stderr line to `on_line` while the
+    /// process is still running. Lines are passed without their trailing newline.
+    async fn stream(
+        &self,
+        program: &str,
+        args: &[String],
+        on_line: LineHandler<'_>,
+    ) -> Result<CommandOutput, String>;
+}
+
+impl dyn CommandRunner + '_ {
+    /// Convenience wrapper around `output` accepting any list of string-like arguments.
+    pub async fn run<I, S>(&self, program: &str, args: I) -> Result<CommandOutput, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let args: Vec<String> = args.into_iter().map(|a| a.as_ref().to_string()).collect();
+        self.output(program, &args).await
+    }
+
+    /// Convenience wrapper around `output_with_stdin`, see `run`.
+    pub async fn run_with_stdin<I, S>(
+        &self,
+        program: &str,
+        args: I,
+        stdin: &str,
+    ) -> Result<CommandOutput, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let args: Vec<String> = args.into_iter().map(|a| a.as_ref().to_string()).collect();
+        self.output_with_stdin(program, &args, stdin.as_bytes())
+            .await
+    }
+}
+
+/// Runner shared as Tauri managed state.
+pub type SharedRunner = Arc<dyn CommandRunner>;
@@ -0,0 +1,125 @@
+//! Fixture runner module
+//!
+//! `CommandRunner` that answers from canned helm/kubectl output and records
+//! every invocation, used to test commands without a cluster.
+
+use super::command::{CommandOutput, CommandRunner, LineHandler, OutputStream};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Replays canned output keyed on the full command line.
+///
+/// Keys are the program followed by its arguments joined with single spaces,
+/// e.g. `"helm ls -A -o json"`. A key ending in `*` matches any command line
+/// starting with the text before it, for arguments such as temporary file
+/// paths. Commands without a fixture fail as if the binary could not be started.
+#[derive(Default)]
+pub struct FixtureRunner {
+    fixtures: HashMap<String, CommandOutput>,
+    calls: Mutex<Vec<String>>,
+    inputs: Mutex<Vec<String>>,
+}
+
+impl FixtureRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a successful invocation printing `stdout`.
+    pub fn respond(self, command_line: &str, stdout: &str) -> Self {
+        self.fixture(
+            command_line,
+            CommandOutput {
+                code: Some(0),
+                stdout: stdout.as_bytes().to_vec(),
+                stderr: Vec::new(),
+            },
+        )
+    }
+
+    /// Register a failing invocation printing `stderr` and exiting with `code`.
+    pub fn fail(self, command_line: &str, stderr: &str, code: i32) -> Self {
+        self.fixture(
+            command_line,
+            CommandOutput {
+                code: Some(code),
+                stdout: Vec::new(),
+                stderr: stderr.as_bytes().to_vec(),
+            },
+        )
+    }
+
+    /// Register an arbitrary output for a command line.
+    pub fn fixture(mut self, command_line: &str, output: CommandOutput) -> Self {
+        self.fixtures.insert(command_line.to_string(), output);
+        self
+    }
+
+    /// Command lines that were run, in order.
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().map(|c| c.clone()).unwrap_or_default()
+    }
+
+    /// Standard input passed to commands with `output_with_stdin`, in order.
+    pub fn inputs(&self) -> Vec<String> {
+        self.inputs.lock().map(|i| i.clone()).unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl CommandRunner for FixtureRunner {
+    async fn output(&self, program: &str, args: &[String]) -> Result<CommandOutput, String> {
+        let command_line = std::iter::once(program)
+            .chain(args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        if let Ok(mut calls) = self.calls.lock() {
+            calls.push(command_line.clone());
+        }
+
+        self.fixtures
+            .get(&command_line)
+            .or_else(|| {
+                self.fixtures
+                    .iter()
+                    .filter_map(|(key, output)| Some((key.strip_suffix('*')?, output)))
+                    .filter(|(prefix, _)| command_line.starts_with(prefix))
+                    .max_by_key(|(prefix, _)| prefix.len())
+                    .map(|(_, output)| output)
+            })
+            .cloned()
+            .ok_or_else(|| format!("No fixture for `{}`", command_line))
+    }
+
+    async fn output_with_stdin(
+        &self,
+        program: &str,
+        args: &[String],
+        stdin: &[u8],
+    ) -> Result<CommandOutput, String> {
+        if let Ok(mut inputs) = self.inputs.lock() {
+            inputs.push(String::from_utf8_lossy(stdin).into_owned());
+        }
+        self.output(program, args).await
+    }
+
+    async fn stream(
+        &self,
+        program: &str,
+        args: &[String],
+        on_line: LineHandler<'_>,
+    ) -> Result<CommandOutput, String> {
+        let output = self.output(program, args).await?;
+
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            on_line(OutputStream::Stdout, line);
+        }
+        for line in String::from_utf8_lossy(&output.stderr).lines() {
+            on_line(OutputStream::Stderr, line);
+        }
+
+        Ok(output)
+    }
+}
@@ -0,0 +1,115 @@
+//! Shell runner module
+//!
+//! Default `CommandRunner` backed by the Tauri shell plugin.
+
+use super::command::{CommandOutput, CommandRunner, LineHandler, OutputStream};
+use async_trait::async_trait;
+use std::process::Stdio;
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+use tauri_plugin_shell::ShellExt;
+use tokio::io::AsyncWriteExt;
+
+/// Runs commands through `tauri_plugin_shell`.
+///
+/// Dropping a pending `output`/`stream` future kills the child process, which
+/// is how timeouts and cancellation stop a hung helm or kubectl call.
+pub struct ShellRunner {
+    app: tauri::AppHandle,
+}
+
+impl ShellRunner {
+    pub fn new(app: tauri::AppHandle) -> Self {
+        Self { app }
+    }
+}
+
+#[async_trait]
+impl CommandRunner for ShellRunner {
+    async fn output(&self, program: &str, args: &[String]) -> Result<CommandOutput, String> {
+        self.stream(program, args, &|_, _| {}).await
+    }
+
+    async fn output_with_stdin(
+        &self,
+        program: &str,
+        args: &[String],
+        stdin: &[u8],
+    ) -> Result<CommandOutput, String> {
+        // The shell plugin cannot close a child's stdin without giving up the
+        // handle that kills it, so the command it resolves is run with tokio
+        let command: std::process::Command = self.app.shell().command(program).args(args).into();
+        let mut child = tokio::process::Command::from(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+
+        let mut input = child.stdin.take().ok_or("Failed to open stdin")?;
+        input.write_all(stdin).await.map_err(|e| e.to_string())?;
+        drop(input);
+
+        let output = child.wait_with_output().await.map_err(|e| e.to_string())?;
+        Ok(CommandOutput {
+            code: output.status.code(),
+            stdout: output.stdout,
+            stderr: output.stderr,
+        })
+    }
+
+    async fn stream(
+        &self,
+        program: &str,
+        args: &[String],
+        on_line: LineHandler<'_>,
+    ) -> Result<CommandOutput, String> {
+        let (mut events, child) = self
+            .app
+            .shell()
+            .command(program)
+            .args(args)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+        let mut child = KillOnDrop(Some(child));
+
+        let mut output = CommandOutput::default();
+        while let Some(event) = events.recv().await {
+            match event {
+                CommandEvent::Stdout(line) => {
+                    on_line(OutputStream::Stdout, &trim_line(&line));
+                    output.stdout.extend(line);
+                }
+                CommandEvent::Stderr(line) => {
+                    on_line(OutputStream::Stderr, &trim_line(&line));
+                    output.stderr.extend(line);
+                }
+                CommandEvent::Terminated(payload) => output.code = payload.code,
+                CommandEvent::Error(e) => return Err(e),
+                _ => {}
+            }
+        }
+
+        // The process has exited, nothing left to kill
+        child.0.take();
+        Ok(output)
+    }
+}
+
+/// Kills the wrapped child process unless it was disarmed after exiting
+struct KillOnDrop(Option<CommandChild>);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        if let Some(child) = self.0.take() {
+            let _ = child.kill();
+        }
+    }
+}
+
+/// Decode a raw output line and strip its line terminator
+fn trim_line(line: &[u8]) -> String {
+    String::from_utf8_lossy(line)
+        .trim_end_matches(['\r', '\n'])
+        .to_string()
+}
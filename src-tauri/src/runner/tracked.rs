@@ -0,0 +1,54 @@
+//! Tracked runner module
+//!
+//! `CommandRunner` decorator registering every invocation with the
+//! `OperationRegistry`, which applies timeouts and handles cancellation.
+
+use super::command::{CommandOutput, CommandRunner, LineHandler, SharedRunner};
+use crate::operations::registry::SharedRegistry;
+use async_trait::async_trait;
+
+pub struct TrackedRunner {
+    inner: SharedRunner,
+    registry: SharedRegistry,
+}
+
+impl TrackedRunner {
+    pub fn new(inner: SharedRunner, registry: SharedRegistry) -> Self {
+        Self { inner, registry }
+    }
+}
+
+#[async_trait]
+impl CommandRunner for TrackedRunner {
+    async fn output(&self, program: &str, args: &[String]) -> Result<CommandOutput, String> {
+        self.registry
+            .track(program, args, self.inner.output(program, args))
+            .await
+    }
+
+    async fn output_with_stdin(
+        &self,
+        program: &str,
+        args: &[String],
+        stdin: &[u8],
+    ) -> Result<CommandOutput, String> {
+        self.registry
+            .track(
+                program,
+                args,
+                self.inner.output_with_stdin(program, args, stdin),
+            )
+            .await
+    }
+
+    async fn stream(
+        &self,
+        program: &str,
+        args: &[String],
+        on_line: LineHandler<'_>,
+    ) -> Result<CommandOutput, String> {
+        self.registry
+            .track(program, args, self.inner.stream(program, args, on_line))
+            .await
+    }
+}
@@ -4,88 +4,189 @@
 //!
 //! Utilities for pulling Helm charts and obtaining their values schema.
 
+use crate::helm::chart_tree::read_chart;
+use crate::schema::chart_schema::{build_chart_schema, ValuesSchema};
 use crate::schema::schema_utils::create_empty_schema;
-use tauri_plugin_shell::ShellExt;
+use crate::runner::command::CommandRunner;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// Directory under the app cache dir in which each pull gets its own workspace.
+fn pull_workspace_root() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("rudder")
+        .join("chart-pulls")
+}
+
+/// Create a uniquely named directory, readable only by the current user, to
+/// pull a chart into. It is removed with everything in it when the returned
+/// guard is dropped, so concurrent pulls never share or delete each other's files.
+fn create_pull_workspace(root: &Path) -> Result<TempDir, String> {
+    fs::create_dir_all(root)
+        .map_err(|e| format!("Failed to create '{}': {}", root.display(), e))?;
+    tempfile::Builder::new()
+        .prefix("pull-")
+        .tempdir_in(root)
+        .map_err(|e| format!("Failed to create a chart pull directory: {}", e))
+}
 
 /// Pull a chart and extract its schema
 ///
-/// Uses the provided app handle to invoke `helm pull` for the given
-/// repository/chart/version, untars the chart into a temporary directory
-/// ("temp-charts"), and attempts to read and parse `values.schema.json` from
-/// the extracted chart directory. The temporary directory is removed before
-/// returning. If the schema file is missing or invalid, an empty schema is
-/// returned instead.
+/// Uses the provided command runner to invoke `helm pull` for the given chart
+/// reference (`repo/chart` or `oci://registry/path/chart`) and version,
+/// untars the chart into a private temporary directory under the app cache
+/// dir, and builds the schema from the extracted chart's `values.schema.json`,
+/// or infers it from the chart's `values.yaml` if it has none, together with
+/// the subcharts in its `charts/` directory. The temporary directory is
+/// removed before returning, whatever the outcome, and is unique to the call
+/// so that several charts can be pulled in parallel. If the chart cannot be
+/// read or its schema file is invalid, an empty schema is returned instead.
 ///
 /// # Parameters
-/// - `app`: Tauri AppHandle used to run shell commands.
-/// - `repo_name`: Name of the chart repository.
-/// - `chart_name`: Name of the chart.
+/// - `runner`: Command runner used to run `helm pull`.
+/// - `chart_ref`: Chart reference passed to `helm pull`.
+/// - `chart_name`: Name of the chart, i.e. the directory it is extracted to.
 /// - `chart_version`: Version string of the chart.
 ///
 /// # Returns
-/// On success returns `Ok(serde_json::Value)` containing the parsed schema (or an empty schema).
+/// On success returns `Ok(ValuesSchema)` containing the schema (or an empty schema)
+/// and whether it was inferred.
 /// On failure returns `Err(String)` with an error message describing the failure.
 ///
 /// # Notes
 /// This function is `async` and must be awaited by callers.
 pub async fn pull_chart_and_extract_schema(
-    app: &tauri::AppHandle,
-    repo_name: &str,
+    runner: &dyn CommandRunner,
+    chart_ref: &str,
+    chart_name: &str,
+    chart_version: &str,
+) -> Result<ValuesSchema, String> {
+    pull_chart_into(runner, &pull_workspace_root(), chart_ref, chart_name, chart_version).await
+}
+
+/// Pull a chart into a workspace under `workspace_root` and extract its schema.
+async fn pull_chart_into(
+    runner: &dyn CommandRunner,
+    workspace_root: &Path,
+    chart_ref: &str,
     chart_name: &str,
     chart_version: &str,
-) -> Result<serde_json::Value, String> {
-    let shell = app.shell();
-    // Pull the chart
-    let pull_output = shell
-        .command("helm")
-        .args([
+) -> Result<ValuesSchema, String> {
+    // Pull the chart into its own workspace, removed when `workspace` is dropped
+    let workspace = create_pull_workspace(workspace_root)?;
+    let destination = workspace.path().to_string_lossy();
+    let pull_output = runner
+        .run("helm", [
             "pull",
-            &format!("{}/{}", repo_name, chart_name),
+            chart_ref,
             "--version",
             chart_version,
             "--untar",
             "--destination",
-            "temp-charts",
+            &destination,
         ])
-        .output()
         .await;
 
     match pull_output {
-        Ok(pull_result) if pull_result.status.success() => {
-            // Chart pulled, now read the values.schema.json file
-            use std::fs;
-            use std::path::Path;
-
-            let schema_path = Path::new("temp-charts")
-                .join(chart_name)
-                .join("values.schema.json");
-
-            match fs::read_to_string(&schema_path) {
-                Ok(schema_content) => {
-                    // Parse and validate the schema
-                    let schema: serde_json::Value = serde_json::from_str(&schema_content)
-                        .unwrap_or_else(|_| create_empty_schema());
-
-                    // Clean up the temporary chart directory
-                    let _ = fs::remove_dir_all("temp-charts");
+        Ok(pull_result) if pull_result.success() => {
+            // Chart pulled, now build the schema from it and the subcharts in charts/
+            let chart_dir = workspace.path().join(chart_name);
+            let schema = read_chart(&chart_dir)
+                .ok()
+                .and_then(|(tree, _)| build_chart_schema(&tree).ok())
+                .unwrap_or_else(|| ValuesSchema {
+                    schema: create_empty_schema(),
+                    inferred: false,
+                    defaults: serde_json::json!({}),
+                });
 
-                    Ok(schema)
-                }
-                Err(_) => {
-                    // No schema file found, return empty schema
-                    // Clean up the temporary chart directory
-                    let _ = fs::remove_dir_all("temp-charts");
-                    Ok(create_empty_schema())
-                }
-            }
+            Ok(schema)
         }
         Ok(pull_result) => {
             let stderr = String::from_utf8_lossy(&pull_result.stderr);
             Err(format!(
-                "Failed to pull chart {}/{} version {}: {}",
-                repo_name, chart_name, chart_version, stderr
+                "Failed to pull chart {} version {}: {}",
+                chart_ref, chart_version, stderr
             ))
         }
         Err(e) => Err(format!("Failed to execute helm pull: {}", e)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::command::{CommandOutput, LineHandler};
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Extracts a chart with a single `replicaCount` value into the pull destination.
+    #[derive(Default)]
+    struct PullRunner {
+        destinations: Mutex<Vec<PathBuf>>,
+    }
+
+    #[async_trait]
+    impl CommandRunner for PullRunner {
+        async fn output(&self, _program: &str, args: &[String]) -> Result<CommandOutput, String> {
+            let chart = args[1].rsplit('/').next().unwrap();
+            let destination = PathBuf::from(args.last().unwrap());
+            let chart_dir = destination.join(chart);
+            fs::create_dir_all(&chart_dir).unwrap();
+            fs::write(chart_dir.join("Chart.yaml"), format!("name: {}\n", chart)).unwrap();
+            fs::write(chart_dir.join("values.yaml"), "replicaCount: 1\n").unwrap();
+            self.destinations.lock().unwrap().push(destination);
+            Ok(CommandOutput {
+                code: Some(0),
+                ..Default::default()
+            })
+        }
+
+        async fn output_with_stdin(
+            &self,
+            program: &str,
+            args: &[String],
+            _stdin: &[u8],
+        ) -> Result<CommandOutput, String> {
+            self.output(program, args).await
+        }
+
+        async fn stream(
+            &self,
+            program: &str,
+            args: &[String],
+            _on_line: LineHandler<'_>,
+        ) -> Result<CommandOutput, String> {
+            self.output(program, args).await
+        }
+    }
+
+    #[tokio::test]
+    async fn pulls_concurrently_into_separate_workspaces_and_removes_them() {
+        let runner = PullRunner::default();
+        let root = tempfile::tempdir().unwrap();
+
+        let (web, api) = tokio::join!(
+            pull_chart_into(&runner, root.path(), "acme/web", "web", "1.0.0"),
+            pull_chart_into(&runner, root.path(), "acme/api", "api", "2.0.0"),
+        );
+
+        for schema in [web.unwrap(), api.unwrap()] {
+            assert!(schema.inferred);
+            assert_eq!(
+                schema.schema["properties"]["replicaCount"]["type"],
+                "integer"
+            );
+        }
+        let destinations = runner.destinations.lock().unwrap();
+        assert_eq!(destinations.len(), 2);
+        assert_ne!(destinations[0], destinations[1]);
+        for destination in destinations.iter() {
+            assert!(destination.starts_with(root.path()));
+            assert!(!destination.exists());
+        }
+        assert_eq!(fs::read_dir(root.path()).unwrap().count(), 0);
+    }
+}
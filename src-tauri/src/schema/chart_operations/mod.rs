@@ -1,3 +1,4 @@
 //! Chart operations module
+#[allow(clippy::module_inception)]
 pub mod chart_operations;
 pub use chart_operations::*;
\ No newline at end of file
@@ -0,0 +1,481 @@
+//! Chart schema module
+//!
+//! Builds the values schema of a chart together with its dependencies. The
+//! schema of the chart and of each subchart is read from its
+//! `values.schema.json`, with its `$ref`s inlined, or inferred from the
+//! defaults in its `values.yaml` and the comments documenting them if it
+//! ships none. Subchart schemas are nested under the dependency's alias or
+//! name, the `condition` and `tags` toggles from `Chart.yaml` become boolean
+//! fields, and the `global` section of every subchart is merged into the
+//! parent's, where helm shares it from.
+
+use crate::helm::chart_tree::ChartTree;
+use crate::schema::refs::dereference_schema;
+use crate::schema::validate::INFERRED_KEYWORD;
+use crate::schema::values::infer_schema_from_values;
+use crate::schema::values_docs::{apply_values_docs, parse_values_docs};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+const GLOBAL_KEY: &str = "global";
+
+#[derive(Default, Deserialize)]
+struct ChartYaml {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    dependencies: Vec<Dependency>,
+}
+
+#[derive(Deserialize)]
+struct Dependency {
+    name: String,
+    #[serde(default)]
+    alias: Option<String>,
+    /// Comma-separated values paths, the first of which that is set enables the subchart.
+    #[serde(default)]
+    condition: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+fn chart_yaml(tree: &ChartTree) -> Option<ChartYaml> {
+    serde_yaml::from_str(tree.chart_yaml.as_deref()?).ok()
+}
+
+/// The chart's default values, an empty object if it has none.
+pub fn chart_values(tree: &ChartTree) -> Value {
+    tree.values_yaml
+        .as_deref()
+        .and_then(|yaml| serde_yaml::from_str::<Value>(yaml).ok())
+        .filter(Value::is_object)
+        .unwrap_or_else(|| json!({}))
+}
+
+/// The default values helm starts from when installing a chart: its own
+/// `values.yaml`, with the defaults of each subchart nested under the
+/// dependency's alias or name and their `global` sections merged into the
+/// parent's.
+pub fn default_values(tree: &ChartTree) -> Value {
+    let chart = chart_yaml(tree).unwrap_or_default();
+    let mut values = chart_values(tree);
+
+    for subchart in tree.subcharts.values() {
+        let Some(name) = subchart_name(subchart) else {
+            continue;
+        };
+        let mut sub_values = default_values(subchart);
+        if let Some(global) = sub_values.as_object_mut().and_then(|v| v.remove(GLOBAL_KEY)) {
+            coalesce_values(&mut values, &json!({ GLOBAL_KEY: global }));
+        }
+        for key in dependency_keys(&chart, &name) {
+            coalesce_values(&mut values, &json!({ key: sub_values }));
+        }
+    }
+    values
+}
+
+/// Fill in `defaults` that `values` does not set, as helm does: objects are
+/// merged key by key, and a key set to null in `values` removes the default.
+pub fn coalesce_values(values: &mut Value, defaults: &Value) {
+    let (Value::Object(values), Value::Object(defaults)) = (values, defaults) else {
+        return;
+    };
+    for (key, default) in defaults {
+        match values.get_mut(key) {
+            Some(Value::Null) => {
+                values.remove(key);
+            }
+            Some(value) => coalesce_values(value, default),
+            None => {
+                values.insert(key.clone(), default.clone());
+            }
+        }
+    }
+}
+
+/// Values schema of a chart, including its subcharts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValuesSchema {
+    pub schema: Value,
+    /// Whether the chart ships no `values.schema.json`, so that its schema
+    /// was inferred from the defaults in its `values.yaml`.
+    pub inferred: bool,
+    /// Default values of the chart and its subcharts, see [`default_values`].
+    pub defaults: Value,
+}
+
+/// Build the schema of a chart and its subcharts.
+///
+/// The chart's own schema comes from its `values.schema.json`, or is
+/// inferred from its `values.yaml` if it has none; an invalid
+/// `values.schema.json` is an error.
+pub fn build_chart_schema(tree: &ChartTree) -> Result<ValuesSchema, String> {
+    let (mut schema, inferred) = match schema_file(tree) {
+        Some(schema) => (schema?, false),
+        None => (infer_chart_schema(tree), true),
+    };
+    add_subchart_schemas(tree, &mut schema);
+    Ok(ValuesSchema {
+        schema,
+        inferred,
+        defaults: default_values(tree),
+    })
+}
+
+/// Schema of a subchart, inferred from its values if it has no usable schema file.
+fn subchart_schema(tree: &ChartTree) -> Value {
+    let mut schema = schema_file(tree)
+        .and_then(Result::ok)
+        .filter(Value::is_object)
+        .unwrap_or_else(|| infer_chart_schema(tree));
+    add_subchart_schemas(tree, &mut schema);
+    schema
+}
+
+/// The chart's `values.schema.json` with its references inlined, if it has one.
+fn schema_file(tree: &ChartTree) -> Option<Result<Value, String>> {
+    let json = tree.values_schema.as_deref()?;
+    let schema = serde_json::from_str(json)
+        .map(|schema| dereference_schema(&schema, &tree.schema_files))
+        .map_err(|e| format!("Invalid values.schema.json: {}", e));
+    Some(schema)
+}
+
+/// Schema inferred from a chart's default values, with the descriptions,
+/// types and sections documented in its `values.yaml`.
+///
+/// It is marked as inferred, so that values are not validated against it.
+fn infer_chart_schema(tree: &ChartTree) -> Value {
+    let mut schema = infer_schema_from_values(&chart_values(tree));
+    if let Some(yaml) = &tree.values_yaml {
+        apply_values_docs(&mut schema, &parse_values_docs(yaml));
+    }
+    schema[INFERRED_KEYWORD] = json!(true);
+    schema
+}
+
+fn add_subchart_schemas(tree: &ChartTree, schema: &mut Value) {
+    let chart = chart_yaml(tree).unwrap_or_default();
+    let mut globals = json!({ "type": "object", "properties": {} });
+
+    for subchart in tree.subcharts.values() {
+        let Some(name) = subchart_name(subchart) else {
+            continue;
+        };
+        let mut sub_schema = subchart_schema(subchart);
+        if let Some(global) = properties_mut(&mut sub_schema).and_then(|p| p.remove(GLOBAL_KEY)) {
+            merge_schema(&mut globals, global);
+        }
+
+        for key in dependency_keys(&chart, &name) {
+            nest(schema, key, sub_schema.clone());
+        }
+    }
+
+    let values = chart_values(tree);
+    for dependency in &chart.dependencies {
+        let key = dependency.alias.as_deref().unwrap_or(&dependency.name);
+        let description = format!("Enables the {} subchart", key);
+        let conditions = dependency.condition.iter().flat_map(|c| c.split(','));
+        let tags = dependency.tags.iter().map(|tag| format!("tags.{}", tag));
+        for path in conditions.map(str::to_string).chain(tags) {
+            let path = path.trim();
+            if !path.is_empty() {
+                add_toggle(schema, path, &values, &description);
+            }
+        }
+    }
+
+    let has_globals = globals["properties"]
+        .as_object()
+        .is_some_and(|p| !p.is_empty());
+    if let (true, Some(properties)) = (has_globals, properties_mut(schema)) {
+        // The parent's own definitions of global values win
+        if let Some(parent) = properties.remove(GLOBAL_KEY) {
+            merge_schema(&mut globals, parent);
+        }
+        properties.insert(GLOBAL_KEY.to_string(), globals);
+    }
+}
+
+/// Name of a subchart; files in charts/ without a Chart.yaml are not charts.
+fn subchart_name(subchart: &ChartTree) -> Option<String> {
+    chart_yaml(subchart)
+        .map(|c| c.name)
+        .filter(|n| !n.is_empty())
+}
+
+/// Values keys of a subchart: the same chart may be a dependency several
+/// times under different aliases.
+fn dependency_keys<'a>(chart: &'a ChartYaml, name: &'a str) -> Vec<&'a str> {
+    let mut keys: Vec<&str> = chart
+        .dependencies
+        .iter()
+        .filter(|d| d.name == name)
+        .map(|d| d.alias.as_deref().unwrap_or(&d.name))
+        .collect();
+    if keys.is_empty() {
+        keys.push(name);
+    }
+    keys
+}
+
+/// Nest a subchart schema under `key`, keeping what the parent defines there.
+fn nest(schema: &mut Value, key: &str, sub_schema: Value) {
+    let Some(properties) = properties_mut(schema) else {
+        return;
+    };
+    let mut nested = sub_schema;
+    if let Some(parent) = properties.remove(key) {
+        merge_schema(&mut nested, parent);
+    }
+    properties.insert(key.to_string(), nested);
+}
+
+/// Make sure the dotted values path of a toggle is a boolean field.
+fn add_toggle(schema: &mut Value, path: &str, values: &Value, description: &str) {
+    let segments: Vec<&str> = path.split('.').collect();
+    let Some((last, parents)) = segments.split_last() else {
+        return;
+    };
+
+    let mut node = schema;
+    for segment in parents {
+        let Some(properties) = properties_mut(node) else {
+            return;
+        };
+        node = properties
+            .entry(segment.to_string())
+            .or_insert_with(|| json!({ "type": "object" }));
+    }
+    let Some(properties) = properties_mut(node) else {
+        return;
+    };
+    let Some(toggle) = properties
+        .entry(last.to_string())
+        .or_insert_with(|| json!({}))
+        .as_object_mut()
+    else {
+        return;
+    };
+
+    toggle.entry("type").or_insert_with(|| json!("boolean"));
+    toggle
+        .entry("description")
+        .or_insert_with(|| json!(description));
+    let default = segments
+        .iter()
+        .try_fold(values, |value, segment| value.get(segment))
+        .filter(|value| value.is_boolean());
+    if let Some(default) = default {
+        toggle.entry("default").or_insert_with(|| default.clone());
+    }
+}
+
+/// The `properties` of an object schema, created if missing.
+fn properties_mut(schema: &mut Value) -> Option<&mut Map<String, Value>> {
+    let properties = schema
+        .as_object_mut()?
+        .entry("properties")
+        .or_insert_with(|| json!({}));
+    if !properties.is_object() {
+        *properties = json!({});
+    }
+    properties.as_object_mut()
+}
+
+/// Merge `overlay` into `base`: properties are merged recursively, any other
+/// keyword of `overlay` replaces the one in `base`.
+fn merge_schema(base: &mut Value, overlay: Value) {
+    let (Value::Object(base), Value::Object(overlay)) = (&mut *base, &overlay) else {
+        *base = overlay;
+        return;
+    };
+    for (key, value) in overlay {
+        match (base.get_mut(key), value) {
+            (Some(Value::Object(existing)), Value::Object(properties)) if key == "properties" => {
+                for (name, property) in properties {
+                    match existing.get_mut(name) {
+                        Some(current) => merge_schema(current, property.clone()),
+                        None => {
+                            existing.insert(name.clone(), property.clone());
+                        }
+                    }
+                }
+            }
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chart(chart_yaml: &str, values_yaml: Option<&str>, schema: Option<&str>) -> ChartTree {
+        ChartTree {
+            chart_yaml: Some(chart_yaml.to_string()),
+            values_yaml: values_yaml.map(String::from),
+            values_schema: schema.map(String::from),
+            schema_files: Default::default(),
+            subcharts: Default::default(),
+        }
+    }
+
+    fn web_chart() -> ChartTree {
+        let mut web = chart(
+            "name: web\nversion: 1.0.0\ndependencies:\n  - name: postgresql\n    alias: db\n    condition: db.enabled,global.db.enabled\n  - name: redis\n    condition: redis.enabled\n    tags: [cache]\n",
+            Some("db:\n  enabled: false\nredis:\n  enabled: true\n"),
+            Some(r#"{"type":"object","properties":{"replicaCount":{"type":"integer"},"db":{"description":"Bundled database"},"global":{"properties":{"imageRegistry":{"type":"string","description":"Parent registry"}}}}}"#),
+        );
+        web.subcharts.insert(
+            "postgresql-15.0.0.tgz".into(),
+            chart(
+                "name: postgresql\nversion: 15.0.0\n",
+                None,
+                Some(r#"{"type":"object","properties":{"auth":{"type":"object","properties":{"database":{"type":"string"}}},"global":{"type":"object","properties":{"postgresql":{"type":"object"},"imageRegistry":{"type":"string"}}}}}"#),
+            ),
+        );
+        web.subcharts.insert(
+            "redis".into(),
+            chart(
+                "name: redis\nversion: 18.0.0\n",
+                Some("architecture: replication\nreplica:\n  replicaCount: 3\nglobal:\n  redis:\n    password: \"\"\n"),
+                None,
+            ),
+        );
+        web.subcharts
+            .insert("README.md".into(), ChartTree::default());
+        web
+    }
+
+    #[test]
+    fn nests_subchart_schemas_under_alias_or_name() {
+        let ValuesSchema { schema, inferred, .. } = build_chart_schema(&web_chart()).unwrap();
+
+        assert!(!inferred);
+        let properties = &schema["properties"];
+
+        assert_eq!(properties["replicaCount"]["type"], "integer");
+        // Read from the subchart's schema, with the parent's annotations kept
+        assert_eq!(
+            properties["db"]["properties"]["auth"]["properties"]["database"]["type"],
+            "string"
+        );
+        assert_eq!(properties["db"]["description"], "Bundled database");
+        assert!(properties.get("postgresql").is_none());
+        // Inferred from the subchart's values
+        assert_eq!(
+            properties["redis"]["properties"]["architecture"]["type"],
+            "string"
+        );
+        assert_eq!(
+            properties["redis"]["properties"]["replica"]["properties"]["replicaCount"]["type"],
+            "integer"
+        );
+    }
+
+    #[test]
+    fn adds_condition_and_tag_toggles() {
+        let schema = build_chart_schema(&web_chart()).unwrap().schema;
+        let properties = &schema["properties"];
+
+        assert_eq!(properties["db"]["properties"]["enabled"]["type"], "boolean");
+        assert_eq!(properties["db"]["properties"]["enabled"]["default"], false);
+        assert_eq!(
+            properties["redis"]["properties"]["enabled"]["default"],
+            true
+        );
+        assert_eq!(
+            properties["global"]["properties"]["db"]["properties"]["enabled"]["type"],
+            "boolean"
+        );
+        assert_eq!(
+            properties["tags"]["properties"]["cache"]["description"],
+            "Enables the redis subchart"
+        );
+    }
+
+    #[test]
+    fn merges_subchart_globals_into_the_parent() {
+        let schema = build_chart_schema(&web_chart()).unwrap().schema;
+        let global = &schema["properties"]["global"]["properties"];
+
+        assert_eq!(global["postgresql"]["type"], "object");
+        assert_eq!(global["redis"]["properties"]["password"]["type"], "string");
+        assert_eq!(global["imageRegistry"]["description"], "Parent registry");
+        assert!(schema["properties"]["db"]["properties"]
+            .get("global")
+            .is_none());
+        assert!(schema["properties"]["redis"]["properties"]
+            .get("global")
+            .is_none());
+    }
+
+    #[test]
+    fn nests_subchart_defaults_under_alias_or_name() {
+        let defaults = build_chart_schema(&web_chart()).unwrap().defaults;
+
+        assert_eq!(defaults["redis"]["architecture"], "replication");
+        // The parent's values win over the subchart's
+        assert_eq!(defaults["redis"]["enabled"], true);
+        assert_eq!(defaults["global"]["redis"]["password"], "");
+        assert!(defaults["redis"].get("global").is_none());
+        assert_eq!(defaults["db"], json!({ "enabled": false }));
+    }
+
+    #[test]
+    fn coalesces_values_over_defaults() {
+        let mut values = json!({ "image": { "tag": "1.26" }, "ingress": null });
+        let defaults = json!({
+            "image": { "repository": "nginx", "tag": "1.25" },
+            "ingress": { "enabled": false },
+            "replicaCount": 1
+        });
+
+        coalesce_values(&mut values, &defaults);
+
+        assert_eq!(
+            values,
+            json!({ "image": { "repository": "nginx", "tag": "1.26" }, "replicaCount": 1 })
+        );
+    }
+
+    #[test]
+    fn infers_the_schema_of_charts_without_a_schema_file() {
+        let web = chart(
+            "name: web\nversion: 1.0.0\n",
+            Some("# -- Number of replicas\nreplicaCount: 1\nimage:\n  repository: nginx\n  tag: \"1.25\"\ningress:\n  enabled: false\n"),
+            None,
+        );
+
+        let ValuesSchema { schema, inferred, .. } = build_chart_schema(&web).unwrap();
+
+        assert!(inferred);
+        let properties = &schema["properties"];
+        assert_eq!(properties["replicaCount"]["type"], "integer");
+        assert_eq!(properties["replicaCount"]["default"], 1);
+        assert_eq!(
+            properties["replicaCount"]["description"],
+            "Number of replicas"
+        );
+        assert_eq!(
+            properties["image"]["properties"]["repository"]["default"],
+            "nginx"
+        );
+        assert_eq!(
+            properties["ingress"]["properties"]["enabled"]["type"],
+            "boolean"
+        );
+    }
+
+    #[test]
+    fn rejects_an_invalid_top_level_schema() {
+        let web = chart("name: web\n", None, Some("{"));
+
+        assert!(build_chart_schema(&web).is_err());
+    }
+}
@@ -27,7 +27,7 @@ pub fn check_cached_schema(
                         return None; // Don't return empty schema, try to generate
                     }
                 }
-            } else if schema.as_object().map_or(true, |obj| obj.is_empty()) {
+            } else if schema.as_object().is_none_or(|obj| obj.is_empty()) {
                 // Direct properties object without wrapper or empty object
                 println!("Cached schema is empty or not properly structured, will try to generate from helm values instead");
                 return None;
@@ -70,6 +70,8 @@ pub fn cache_and_return_empty_schema(
         repo_name,
         namespace,
         &empty_schema,
+        false,
+        None,
     )?;
 
     Ok(serde_json::to_string(&empty_schema).unwrap())
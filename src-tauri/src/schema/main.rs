@@ -4,21 +4,30 @@
 //! and deployed release values.
 
 use crate::db::{connection::DbConnection, schemas};
+use crate::error::RudderError;
 use crate::schema::get_schema_for_chart::{check_cached_schema, cache_and_return_empty_schema};
 use crate::schema::search::{try_all_repos_for_chart};
-use crate::schema::values::{generate_schema_from_helm_values};
-use crate::schema::utils::get_available_repos;
+use crate::schema::values::{generate_schema_from_helm_values, get_deployed_values, overlay_current_values};
+use crate::helm::chart_ref::{is_oci, ChartRef};
+use crate::helm::repo::list_repos;
+use crate::schema::chart_operations::pull_chart_and_extract_schema;
+use crate::runner::command::{CommandRunner, SharedRunner};
 
 /// Fetch the JSON schema for a Helm chart.
 ///
 /// This Tauri command orchestrates the schema generation process:
 /// - First checks the local SQLite cache.
 /// - If a cached schema is found, it is returned immediately.
+/// - OCI charts (`repo_name` starting with `oci://`) are pulled by reference.
 /// - If the cache miss occurs, it tries to locate available Helm repositories.
 /// - If a specified repository is found, it pulls the chart and extracts any schema.
-/// - If repositories are not found or the chart lacks a schema file,
+///   Charts without a schema file get one inferred from their `values.yaml`.
+/// - If repositories are not found or the chart cannot be pulled,
 ///   it generates a schema from current deployed values using `helm get values`.
-/// - Finally, the schema (or empty schema) is cached for future sessions.
+/// - Finally, the schema (or empty schema) is cached for future sessions,
+///   recording whether it was inferred.
+/// - When a release is given, its deployed values are overlaid on the chart's
+///   schema as the current values; they are not cached with it.
 #[tauri::command]
 pub async fn get_schema_for_chart(
     chart_name: String,
@@ -26,55 +35,99 @@ pub async fn get_schema_for_chart(
     repo_name: String,
     namespace: Option<String>,
     release_name: Option<String>,
-    app: tauri::AppHandle,
+    runner: tauri::State<'_, SharedRunner>,
     db: tauri::State<'_, DbConnection>,
-) -> Result<String, String> {
+) -> Result<String, RudderError> {
+    let runner = runner.inner().as_ref();
+
+    // OCI charts have no repository name; they are pulled and cached by their full reference
+    let oci_ref = if is_oci(&repo_name) {
+        Some(ChartRef::from_repo(&repo_name, &chart_name)?)
+    } else {
+        None
+    };
+    let repo_name = oci_ref.as_ref().map_or(repo_name, ChartRef::cache_repo);
+
     // First check if we have the schema cached in SQLite
     if let Some(result) = check_cached_schema(&db, &chart_name, &chart_version, &repo_name) {
-        return result;
+        let cached = result.map_err(RudderError::database)?;
+        return Ok(match (&release_name, &namespace) {
+            (Some(rel_name), Some(ns)) => match serde_json::from_str(&cached) {
+                Ok(schema) => with_current_values(runner, schema, rel_name, ns).await,
+                Err(_) => cached,
+            },
+            _ => cached,
+        });
     }
 
-    // When schema is not cached, we need to fetch it
-    // Get available repositories and check if the requested repo exists
-    let (available_repos, requested_repo_exists) = get_available_repos(&app, &repo_name).await;
-
-    // Determine which repos to try
-    let repos_to_try = if requested_repo_exists {
-        vec![repo_name.clone()]
+    let fetched = if let Some(oci_ref) = &oci_ref {
+        pull_chart_and_extract_schema(runner, &oci_ref.to_string(), &chart_name, &chart_version)
+            .await
+            .map(|schema| (schema, repo_name.clone()))
     } else {
-        available_repos
-    };
+        // When schema is not cached, we need to fetch it
+        // Get available repositories and check if the requested repo exists
+        let available_repos: Vec<String> = list_repos(runner)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|repo| repo.name)
+            .collect();
 
-    // If no repositories at all, cache empty schema and return error
-    if repos_to_try.is_empty() {
-        return cache_and_return_empty_schema(
-            &db,
-            &chart_name,
-            &chart_version,
-            "no-repos-available",
-            namespace.as_deref(),
-        );
-    }
+        // Determine which repos to try
+        let repos_to_try = if available_repos.contains(&repo_name) {
+            vec![repo_name.clone()]
+        } else {
+            available_repos
+        };
+
+        // If no repositories at all, cache empty schema and return error
+        if repos_to_try.is_empty() {
+            return cache_and_return_empty_schema(
+                &db,
+                &chart_name,
+                &chart_version,
+                "no-repos-available",
+                namespace.as_deref(),
+            )
+            .map_err(RudderError::database);
+        }
+
+        // Try to find the chart in the available repositories
+        try_all_repos_for_chart(runner, &repos_to_try, &chart_name, &chart_version)
+            .await
+            .map(|schema| {
+                let found_in = if repos_to_try.len() == 1 { &repo_name } else { &repos_to_try[0] };
+                (schema, found_in.clone())
+            })
+    };
 
-    // Try to find the chart in the available repositories
-    match try_all_repos_for_chart(&app, &repos_to_try, &chart_name, &chart_version).await {
-        Ok(schema) => {
+    match fetched {
+        Ok((values_schema, found_in)) => {
             // Cache the successful schema in SQLite
             schemas::store_chart_schema(
                 &db,
                 &chart_name,
                 &chart_version,
-                if repos_to_try.len() == 1 { &repo_name } else { &repos_to_try[0] },
+                &found_in,
                 namespace.as_deref(),
-                &schema,
-            )?;
-            Ok(serde_json::to_string(&schema).unwrap())
+                &values_schema.schema,
+                values_schema.inferred,
+                Some(&values_schema.defaults),
+            )
+            .map_err(RudderError::database)?;
+            match (&release_name, &namespace) {
+                (Some(rel_name), Some(ns)) => {
+                    Ok(with_current_values(runner, values_schema.schema, rel_name, ns).await)
+                }
+                _ => Ok(serde_json::to_string(&values_schema.schema).unwrap()),
+            }
         }
         Err(_) => {
             // If we have tried all repositories without success, regenerate from current values
             if let (Some(ref rel_name), Some(ref ns)) = (&release_name, &namespace) {
                 println!("Attempting to generate schema from current values for {}/{}", ns, rel_name);
-                match generate_schema_from_helm_values(&app, rel_name, ns).await {
+                match generate_schema_from_helm_values(runner, rel_name, ns).await {
                     Ok(generated_schema) => {
                         println!("Generated schema from helm values for {}/{}", ns, rel_name);
                         // Cache the generated schema
@@ -85,7 +138,10 @@ pub async fn get_schema_for_chart(
                             &repo_name,
                             namespace.as_deref(),
                             &generated_schema,
-                        )?;
+                            true,
+                            None,
+                        )
+                        .map_err(RudderError::database)?;
                         // Return result as this is the final output
                         Ok(serde_json::to_string(&generated_schema).unwrap())
                     }
@@ -99,6 +155,7 @@ pub async fn get_schema_for_chart(
                             &repo_name,
                             namespace.as_deref(),
                         )
+                        .map_err(RudderError::database)
                     }
                 }
             } else {
@@ -110,7 +167,201 @@ pub async fn get_schema_for_chart(
                     &repo_name,
                     namespace.as_deref(),
                 )
+                .map_err(RudderError::database)
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Overlay the values deployed in a release on a chart schema and serialize it.
+///
+/// The chart schema is returned unchanged if the values cannot be fetched.
+async fn with_current_values(
+    runner: &dyn CommandRunner,
+    mut schema: serde_json::Value,
+    release_name: &str,
+    namespace: &str,
+) -> String {
+    match get_deployed_values(runner, release_name, namespace).await {
+        Ok(values) => overlay_current_values(&mut schema, &values),
+        Err(e) => println!("Failed to get current values for {}/{}: {}", namespace, release_name, e),
+    }
+    serde_json::to_string(&schema).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::fixture::FixtureRunner;
+    use std::sync::{Arc, Mutex};
+    use tauri::Manager;
+
+    fn mock_app(fixture: FixtureRunner) -> tauri::App<tauri::test::MockRuntime> {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::connection::create_tables(&conn).unwrap();
+        let db: DbConnection = Arc::new(Mutex::new(conn));
+        let runner: SharedRunner = Arc::new(fixture);
+
+        let app = tauri::test::mock_app();
+        app.manage(db);
+        app.manage(runner);
+        app
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_deployed_values_when_chart_is_missing() {
+        let fixture = FixtureRunner::new()
+            .respond(
+                "helm repo list -o json",
+                r#"[{"name":"bitnami","url":"https://charts.bitnami.com/bitnami"}]"#,
+            )
+            .fail(
+                "helm search repo bitnami/nginx --version 15.4.0 -o json",
+                "Error: no chart found",
+                1,
+            )
+            .respond(
+                "helm get values web -n apps -o json",
+                r#"{"replicaCount": 2, "image": {"tag": "1.25"}}"#,
+            );
+        let app = mock_app(fixture);
+
+        let schema: serde_json::Value = serde_json::from_str(
+            &get_schema_for_chart(
+                "nginx".into(),
+                "15.4.0".into(),
+                "bitnami".into(),
+                Some("apps".into()),
+                Some("web".into()),
+                app.state(),
+                app.state(),
+            )
+            .await
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(schema["properties"]["replicaCount"]["type"], "integer");
+        assert_eq!(
+            schema["properties"]["image"]["properties"]["tag"]["type"],
+            "string"
+        );
+
+        let cached = schemas::list_cached_schemas(&app.state()).unwrap();
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached[0].repo_name, "bitnami");
+        assert!(cached[0].inferred);
+    }
+
+    #[tokio::test]
+    async fn overlays_deployed_values_on_the_cached_chart_schema() {
+        let fixture = FixtureRunner::new().respond(
+            "helm get values web -n apps -o json",
+            r#"{"replicaCount": 3, "image": {"tag": "1.26"}, "podLabels": {"team": "web"}}"#,
+        );
+        let app = mock_app(fixture);
+        let chart_schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "replicaCount": {"type": "integer", "default": 1},
+                "image": {
+                    "type": "object",
+                    "properties": {
+                        "repository": {"type": "string", "default": "nginx"},
+                        "tag": {"type": "string", "default": "1.25"}
+                    },
+                    "default": {"repository": "nginx", "tag": "1.25"}
+                },
+                "service": {"type": "object", "properties": {"port": {"type": "integer", "default": 80}}}
+            }
+        });
+        schemas::store_chart_schema(&app.state(), "nginx", "15.4.0", "bitnami", None, &chart_schema, true, None)
+            .unwrap();
+
+        let schema: serde_json::Value = serde_json::from_str(
+            &get_schema_for_chart(
+                "nginx".into(),
+                "15.4.0".into(),
+                "bitnami".into(),
+                Some("apps".into()),
+                Some("web".into()),
+                app.state(),
+                app.state(),
+            )
+            .await
+            .unwrap(),
+        )
+        .unwrap();
+
+        let properties = &schema["properties"];
+        assert_eq!(properties["replicaCount"]["default"], 3);
+        assert_eq!(properties["image"]["properties"]["tag"]["default"], "1.26");
+        assert_eq!(
+            properties["image"]["default"],
+            serde_json::json!({"repository": "nginx", "tag": "1.26"})
+        );
+        assert_eq!(properties["service"]["properties"]["port"]["default"], 80);
+        assert_eq!(properties["podLabels"]["properties"]["team"]["type"], "string");
+        // Objects without a chart default only get defaults on their properties
+        assert!(schema.get("default").is_none());
+
+        // The cache keeps the chart's own defaults
+        let cached = schemas::get_chart_schema(&app.state(), "nginx", "15.4.0", "bitnami")
+            .unwrap()
+            .unwrap();
+        assert_eq!(cached.schema_content, chart_schema);
+    }
+
+    #[tokio::test]
+    async fn pulls_oci_charts_by_reference_and_caches_them_under_it() {
+        let fixture = FixtureRunner::new()
+            .fail(
+                "helm pull oci://ghcr.io/acme/charts/web --version 1.2.0 --untar --destination *",
+                "Error: failed to authorize: 403 Forbidden",
+                1,
+            )
+            .respond("helm get values web -n apps -o json", r#"{"replicaCount": 2}"#);
+        let app = mock_app(fixture);
+
+        let schema: serde_json::Value = serde_json::from_str(
+            &get_schema_for_chart(
+                "web".into(),
+                "1.2.0".into(),
+                "oci://ghcr.io/acme/charts".into(),
+                Some("apps".into()),
+                Some("web".into()),
+                app.state(),
+                app.state(),
+            )
+            .await
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(schema["properties"]["replicaCount"]["type"], "integer");
+        let cached = schemas::list_cached_schemas(&app.state()).unwrap();
+        assert_eq!(cached[0].repo_name, "oci://ghcr.io/acme/charts/web");
+    }
+
+    #[tokio::test]
+    async fn caches_empty_schema_when_no_repos_are_configured() {
+        let fixture = FixtureRunner::new().respond("helm repo list -o json", "[]");
+        let app = mock_app(fixture);
+
+        let schema = get_schema_for_chart(
+            "nginx".into(),
+            "15.4.0".into(),
+            "bitnami".into(),
+            None,
+            None,
+            app.state(),
+            app.state(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(schema, r#"{"properties":{},"type":"object"}"#);
+        let cached = schemas::list_cached_schemas(&app.state()).unwrap();
+        assert_eq!(cached[0].repo_name, "no-repos-available");
+    }
+}
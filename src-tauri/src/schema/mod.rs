@@ -3,9 +3,13 @@ pub mod get_schema_for_chart;
 pub mod main;
 
 // Module files in same directory
+pub mod chart_schema;
+pub mod refs;
 pub mod search;
 pub mod values;
+pub mod values_docs;
 pub mod utils;
+pub mod validate;
 
 // Subdirectories
 pub mod chart_operations;
@@ -14,3 +18,6 @@ pub mod schema_utils;
 
 // Re-export main functions
 pub use main::get_schema_for_chart;
+
+/// Nesting limit for `$ref`, so that a recursive schema cannot loop forever.
+pub const MAX_REF_DEPTH: usize = 64;
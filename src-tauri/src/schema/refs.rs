@@ -0,0 +1,413 @@
+//! Schema references module
+//!
+//! Resolves the `$ref`s of a chart schema so that it is self-contained before
+//! it is cached: the form renders it without following references, and
+//! without network access. References may point into the schema itself, into
+//! other JSON files of the chart, or to the upstream Kubernetes JSON schemas,
+//! which are served from a bundled copy of the core/v1 and apps/v1
+//! definitions and the meta/v1 types they use.
+//!
+//! The bundled definitions are those of the Kubernetes v1.30 OpenAPI spec,
+//! as published by the k8s-openapi 0.22 crate, and are generated by
+//! `tools/k8s-definitions`.
+
+use crate::schema::MAX_REF_DEPTH;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+/// Kubernetes definitions that chart schemas refer to, e.g. for
+/// `resources`, `affinity` or `securityContext`.
+const K8S_DEFINITIONS: &str = include_str!("k8s_definitions.json");
+
+/// Path of the chart's own schema file, which other files may refer back to.
+const ROOT_FILE: &str = "values.schema.json";
+
+/// Keywords whose value is a map of names to schemas.
+const SCHEMA_MAP_KEYWORDS: &[&str] = &["properties", "patternProperties", "dependentSchemas"];
+
+/// Keywords whose value is data, in which a `$ref` key is not a reference.
+const DATA_KEYWORDS: &[&str] = &["const", "default", "enum", "examples"];
+
+/// Keywords that only hold the targets of references, unused once they are inlined.
+const DEFINITION_KEYWORDS: &[&str] = &["definitions", "$defs"];
+
+fn k8s_definitions() -> &'static Value {
+    static DEFINITIONS: OnceLock<Value> = OnceLock::new();
+    DEFINITIONS.get_or_init(|| {
+        serde_json::from_str(K8S_DEFINITIONS)
+            .expect("bundled Kubernetes definitions are valid JSON")
+    })
+}
+
+/// Document a reference is resolved against.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Document {
+    /// A JSON file of the chart, by its path relative to the chart root.
+    Chart(String),
+    Kubernetes,
+}
+
+/// Inline every `$ref` of a chart schema.
+///
+/// `files` are the chart's other JSON files by their path relative to the
+/// chart root. The `$ref`'s sibling keywords are kept on top of the inlined
+/// schema. References that cannot be resolved offline, and references back
+/// into a schema that is being inlined, are replaced by an open schema with a
+/// `$comment` naming them.
+pub fn dereference_schema(schema: &Value, files: &BTreeMap<String, String>) -> Value {
+    let mut resolver = Resolver {
+        root: schema,
+        files,
+        parsed: HashMap::new(),
+        stack: Vec::new(),
+    };
+    resolver.dereference(schema, &Document::Chart(ROOT_FILE.to_string()))
+}
+
+struct Resolver<'a> {
+    root: &'a Value,
+    files: &'a BTreeMap<String, String>,
+    /// Chart files parsed so far, `None` for files that are not valid JSON.
+    parsed: HashMap<String, Option<Value>>,
+    /// References being inlined, to detect recursion.
+    stack: Vec<String>,
+}
+
+impl Resolver<'_> {
+    fn dereference(&mut self, node: &Value, base: &Document) -> Value {
+        match node {
+            Value::Object(map) => self.dereference_object(map, base),
+            Value::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .map(|item| self.dereference(item, base))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+
+    fn dereference_object(&mut self, map: &Map<String, Value>, base: &Document) -> Value {
+        let mut siblings = Map::new();
+        for (key, value) in map {
+            if key == "$ref" || DEFINITION_KEYWORDS.contains(&key.as_str()) {
+                continue;
+            }
+            let value = match value {
+                Value::Object(schemas) if SCHEMA_MAP_KEYWORDS.contains(&key.as_str()) => {
+                    Value::Object(
+                        schemas
+                            .iter()
+                            .map(|(name, schema)| (name.clone(), self.dereference(schema, base)))
+                            .collect(),
+                    )
+                }
+                _ if DATA_KEYWORDS.contains(&key.as_str()) => value.clone(),
+                _ => self.dereference(value, base),
+            };
+            siblings.insert(key.clone(), value);
+        }
+
+        let Some(Value::String(reference)) = map.get("$ref") else {
+            return Value::Object(siblings);
+        };
+        let target = match self.resolve(reference, base) {
+            Some((document, fragment, target)) => {
+                let key = format!("{:?}#{}", document, fragment);
+                if self.stack.contains(&key) || self.stack.len() >= MAX_REF_DEPTH {
+                    Err("Recursive reference")
+                } else {
+                    self.stack.push(key);
+                    let target = self.dereference(&target, &document);
+                    self.stack.pop();
+                    Ok(target)
+                }
+            }
+            None => Err("Unresolved reference"),
+        };
+
+        match target {
+            Ok(Value::Object(mut target)) => {
+                target.extend(siblings);
+                Value::Object(target)
+            }
+            Ok(target) if siblings.is_empty() => target,
+            Ok(_) => Value::Object(siblings),
+            Err(reason) => {
+                siblings
+                    .entry("$comment")
+                    .or_insert_with(|| Value::String(format!("{}: {}", reason, reference)));
+                Value::Object(siblings)
+            }
+        }
+    }
+
+    /// The document a reference points into, the JSON pointer to its
+    /// target and the target schema.
+    fn resolve(&mut self, reference: &str, base: &Document) -> Option<(Document, String, Value)> {
+        let (location, fragment) = reference.split_once('#').unwrap_or((reference, ""));
+        let (document, fragment) = if location.is_empty() {
+            (base.clone(), fragment.to_string())
+        } else if location.contains("://") {
+            let name = kubernetes_definition(location, fragment)?;
+            // The fragment of a per-type file points into that type
+            let inner = match fragment.strip_prefix("/definitions/") {
+                Some(_) => "",
+                None => fragment,
+            };
+            (
+                Document::Kubernetes,
+                format!("/definitions/{}{}", name, inner),
+            )
+        } else {
+            let Document::Chart(base_path) = base else {
+                return None;
+            };
+            (
+                Document::Chart(join_path(base_path, location)?),
+                fragment.to_string(),
+            )
+        };
+
+        let target = pointer(self.document(&document)?, &fragment)?.clone();
+        Some((document, fragment, target))
+    }
+
+    fn document(&mut self, document: &Document) -> Option<&Value> {
+        match document {
+            Document::Kubernetes => Some(k8s_definitions()),
+            Document::Chart(path) if path == ROOT_FILE => Some(self.root),
+            Document::Chart(path) => {
+                let files = self.files;
+                self.parsed
+                    .entry(path.clone())
+                    .or_insert_with(|| {
+                        files
+                            .get(path)
+                            .and_then(|json| serde_json::from_str(json).ok())
+                    })
+                    .as_ref()
+            }
+        }
+    }
+}
+
+/// Value at a JSON pointer fragment; an empty fragment is the whole document.
+fn pointer<'a>(document: &'a Value, fragment: &str) -> Option<&'a Value> {
+    if fragment.is_empty() {
+        return Some(document);
+    }
+    // Plain-name anchors are not supported
+    fragment.starts_with('/').then_some(())?;
+    document.pointer(&fragment.replace("%24", "$").replace("%25", "%"))
+}
+
+/// Resolve a relative file reference against the file it appears in.
+///
+/// Paths leaving the chart root are not resolved.
+fn join_path(base: &str, relative: &str) -> Option<String> {
+    let mut segments: Vec<&str> = base.split('/').collect();
+    segments.pop();
+    for segment in relative.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+    Some(segments.join("/"))
+}
+
+/// Name of the bundled definition a Kubernetes JSON schema URL points to.
+///
+/// Both the `_definitions.json#/definitions/<name>` form and the per-type
+/// files such as `affinity-v1.json` or `deploymentstrategy-apps-v1.json`
+/// are recognised, whatever the mirror and Kubernetes version.
+fn kubernetes_definition(url: &str, fragment: &str) -> Option<String> {
+    let definitions = k8s_definitions()["definitions"].as_object()?;
+    if let Some(name) = fragment.strip_prefix("/definitions/") {
+        return definitions.contains_key(name).then(|| name.to_string());
+    }
+
+    let file = url.rsplit('/').next()?.strip_suffix(".json")?;
+    definitions
+        .keys()
+        .find(|name| type_file_name(name).eq_ignore_ascii_case(file))
+        .cloned()
+}
+
+/// File name of a definition in the per-type Kubernetes JSON schemas, e.g.
+/// `affinity-v1` for `io.k8s.api.core.v1.Affinity`.
+fn type_file_name(definition: &str) -> String {
+    let mut parts: Vec<&str> = definition.split('.').collect();
+    let kind = parts.pop().unwrap_or_default().to_lowercase();
+    match parts.as_slice() {
+        ["io", "k8s", "api", "core", version] => format!("{}-{}", kind, version),
+        ["io", "k8s", "api", group, version] => format!("{}-{}-{}", kind, group, version),
+        ["io", "k8s", "apimachinery", "pkg", "apis", group, version] => {
+            format!("{}-{}-{}", kind, group, version)
+        }
+        _ => kind,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn has_ref(value: &Value) -> bool {
+        match value {
+            Value::Object(map) => map.contains_key("$ref") || map.values().any(has_ref),
+            Value::Array(items) => items.iter().any(has_ref),
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn inlines_local_definitions_and_chart_files() {
+        let schema = json!({
+            "type": "object",
+            "definitions": {
+                "image": {
+                    "type": "object",
+                    "properties": { "tag": { "type": "string" } }
+                }
+            },
+            "properties": {
+                "image": { "$ref": "#/definitions/image", "description": "Container image" },
+                "service": { "$ref": "./schemas/service.json" },
+                "default": { "const": { "$ref": "not a reference" } }
+            }
+        });
+        let files = BTreeMap::from([(
+            "schemas/service.json".to_string(),
+            r##"{
+                "type": "object",
+                "$defs": { "protocol": { "enum": ["TCP", "UDP"] } },
+                "properties": {
+                    "protocol": { "$ref": "#/$defs/protocol" },
+                    "image": { "$ref": "../values.schema.json#/definitions/image" }
+                }
+            }"##
+            .to_string(),
+        )]);
+
+        let schema = dereference_schema(&schema, &files);
+
+        assert!(!has_ref(&schema["properties"]["image"]));
+        assert!(schema.get("definitions").is_none());
+        let image = &schema["properties"]["image"];
+        assert_eq!(image["properties"]["tag"]["type"], "string");
+        assert_eq!(image["description"], "Container image");
+        let service = &schema["properties"]["service"];
+        assert_eq!(
+            service["properties"]["protocol"]["enum"],
+            json!(["TCP", "UDP"])
+        );
+        assert_eq!(
+            service["properties"]["image"]["properties"]["tag"]["type"],
+            "string"
+        );
+        assert!(service.get("$defs").is_none());
+        // Data is left alone, even under a property named like a keyword
+        assert_eq!(
+            schema["properties"]["default"]["const"],
+            json!({ "$ref": "not a reference" })
+        );
+    }
+
+    #[test]
+    fn maps_kubernetes_urls_to_the_bundled_definitions() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "resources": {
+                    "$ref": "https://kubernetesjsonschema.dev/v1.18.1/_definitions.json#/definitions/io.k8s.api.core.v1.ResourceRequirements"
+                },
+                "affinity": {
+                    "$ref": "https://raw.githubusercontent.com/yannh/kubernetes-json-schema/master/v1.29.0-standalone-strict/affinity-v1.json"
+                },
+                "updateStrategy": {
+                    "$ref": "https://raw.githubusercontent.com/yannh/kubernetes-json-schema/master/master/deploymentstrategy-apps-v1.json"
+                }
+            }
+        });
+
+        let schema = dereference_schema(&schema, &BTreeMap::new());
+
+        assert!(!has_ref(&schema));
+        let properties = &schema["properties"];
+        assert_eq!(
+            properties["resources"]["properties"]["limits"]["additionalProperties"]["oneOf"],
+            json!([{ "type": "string" }, { "type": "number" }])
+        );
+        let node_affinity = &properties["affinity"]["properties"]["nodeAffinity"];
+        assert_eq!(
+            node_affinity["properties"]["requiredDuringSchedulingIgnoredDuringExecution"]
+                ["properties"]["nodeSelectorTerms"]["items"]["properties"]["matchExpressions"]
+                ["items"]["required"],
+            json!(["key", "operator"])
+        );
+        assert_eq!(
+            properties["updateStrategy"]["properties"]["rollingUpdate"]["properties"]["maxSurge"]
+                ["oneOf"],
+            json!([{ "type": "string" }, { "type": "integer" }])
+        );
+    }
+
+    #[test]
+    fn leaves_unresolvable_and_recursive_references_open() {
+        let schema = json!({
+            "definitions": {
+                "node": {
+                    "type": "object",
+                    "properties": { "children": { "type": "array", "items": { "$ref": "#/definitions/node" } } }
+                }
+            },
+            "properties": {
+                "tree": { "$ref": "#/definitions/node" },
+                "ingress": { "$ref": "https://example.com/ingress.json", "type": "object" },
+                "missing": { "$ref": "schemas/missing.json" }
+            }
+        });
+
+        let schema = dereference_schema(&schema, &BTreeMap::new());
+
+        assert!(!has_ref(&schema));
+        let properties = &schema["properties"];
+        assert_eq!(
+            properties["tree"]["properties"]["children"]["items"]["$comment"],
+            "Recursive reference: #/definitions/node"
+        );
+        assert_eq!(properties["ingress"]["type"], "object");
+        assert_eq!(
+            properties["ingress"]["$comment"],
+            "Unresolved reference: https://example.com/ingress.json"
+        );
+        assert_eq!(
+            properties["missing"]["$comment"],
+            "Unresolved reference: schemas/missing.json"
+        );
+    }
+
+    #[test]
+    fn bundled_definitions_are_self_contained() {
+        let definitions = k8s_definitions()["definitions"].as_object().unwrap();
+        for name in definitions.keys() {
+            let reference = json!({ "$ref": format!("https://kubernetesjsonschema.dev/master/_definitions.json#/definitions/{}", name) });
+
+            let schema = dereference_schema(&reference, &BTreeMap::new());
+
+            assert!(!has_ref(&schema), "{} has unresolved references", name);
+            assert!(
+                schema.get("$comment").is_none(),
+                "{} was not resolved",
+                name
+            );
+        }
+    }
+}
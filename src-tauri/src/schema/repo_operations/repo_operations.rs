@@ -2,8 +2,6 @@
 //!
 //! This module was automatically extracted by Rusty Refactor.
 
-use tauri_plugin_shell::ShellExt;
-
 /// Check if a schema is cached in the database and return it if found.
 ///
 /// Queries the provided database connection for a cached chart schema
@@ -46,50 +44,3 @@ pub fn check_for_cached_schema(
         }
     }
 }
-
-/// Get list of available Helm repositories and check if the requested repo exists.
-///
-/// Runs `helm repo list` via the provided `shell` extension, parses the
-/// command output, collects repository names into a vector and determines
-/// whether `requested_repo` is present in that list. The function returns
-/// the list of discovered repositories along with a boolean indicating
-/// presence of the requested repository.
-///
-/// # Parameters
-/// - `app`: Tauri AppHandle used to execute the `helm` command asynchronously.
-/// - `requested_repo`: repository name to check for presence in the list.
-///
-/// # Returns
-/// `(Vec<String>, bool)` where the first element is the list of repository
-/// names and the second element is `true` if `requested_repo` was found.
-pub async fn get_available_repos(
-    app: &tauri::AppHandle,
-    requested_repo: &str,
-) -> (Vec<String>, bool) {
-    let shell = app.shell();
-    let repo_list_output = shell.command("helm").args(["repo", "list"]).output().await;
-
-    let mut available_repos = Vec::new();
-    let mut requested_repo_exists = false;
-
-    if let Ok(result) = repo_list_output {
-        if result.status.success() {
-            let output = String::from_utf8_lossy(&result.stdout);
-            for line in output.lines().skip(1) {
-                if line.trim().is_empty() {
-                    continue;
-                }
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 1 {
-                    let repo_name_from_list = parts[0];
-                    available_repos.push(repo_name_from_list.to_string());
-                    if repo_name_from_list == requested_repo {
-                        requested_repo_exists = true;
-                    }
-                }
-            }
-        }
-    };
-
-    (available_repos, requested_repo_exists)
-}
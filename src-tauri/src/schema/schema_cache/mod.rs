@@ -1,3 +1,4 @@
 //! Schema cache module
+#[allow(clippy::module_inception)]
 pub mod schema_cache;
 pub use schema_cache::*;
\ No newline at end of file
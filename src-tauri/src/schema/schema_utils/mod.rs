@@ -1,3 +1,4 @@
 //! Schema utils module
+#[allow(clippy::module_inception)]
 pub mod schema_utils;
 pub use schema_utils::*;
\ No newline at end of file
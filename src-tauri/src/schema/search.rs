@@ -4,20 +4,21 @@
 //! location, and extract the chart's JSON schema (values.schema.json) if present.
 
 use crate::schema::chart_operations::pull_chart_and_extract_schema;
+use crate::schema::chart_schema::ValuesSchema;
 use crate::schema::utils::is_network_error;
-use tauri_plugin_shell::ShellExt;
+use crate::runner::command::CommandRunner;
 
 /// Try all repositories to find a chart and its schema
 pub async fn try_all_repos_for_chart(
-    app: &tauri::AppHandle,
+    runner: &dyn CommandRunner,
     repos: &[String],
     chart_name: &str,
     chart_version: &str,
-) -> Result<serde_json::Value, String> {
+) -> Result<ValuesSchema, String> {
     let mut last_error = String::new();
 
     for current_repo in repos {
-        match try_repo_for_chart(app, current_repo, chart_name, chart_version).await {
+        match try_repo_for_chart(runner, current_repo, chart_name, chart_version).await {
             Ok(schema) => return Ok(schema),
             Err(e) => {
                 if is_network_error(&e) {
@@ -33,16 +34,14 @@ pub async fn try_all_repos_for_chart(
 
 /// Try to find and pull a chart from a specific repository
 pub async fn try_repo_for_chart(
-    app: &tauri::AppHandle,
+    runner: &dyn CommandRunner,
     repo_name: &str,
     chart_name: &str,
     chart_version: &str,
-) -> Result<serde_json::Value, String> {
-    let shell = app.shell();
+) -> Result<ValuesSchema, String> {
     // First, check if the chart is available in this repo
-    let search_output = shell
-        .command("helm")
-        .args([
+    let search_output = runner
+        .run("helm", [
             "search",
             "repo",
             &format!("{}/{}", repo_name, chart_name),
@@ -51,13 +50,13 @@ pub async fn try_repo_for_chart(
             "-o",
             "json",
         ])
-        .output()
         .await;
 
     match search_output {
-        Ok(result) if result.status.success() => {
+        Ok(result) if result.success() => {
             // Chart found in repo, now try to pull it to get the schema
-            pull_chart_and_extract_schema(app, repo_name, chart_name, chart_version).await
+            let chart_ref = format!("{}/{}", repo_name, chart_name);
+            pull_chart_and_extract_schema(runner, &chart_ref, chart_name, chart_version).await
         }
         Ok(result) => {
             let stderr = String::from_utf8_lossy(&result.stderr);
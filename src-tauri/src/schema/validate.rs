@@ -0,0 +1,734 @@
+//! Values validation module
+//!
+//! Checks chart values against a chart's JSON schema before helm is run, so
+//! the UI can point at every offending field instead of showing helm's first
+//! error. Draft-07 and 2020-12 schemas are supported; `format` is treated as
+//! an annotation, as 2020-12 does by default, and references outside the
+//! schema are not followed.
+
+use crate::db::{connection::DbConnection, schemas, schemas::ChartSchema};
+use crate::error::{ErrorKind, RudderError};
+use crate::helm::chart_ref::{is_oci, ChartRef};
+use crate::helm::local_chart::{is_local_chart, load_local_chart};
+use crate::schema::chart_schema::coalesce_values;
+use crate::schema::MAX_REF_DEPTH;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::State;
+
+/// Marks a schema that was inferred from values rather than declared by the
+/// chart. Inferred schemas only describe the defaults, so values are not
+/// validated against them.
+pub const INFERRED_KEYWORD: &str = "x-inferred";
+
+/// A value that does not match the schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationError {
+    /// JSON pointer to the value, e.g. `/image/tag`; empty for the values themselves.
+    pub path: String,
+    /// Schema keyword the value failed, e.g. `type` or `required`.
+    pub keyword: String,
+    pub message: String,
+}
+
+/// Validate values against a JSON schema and return every error found.
+pub fn validate_values(schema: &Value, values: &Value) -> Vec<ValidationError> {
+    let mut validator = Validator {
+        root: schema,
+        // `$ref` replaces its sibling keywords before 2019-09
+        refs_override_siblings: !schema
+            .get("$schema")
+            .and_then(Value::as_str)
+            .is_some_and(|s| s.contains("2019-09") || s.contains("2020-12")),
+        depth: 0,
+        errors: Vec::new(),
+    };
+    validator.validate(schema, values, "");
+    validator.errors
+}
+
+/// Validate values against a cached chart schema, once merged with the
+/// chart's default values as helm does, so that keys the chart defaults do
+/// not have to be set again.
+///
+/// Nothing is checked against an inferred schema.
+pub fn validate_chart_values(cached: &ChartSchema, values: &Value) -> Vec<ValidationError> {
+    if cached.inferred {
+        return Vec::new();
+    }
+    let mut merged = values.clone();
+    if let Some(defaults) = &cached.default_values {
+        coalesce_values(&mut merged, defaults);
+    }
+    validate_values(&cached.schema_content, &merged)
+}
+
+/// Chart a schema is cached under by `get_schema_for_chart`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaKey {
+    pub chart_name: String,
+    pub chart_version: String,
+    pub repo_name: String,
+}
+
+impl SchemaKey {
+    /// The cached schema of the chart, if any.
+    pub fn cached_schema(&self, db: &State<DbConnection>) -> Result<Option<ChartSchema>, RudderError> {
+        // OCI charts are cached under their full reference, as in get_schema_for_chart
+        let repo_name = if is_oci(&self.repo_name) {
+            ChartRef::from_repo(&self.repo_name, &self.chart_name)?.cache_repo()
+        } else {
+            self.repo_name.clone()
+        };
+        schemas::get_chart_schema(db, &self.chart_name, &self.chart_version, &repo_name)
+            .map_err(RudderError::database)
+    }
+}
+
+/// Validate the values of an upgrade or dry-run against the schema of the
+/// chart being installed.
+///
+/// A local chart is read from disk, so that edits to it are picked up. For
+/// other charts the schema cached under `schema_key` is used, or else the one
+/// cached for the chart reference and `version`. Inferred schemas are never
+/// checked.
+///
+/// Returns a `SchemaNotFound` error if no schema is cached for the chart.
+pub fn validate_upgrade_values(
+    db: &State<DbConnection>,
+    chart_path: &str,
+    version: Option<&str>,
+    schema_key: Option<&SchemaKey>,
+    values: &Value,
+) -> Result<(), RudderError> {
+    let chart_schema = if is_local_chart(chart_path) {
+        let chart = match load_local_chart(db, chart_path) {
+            Ok(chart) => chart,
+            Err(e) if e.kind == ErrorKind::Database => return Err(e),
+            // A chart that cannot be read is left to helm to report
+            Err(_) => return Ok(()),
+        };
+        ChartSchema {
+            repo_name: chart.cache_repo(),
+            chart_name: chart.name,
+            chart_version: chart.version,
+            namespace: None,
+            schema_content: chart.schema,
+            inferred: chart.schema_inferred,
+            default_values: Some(chart.values),
+            created_at: None,
+        }
+    } else {
+        let schema_key = match (schema_key, ChartRef::parse(chart_path), version) {
+            (Some(schema_key), _, _) => schema_key.clone(),
+            (None, Ok(chart_ref), Some(version)) => SchemaKey {
+                chart_name: chart_ref.name().to_string(),
+                chart_version: version.to_string(),
+                repo_name: chart_ref.cache_repo(),
+            },
+            // Without a version the chart being installed is not known yet
+            _ => {
+                return Err(RudderError::new(
+                    ErrorKind::SchemaNotFound,
+                    format!("No schema found for chart '{}'", chart_path),
+                ))
+            }
+        };
+        schema_key.cached_schema(db)?.ok_or_else(|| {
+            RudderError::new(
+                ErrorKind::SchemaNotFound,
+                format!(
+                    "No schema found for chart {} {} from {}",
+                    schema_key.chart_name, schema_key.chart_version, schema_key.repo_name
+                ),
+            )
+        })?
+    };
+
+    let errors = validate_chart_values(&chart_schema, values);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(RudderError::schema_violations(errors))
+    }
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+    refs_override_siblings: bool,
+    depth: usize,
+    errors: Vec<ValidationError>,
+}
+
+impl<'a> Validator<'a> {
+    /// Errors of a value against a subschema, without recording them.
+    fn errors_for(&self, schema: &Value, value: &Value, path: &str) -> Vec<ValidationError> {
+        let mut validator = Validator {
+            root: self.root,
+            refs_override_siblings: self.refs_override_siblings,
+            depth: self.depth,
+            errors: Vec::new(),
+        };
+        validator.validate(schema, value, path);
+        validator.errors
+    }
+
+    fn is_valid(&self, schema: &Value, value: &Value) -> bool {
+        self.errors_for(schema, value, "").is_empty()
+    }
+
+    fn error(&mut self, path: &str, keyword: &str, message: String) {
+        self.errors.push(ValidationError {
+            path: path.to_string(),
+            keyword: keyword.to_string(),
+            message,
+        });
+    }
+
+    fn validate(&mut self, schema: &Value, value: &Value, path: &str) {
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => {
+                self.error(path, "false", "No value is allowed here".to_string());
+                return;
+            }
+            Value::Object(schema) => schema,
+            _ => return,
+        };
+        if schema.get(INFERRED_KEYWORD) == Some(&Value::Bool(true)) {
+            return;
+        }
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            if let Some(target) = resolve_ref(self.root, reference) {
+                if self.depth < MAX_REF_DEPTH {
+                    self.depth += 1;
+                    self.validate(target, value, path);
+                    self.depth -= 1;
+                }
+            }
+            if self.refs_override_siblings {
+                return;
+            }
+        }
+
+        self.validate_type(schema, value, path);
+        self.validate_combinators(schema, value, path);
+        match value {
+            Value::Object(object) => self.validate_object(schema, object, path),
+            Value::Array(items) => self.validate_array(schema, items, path),
+            Value::String(string) => self.validate_string(schema, string, path),
+            Value::Number(_) => self.validate_number(schema, value, path),
+            _ => {}
+        }
+    }
+
+    fn validate_type(&mut self, schema: &Map<String, Value>, value: &Value, path: &str) {
+        if let Some(expected) = schema.get("type") {
+            let types: Vec<&str> = match expected {
+                Value::String(name) => vec![name.as_str()],
+                Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !types.is_empty() && !types.iter().any(|t| type_matches(t, value)) {
+                self.error(
+                    path,
+                    "type",
+                    format!("Expected {}, got {}", types.join(" or "), type_name(value)),
+                );
+            }
+        }
+        if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+            if !allowed.iter().any(|a| json_equal(a, value)) {
+                let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+                self.error(
+                    path,
+                    "enum",
+                    format!("Must be one of {}", allowed.join(", ")),
+                );
+            }
+        }
+        if let Some(constant) = schema.get("const") {
+            if !json_equal(constant, value) {
+                self.error(path, "const", format!("Must be {}", constant));
+            }
+        }
+    }
+
+    fn validate_combinators(&mut self, schema: &Map<String, Value>, value: &Value, path: &str) {
+        if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+            for subschema in all {
+                self.validate(subschema, value, path);
+            }
+        }
+        if let Some(any) = schema.get("anyOf").and_then(Value::as_array) {
+            if !any.iter().any(|s| self.is_valid(s, value)) {
+                self.error(
+                    path,
+                    "anyOf",
+                    "Does not match any of the allowed schemas".to_string(),
+                );
+            }
+        }
+        if let Some(one) = schema.get("oneOf").and_then(Value::as_array) {
+            let matches = one.iter().filter(|s| self.is_valid(s, value)).count();
+            if matches != 1 {
+                self.error(
+                    path,
+                    "oneOf",
+                    format!(
+                        "Must match exactly one of the allowed schemas, matches {}",
+                        matches
+                    ),
+                );
+            }
+        }
+        if let Some(not) = schema.get("not") {
+            if self.is_valid(not, value) {
+                self.error(
+                    path,
+                    "not",
+                    "Matches a schema it must not match".to_string(),
+                );
+            }
+        }
+        if let Some(condition) = schema.get("if") {
+            let branch = if self.is_valid(condition, value) {
+                schema.get("then")
+            } else {
+                schema.get("else")
+            };
+            if let Some(branch) = branch {
+                self.validate(branch, value, path);
+            }
+        }
+    }
+
+    fn validate_object(
+        &mut self,
+        schema: &Map<String, Value>,
+        object: &Map<String, Value>,
+        path: &str,
+    ) {
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(key) {
+                    self.error(
+                        &child_path(path, key),
+                        "required",
+                        "Required value is missing".to_string(),
+                    );
+                }
+            }
+        }
+        let count = object.len() as u64;
+        if let Some(min) = schema.get("minProperties").and_then(Value::as_u64) {
+            if count < min {
+                self.error(
+                    path,
+                    "minProperties",
+                    format!("Must have at least {} properties", min),
+                );
+            }
+        }
+        if let Some(max) = schema.get("maxProperties").and_then(Value::as_u64) {
+            if count > max {
+                self.error(
+                    path,
+                    "maxProperties",
+                    format!("Must have at most {} properties", max),
+                );
+            }
+        }
+
+        // `dependencies` is the draft-07 form of dependentRequired and dependentSchemas
+        let dependencies = ["dependentRequired", "dependentSchemas", "dependencies"]
+            .into_iter()
+            .filter_map(|keyword| schema.get(keyword)?.as_object());
+        for dependencies in dependencies {
+            for (key, dependency) in dependencies.iter().filter(|(k, _)| object.contains_key(*k)) {
+                match dependency {
+                    Value::Array(required) => {
+                        for other in required.iter().filter_map(Value::as_str) {
+                            if !object.contains_key(other) {
+                                self.error(
+                                    &child_path(path, other),
+                                    "dependentRequired",
+                                    format!("Required when '{}' is set", key),
+                                );
+                            }
+                        }
+                    }
+                    subschema => self.validate(subschema, &Value::Object(object.clone()), path),
+                }
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let patterns: Vec<(Regex, &Value)> = schema
+            .get("patternProperties")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .filter_map(|(pattern, subschema)| Some((Regex::new(pattern).ok()?, subschema)))
+            .collect();
+        let additional = schema.get("additionalProperties");
+        let property_names = schema.get("propertyNames");
+
+        for (key, value) in object {
+            let value_path = child_path(path, key);
+            if let Some(property_names) = property_names {
+                for mut error in
+                    self.errors_for(property_names, &Value::String(key.clone()), &value_path)
+                {
+                    error.keyword = format!("propertyNames/{}", error.keyword);
+                    self.errors.push(error);
+                }
+            }
+
+            let mut matched = false;
+            if let Some(subschema) = properties.and_then(|p| p.get(key)) {
+                matched = true;
+                self.validate(subschema, value, &value_path);
+            }
+            for (pattern, subschema) in &patterns {
+                if pattern.is_match(key) {
+                    matched = true;
+                    self.validate(subschema, value, &value_path);
+                }
+            }
+            match additional {
+                Some(Value::Bool(false)) if !matched => self.error(
+                    &value_path,
+                    "additionalProperties",
+                    "Property is not allowed by the schema".to_string(),
+                ),
+                Some(subschema) if !matched => self.validate(subschema, value, &value_path),
+                _ => {}
+            }
+        }
+    }
+
+    fn validate_array(&mut self, schema: &Map<String, Value>, items: &[Value], path: &str) {
+        let count = items.len() as u64;
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+            if count < min {
+                self.error(
+                    path,
+                    "minItems",
+                    format!("Must have at least {} items", min),
+                );
+            }
+        }
+        if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+            if count > max {
+                self.error(path, "maxItems", format!("Must have at most {} items", max));
+            }
+        }
+        if schema.get("uniqueItems") == Some(&Value::Bool(true)) {
+            let duplicate = items
+                .iter()
+                .enumerate()
+                .any(|(i, item)| items[..i].iter().any(|other| json_equal(item, other)));
+            if duplicate {
+                self.error(path, "uniqueItems", "Items must be unique".to_string());
+            }
+        }
+
+        // 2020-12 has prefixItems followed by items, draft-07 an items array followed by additionalItems
+        let (prefix, rest) = match (schema.get("prefixItems"), schema.get("items")) {
+            (Some(Value::Array(prefix)), rest) => (prefix.as_slice(), rest),
+            (_, Some(Value::Array(prefix))) => (prefix.as_slice(), schema.get("additionalItems")),
+            (_, rest) => (&[][..], rest),
+        };
+        for (index, item) in items.iter().enumerate() {
+            let subschema = match prefix.get(index) {
+                Some(subschema) => subschema,
+                None => match rest {
+                    Some(subschema) => subschema,
+                    None => break,
+                },
+            };
+            self.validate(subschema, item, &format!("{}/{}", path, index));
+        }
+
+        if let Some(contains) = schema.get("contains") {
+            let matches = items
+                .iter()
+                .filter(|item| self.is_valid(contains, item))
+                .count() as u64;
+            let min = schema
+                .get("minContains")
+                .and_then(Value::as_u64)
+                .unwrap_or(1);
+            let max = schema.get("maxContains").and_then(Value::as_u64);
+            if matches < min || max.is_some_and(|max| matches > max) {
+                self.error(
+                    path,
+                    "contains",
+                    format!("{} items match the required schema", matches),
+                );
+            }
+        }
+    }
+
+    fn validate_string(&mut self, schema: &Map<String, Value>, string: &str, path: &str) {
+        let length = string.chars().count() as u64;
+        if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+            if length < min {
+                self.error(
+                    path,
+                    "minLength",
+                    format!("Must be at least {} characters long", min),
+                );
+            }
+        }
+        if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+            if length > max {
+                self.error(
+                    path,
+                    "maxLength",
+                    format!("Must be at most {} characters long", max),
+                );
+            }
+        }
+        if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+            // Patterns the regex engine cannot compile are not enforced
+            if Regex::new(pattern).is_ok_and(|regex| !regex.is_match(string)) {
+                self.error(
+                    path,
+                    "pattern",
+                    format!("Must match the pattern {}", pattern),
+                );
+            }
+        }
+    }
+
+    fn validate_number(&mut self, schema: &Map<String, Value>, value: &Value, path: &str) {
+        let Some(number) = value.as_f64() else {
+            return;
+        };
+        let bound = |keyword: &str| schema.get(keyword).and_then(Value::as_f64);
+
+        if let Some(minimum) = bound("minimum") {
+            // Draft-04 schemas still found in charts make the bound exclusive with a boolean
+            let exclusive = schema.get("exclusiveMinimum") == Some(&Value::Bool(true));
+            if number < minimum || (exclusive && number == minimum) {
+                self.error(path, "minimum", format!("Must be at least {}", minimum));
+            }
+        }
+        if let Some(maximum) = bound("maximum") {
+            let exclusive = schema.get("exclusiveMaximum") == Some(&Value::Bool(true));
+            if number > maximum || (exclusive && number == maximum) {
+                self.error(path, "maximum", format!("Must be at most {}", maximum));
+            }
+        }
+        if let Some(minimum) = bound("exclusiveMinimum") {
+            if number <= minimum {
+                self.error(
+                    path,
+                    "exclusiveMinimum",
+                    format!("Must be greater than {}", minimum),
+                );
+            }
+        }
+        if let Some(maximum) = bound("exclusiveMaximum") {
+            if number >= maximum {
+                self.error(
+                    path,
+                    "exclusiveMaximum",
+                    format!("Must be less than {}", maximum),
+                );
+            }
+        }
+        if let Some(divisor) = bound("multipleOf").filter(|d| *d > 0.0) {
+            let quotient = number / divisor;
+            if (quotient - quotient.round()).abs() > 1e-9 {
+                self.error(
+                    path,
+                    "multipleOf",
+                    format!("Must be a multiple of {}", divisor),
+                );
+            }
+        }
+    }
+}
+
+/// Resolve a reference within the schema, e.g. `#/definitions/port` or `#/$defs/port`.
+fn resolve_ref<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    let pointer = reference.strip_prefix('#')?;
+    if pointer.is_empty() {
+        return Some(root);
+    }
+    root.pointer(&pointer.replace("%25", "%"))
+}
+
+/// JSON pointer of a property of the value at `path`.
+fn child_path(path: &str, key: &str) -> String {
+    format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"))
+}
+
+fn type_matches(name: &str, value: &Value) -> bool {
+    match name {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Equality as JSON schema defines it, where `1` and `1.0` are equal.
+fn json_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| json_equal(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, value)| b.get(key).is_some_and(|other| json_equal(value, other)))
+        }
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn errors(schema: Value, values: Value) -> Vec<(String, String)> {
+        let mut errors: Vec<(String, String)> = validate_values(&schema, &values)
+            .into_iter()
+            .map(|e| (e.path, e.keyword))
+            .collect();
+        errors.sort();
+        errors
+    }
+
+    fn pair(path: &str, keyword: &str) -> (String, String) {
+        (path.to_string(), keyword.to_string())
+    }
+
+    #[test]
+    fn reports_every_invalid_value_with_its_path() {
+        let schema = json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "type": "object",
+            "required": ["image"],
+            "properties": {
+                "replicaCount": {"type": "integer", "minimum": 1},
+                "service": {
+                    "type": "object",
+                    "properties": {
+                        "type": {"enum": ["ClusterIP", "NodePort", "LoadBalancer"]},
+                        "port": {"$ref": "#/definitions/port"}
+                    },
+                    "additionalProperties": false
+                },
+                "ingress": {
+                    "type": "object",
+                    "properties": {
+                        "hosts": {"type": "array", "items": {"type": "string", "pattern": "^[a-z0-9.-]+$"}}
+                    }
+                }
+            },
+            "definitions": {
+                "port": {"type": "integer", "maximum": 65535}
+            }
+        });
+        let values = json!({
+            "replicaCount": 0,
+            "service": {"type": "Internal", "port": 80000, "nodePort": 30080},
+            "ingress": {"hosts": ["web.example.com", "Web_Example"]}
+        });
+
+        assert_eq!(
+            errors(schema, values),
+            vec![
+                pair("/image", "required"),
+                pair("/ingress/hosts/1", "pattern"),
+                pair("/replicaCount", "minimum"),
+                pair("/service/nodePort", "additionalProperties"),
+                pair("/service/port", "maximum"),
+                pair("/service/type", "enum"),
+            ]
+        );
+    }
+
+    #[test]
+    fn supports_2020_12_keywords() {
+        let schema = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$defs": {"name": {"type": "string", "minLength": 1}},
+            "type": "object",
+            "properties": {
+                "args": {
+                    "type": "array",
+                    "prefixItems": [{"const": "serve"}],
+                    "items": {"$ref": "#/$defs/name", "maxLength": 8}
+                },
+                "tls": {
+                    "type": "object",
+                    "dependentRequired": {"enabled": ["secretName"]}
+                },
+                "resources": {"oneOf": [{"type": "null"}, {"type": "object"}]}
+            }
+        });
+        let values = json!({
+            "args": ["run", "", "verbose-logging"],
+            "tls": {"enabled": true},
+            "resources": {}
+        });
+
+        assert_eq!(
+            errors(schema, values),
+            vec![
+                pair("/args/0", "const"),
+                pair("/args/1", "minLength"),
+                pair("/args/2", "maxLength"),
+                pair("/tls/secretName", "dependentRequired"),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_inferred_schemas_and_accepts_valid_values() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "port": {"type": "integer"},
+                "sidecar": {"x-inferred": true, "type": "object", "properties": {"image": {"type": "string"}}}
+            }
+        });
+
+        assert!(errors(
+            schema.clone(),
+            json!({"port": 8080.0, "sidecar": {"image": 1}})
+        )
+        .is_empty());
+        assert_eq!(
+            errors(schema, json!({"port": "8080"})),
+            vec![pair("/port", "type")]
+        );
+    }
+}
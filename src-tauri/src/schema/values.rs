@@ -2,21 +2,33 @@
 //!
 //! Generate JSON schema from deployed Helm release values
 
-use tauri_plugin_shell::ShellExt;
+use crate::runner::command::CommandRunner;
+use regex::Regex;
+use serde_json::{json, Map, Value};
+use std::sync::OnceLock;
 
 /// Generate a JSON schema from the current Helm release values
 pub async fn generate_schema_from_helm_values(
-    app: &tauri::AppHandle,
+    runner: &dyn CommandRunner,
     release_name: &str,
     namespace: &str,
-) -> Result<serde_json::Value, String> {
+) -> Result<Value, String> {
     println!("Attempting to generate schema from helm values for {}/{}", namespace, release_name);
-    let shell = app.shell();
-    
+    let values = get_deployed_values(runner, release_name, namespace).await?;
+
+    // Generate schema from the values
+    Ok(infer_schema_from_values(&values))
+}
+
+/// Get the values set on a deployed Helm release
+pub async fn get_deployed_values(
+    runner: &dyn CommandRunner,
+    release_name: &str,
+    namespace: &str,
+) -> Result<Value, String> {
     // Get current values from the deployed release
-    let output = shell
-        .command("helm")
-        .args([
+    let output = runner
+        .run("helm", [
             "get",
             "values",
             release_name,
@@ -25,7 +37,6 @@ pub async fn generate_schema_from_helm_values(
             "-o",
             "json",
         ])
-        .output()
         .await;
         
     let output = match output {
@@ -36,89 +47,571 @@ pub async fn generate_schema_from_helm_values(
         }
     };
     
-    if !output.status.success() {
+    if !output.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         println!("Helm get values command failed with output: {}", stderr);
         return Err(format!("Failed to get helm values: {}", stderr));
     }
 
     let values_json = String::from_utf8_lossy(&output.stdout);
-    let values: serde_json::Value = serde_json::from_str(&values_json)
-        .map_err(|e| format!("Failed to parse helm values: {}", e))?;
+    serde_json::from_str(&values_json)
+        .map_err(|e| format!("Failed to parse helm values: {}", e))
+}
 
-    // Generate schema from the values
-    let schema = generate_schema_from_value(&values);
-    
-    Ok(serde_json::json!({
+/// Infer a JSON schema from a values document such as a chart's `values.yaml`
+pub fn infer_schema_from_values(values: &Value) -> Value {
+    json!({
         "type": "object",
-        "properties": schema,
-    }))
+        "properties": generate_schema_from_value(values),
+    })
+}
+
+/// Overlay deployed values on a schema as its current values
+///
+/// Every deployed leaf or array value becomes the `default` of its property,
+/// so that forms are prefilled with what is deployed; objects are descended
+/// into, and only a `default` the chart declares on them is updated. Values
+/// without a property in the schema get an inferred one.
+pub fn overlay_current_values(schema: &mut Value, values: &Value) {
+    let Some(schema) = schema.as_object_mut() else {
+        return;
+    };
+
+    // Values of a different type than the schema declares are only set as the default
+    let is_object_schema = schema.get("type").is_none_or(|t| t == "object");
+    let (true, Value::Object(map)) = (is_object_schema, values) else {
+        schema.insert("default".to_string(), values.clone());
+        return;
+    };
+
+    let properties = schema
+        .entry("properties")
+        .or_insert_with(|| json!({}));
+    if let Some(properties) = properties.as_object_mut() {
+        for (key, val) in map {
+            match properties.get_mut(key) {
+                Some(property) => overlay_current_values(property, val),
+                None => {
+                    properties.insert(key.clone(), generate_property_schema(key, val));
+                }
+            }
+        }
+    }
+    if let Some(default) = schema.get_mut("default") {
+        merge_values(default, values);
+    }
+}
+
+/// Merge values the way helm coalesces them: objects key by key, anything else replaced
+fn merge_values(base: &mut Value, overlay: &Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, val) in overlay {
+                match base.get_mut(key) {
+                    Some(existing) => merge_values(existing, val),
+                    None => {
+                        base.insert(key.clone(), val.clone());
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay.clone(),
+    }
 }
 
 /// Recursively generate schema properties from a JSON value
-fn generate_schema_from_value(value: &serde_json::Value) -> serde_json::Value {
+fn generate_schema_from_value(value: &Value) -> Value {
     match value {
-        serde_json::Value::Object(map) => {
-            let mut properties = serde_json::Map::new();
-            
-            for (key, val) in map {
-                let prop_schema = match val {
-                    serde_json::Value::String(_) => serde_json::json!({
-                        "type": "string",
-                        "default": val
-                    }),
-                    serde_json::Value::Number(_) => {
-                        if val.as_i64().is_some() {
-                            serde_json::json!({
-                                "type": "integer",
-                                "default": val
-                            })
-                        } else {
-                            serde_json::json!({
-                                "type": "number",
-                                "default": val
-                            })
-                        }
-                    },
-                    serde_json::Value::Bool(_) => serde_json::json!({
-                        "type": "boolean",
-                        "default": val
-                    }),
-                    serde_json::Value::Array(arr) => {
-                        if arr.is_empty() {
-                            serde_json::json!({
-                                "type": "array",
-                                "items": { "type": "string" },
-                                "default": val
-                            })
-                        } else {
-                            let item_schema = generate_schema_from_value(&arr[0]);
-                            serde_json::json!({
-                                "type": "array",
-                                "items": item_schema,
-                                "default": val
-                            })
-                        }
-                    },
-                    serde_json::Value::Object(_) => {
-                        let nested_schema = generate_schema_from_value(val);
-                        serde_json::json!({
-                            "type": "object",
-                            "properties": nested_schema,
-                            "default": val
-                        })
-                    },
-                    serde_json::Value::Null => serde_json::json!({
-                        "type": "string",
-                        "default": null
-                    }),
+        Value::Object(map) => {
+            let mut properties = object_properties(map, true);
+            properties.values_mut().for_each(widen_null_types);
+            Value::Object(properties)
+        }
+        _ => json!({}),
+    }
+}
+
+/// Generate the schema of a single property from its value
+fn generate_property_schema(key: &str, val: &Value) -> Value {
+    let mut schema = infer_schema(Some(key), val, true);
+    widen_null_types(&mut schema);
+    schema
+}
+
+/// Schemas of the properties of an object value
+fn object_properties(map: &Map<String, Value>, with_defaults: bool) -> Map<String, Value> {
+    let is_image =
+        map.contains_key("repository") && (map.contains_key("tag") || map.contains_key("digest"));
+
+    let mut properties = Map::new();
+    for (key, val) in map {
+        let mut schema = infer_schema(Some(key), val, with_defaults);
+        if is_image {
+            add_image_hints(key, &mut schema);
+        }
+        properties.insert(key.clone(), schema);
+    }
+    properties
+}
+
+/// Infer the schema of a value set under `key`
+///
+/// Only scalars and arrays get a `default`: the defaults of an object are
+/// those of its properties.
+fn infer_schema(key: Option<&str>, val: &Value, with_default: bool) -> Value {
+    let mut schema = match val {
+        Value::Null => json!({ "type": "null" }),
+        Value::Bool(_) => json!({ "type": "boolean" }),
+        Value::Number(n) => match n.as_i64() {
+            Some(n) if key.is_some_and(is_port_key) && (1..=65535).contains(&n) => json!({
+                "type": "integer",
+                "format": "port",
+                "minimum": 1,
+                "maximum": 65535
+            }),
+            Some(_) => json!({ "type": "integer" }),
+            None if n.is_u64() => json!({ "type": "integer" }),
+            None => json!({ "type": "number" }),
+        },
+        Value::String(s) => string_schema(key, s),
+        Value::Array(items) => json!({
+            "type": "array",
+            "items": items_schema(key, items)
+        }),
+        Value::Object(map) => {
+            return json!({
+                "type": "object",
+                "properties": object_properties(map, with_default)
+            })
+        }
+    };
+    if with_default {
+        schema["default"] = val.clone();
+    }
+    schema
+}
+
+/// Schema of the items of an array set under `key`, merged across all of them
+fn items_schema(key: Option<&str>, items: &[Value]) -> Value {
+    items
+        .iter()
+        .map(|item| infer_schema(key, item, false))
+        .reduce(merge_schemas)
+        .unwrap_or_else(|| json!({ "type": "string" }))
+}
+
+fn string_schema(key: Option<&str>, s: &str) -> Value {
+    let mut schema = json!({ "type": "string" });
+    if s == "true" || s == "false" {
+        schema["pattern"] = json!(BOOLEAN_STRING_PATTERN);
+        return schema;
+    }
+
+    let patterns = patterns();
+    // Quantities need a unit here, as plain numbers are not worth a hint
+    let is_quantity =
+        patterns.quantity.is_match(s) && s.ends_with(|c: char| c.is_ascii_alphabetic());
+    let is_duration = patterns.duration.is_match(s);
+    let key = key.unwrap_or_default().to_lowercase();
+    let key_has = |fragments: &[&str]| fragments.iter().any(|f| key.contains(f));
+    // `5m` is both 5 millicores and 5 minutes, the key tells which
+    let hint = match (is_quantity, is_duration) {
+        (true, true) if key_has(QUANTITY_KEYS) => Some(("quantity", QUANTITY_PATTERN)),
+        (true, true) if key_has(DURATION_KEYS) => Some(("go-duration", DURATION_PATTERN)),
+        (true, false) => Some(("quantity", QUANTITY_PATTERN)),
+        (false, true) => Some(("go-duration", DURATION_PATTERN)),
+        _ => None,
+    };
+    if let Some((format, pattern)) = hint {
+        schema["format"] = json!(format);
+        schema["pattern"] = json!(pattern);
+    }
+    schema
+}
+
+/// Hints for the fields of an image object with a repository and a tag or digest
+fn add_image_hints(key: &str, schema: &mut Value) {
+    let patterns = patterns();
+    let (format, pattern, regex) = match key {
+        "repository" => (
+            "image-repository",
+            IMAGE_REPOSITORY_PATTERN,
+            &patterns.image_repository,
+        ),
+        "tag" => ("image-tag", IMAGE_TAG_PATTERN, &patterns.image_tag),
+        "digest" => ("image-digest", IMAGE_DIGEST_PATTERN, &patterns.image_digest),
+        _ => return,
+    };
+    let Some(object) = schema.as_object_mut() else {
+        return;
+    };
+    // YAML reads tags such as `1.25` as numbers, helm renders them as strings
+    let types = schema_types(object);
+    let is_text = types.iter().all(|t| match t.as_str() {
+        "string" | "null" => true,
+        "integer" | "number" => key == "tag",
+        _ => false,
+    });
+    if !is_text {
+        return;
+    }
+    if let Some(default) = object.get_mut("default") {
+        if let Value::Number(n) = default {
+            *default = json!(n.to_string());
+        }
+        if default.as_str().is_some_and(|d| !regex.is_match(d)) {
+            return;
+        }
+    }
+
+    let nullable = types.iter().any(|t| t == "null");
+    object.insert(
+        "type".to_string(),
+        if nullable {
+            json!(["string", "null"])
+        } else {
+            json!("string")
+        },
+    );
+    object.insert("format".to_string(), json!(format));
+    object.insert("pattern".to_string(), json!(pattern));
+}
+
+/// Merge the schemas of two values that may appear in the same place
+///
+/// The result accepts both: types are unioned, properties and items are
+/// merged, and format hints are only kept if both values agree on them.
+fn merge_schemas(a: Value, b: Value) -> Value {
+    let (Value::Object(mut a), Value::Object(mut b)) = (a, b) else {
+        return json!({});
+    };
+    let a_types = schema_types(&a);
+    let b_types = schema_types(&b);
+
+    let mut types: Vec<String> = Vec::new();
+    for t in a_types.iter().chain(&b_types) {
+        if !types.contains(t) {
+            types.push(t.clone());
+        }
+    }
+    if types.iter().any(|t| t == "number") {
+        types.retain(|t| t != "integer");
+    }
+    // `null` goes last, so the first type is the one to edit the value as
+    types.sort_by_key(|t| t == "null");
+
+    let mut merged = Map::new();
+    merged.insert(
+        "type".to_string(),
+        match types.as_slice() {
+            [single] => json!(single),
+            _ => json!(types),
+        },
+    );
+
+    match (a.remove("properties"), b.remove("properties")) {
+        (Some(Value::Object(mut properties)), Some(Value::Object(other))) => {
+            for (key, schema) in other {
+                let schema = match properties.remove(&key) {
+                    Some(existing) => merge_schemas(existing, schema),
+                    None => schema,
                 };
-                
-                properties.insert(key.clone(), prop_schema);
+                properties.insert(key, schema);
             }
-            
-            serde_json::Value::Object(properties)
+            merged.insert("properties".to_string(), Value::Object(properties));
+        }
+        (Some(properties), None) | (None, Some(properties)) => {
+            merged.insert("properties".to_string(), properties);
         }
-        _ => serde_json::json!({})
+        _ => {}
+    }
+
+    match (a.remove("items"), b.remove("items")) {
+        (Some(items), Some(other)) => {
+            merged.insert("items".to_string(), merge_schemas(items, other));
+        }
+        (Some(items), None) | (None, Some(items)) => {
+            merged.insert("items".to_string(), items);
+        }
+        _ => {}
+    }
+
+    let shares_type = a_types.iter().any(|t| t != "null" && b_types.contains(t));
+    for keyword in HINT_KEYWORDS {
+        let hint = match (a.remove(*keyword), b.remove(*keyword)) {
+            (Some(hint), Some(other)) if hint == other => Some(hint),
+            // A hint on a type the other value does not have still holds
+            (Some(hint), None) | (None, Some(hint)) if !shares_type => Some(hint),
+            _ => None,
+        };
+        if let Some(hint) = hint {
+            merged.insert(keyword.to_string(), hint);
+        }
+    }
+    Value::Object(merged)
+}
+
+fn schema_types(schema: &Map<String, Value>) -> Vec<String> {
+    match schema.get("type") {
+        Some(Value::String(t)) => vec![t.clone()],
+        Some(Value::Array(types)) => types
+            .iter()
+            .filter_map(|t| t.as_str().map(String::from))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// A value that was only ever seen as `null` is most likely a string that
+/// was left unset
+fn widen_null_types(schema: &mut Value) {
+    if schema["type"] == "null" {
+        schema["type"] = json!(["string", "null"]);
+    }
+    if let Some(items) = schema.get_mut("items") {
+        widen_null_types(items);
+    }
+    if let Some(Value::Object(properties)) = schema.get_mut("properties") {
+        properties.values_mut().for_each(widen_null_types);
+    }
+}
+
+/// Whether a key names a port, e.g. `port`, `containerPort` or `http_port`
+fn is_port_key(key: &str) -> bool {
+    key.eq_ignore_ascii_case("port")
+        || key.ends_with("Port")
+        || key.to_lowercase().ends_with("_port")
+        || key.to_lowercase().ends_with("-port")
+}
+
+/// Keywords that only hint at the kind of value
+pub(crate) const HINT_KEYWORDS: &[&str] = &["format", "pattern", "minimum", "maximum"];
+
+/// Kubernetes resource quantities, e.g. `500m`, `1Gi` or `2`
+const QUANTITY_PATTERN: &str = r"^[+-]?(\d+(\.\d*)?|\.\d+)([KMGTPE]i|[numkMGTPE]|[eE][+-]?\d+)?$";
+/// Go durations, e.g. `30s`, `1h30m` or `500ms`
+const DURATION_PATTERN: &str = r"^[+-]?((\d+(\.\d*)?|\.\d+)(ns|us|µs|ms|s|m|h))+$";
+const BOOLEAN_STRING_PATTERN: &str = "^(true|false)$";
+/// Image repositories, optionally with a registry host, e.g. `bitnami/nginx`
+const IMAGE_REPOSITORY_PATTERN: &str =
+    r"^([a-zA-Z0-9.-]+(:\d+)?/)?[a-z0-9]+([._-]+[a-z0-9]+)*(/[a-z0-9]+([._-]+[a-z0-9]+)*)*$";
+/// Image tags, empty when the chart defaults to its app version
+const IMAGE_TAG_PATTERN: &str = r"^([\w][\w.-]{0,127})?$";
+const IMAGE_DIGEST_PATTERN: &str =
+    r"^([A-Za-z][A-Za-z0-9]*([-_+.][A-Za-z][A-Za-z0-9]*)*:[0-9a-fA-F]{32,})?$";
+
+/// Key fragments of values that are resource quantities
+const QUANTITY_KEYS: &[&str] = &[
+    "cpu", "memory", "storage", "size", "limit", "request", "capacity", "quota",
+];
+/// Key fragments of values that are durations
+const DURATION_KEYS: &[&str] = &[
+    "timeout",
+    "interval",
+    "period",
+    "duration",
+    "ttl",
+    "delay",
+    "deadline",
+    "wait",
+    "retention",
+    "expir",
+    "backoff",
+];
+
+struct Patterns {
+    quantity: Regex,
+    duration: Regex,
+    image_repository: Regex,
+    image_tag: Regex,
+    image_digest: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        quantity: Regex::new(QUANTITY_PATTERN).expect("valid quantity pattern"),
+        duration: Regex::new(DURATION_PATTERN).expect("valid duration pattern"),
+        image_repository: Regex::new(IMAGE_REPOSITORY_PATTERN).expect("valid repository pattern"),
+        image_tag: Regex::new(IMAGE_TAG_PATTERN).expect("valid tag pattern"),
+        image_digest: Regex::new(IMAGE_DIGEST_PATTERN).expect("valid digest pattern"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Trimmed from the values of a Bitnami-style web chart
+    const WEB_VALUES: &str = r#"
+image:
+  registry: docker.io
+  repository: bitnami/nginx
+  tag: 1.25
+  digest: ""
+  pullPolicy: IfNotPresent
+  pullSecrets: []
+replicaCount: 2
+service:
+  type: LoadBalancer
+  port: 80
+  targetPort: http
+  loadBalancerIP: ~
+resources:
+  limits:
+    cpu: 500m
+    memory: 512Mi
+  requests:
+    cpu: 250m
+    memory: 256Mi
+metrics:
+  serviceMonitor:
+    interval: 30s
+    scrapeTimeout: 5m
+podAnnotations:
+  sidecar.istio.io/inject: "false"
+ingress:
+  hosts:
+    - host: web.example.com
+      paths: [/]
+    - host: api.example.com
+      tls: true
+extraEnvVars:
+  - name: LOG_LEVEL
+    value: debug
+  - name: WORKERS
+    value: 4
+  - name: PROXY
+    value: null
+tolerations: []
+"#;
+
+    fn web_schema() -> Value {
+        let values: Value = serde_yaml::from_str(WEB_VALUES).unwrap();
+        infer_schema_from_values(&values)
+    }
+
+    #[test]
+    fn hints_at_images_quantities_durations_and_ports() {
+        let schema = web_schema();
+        let properties = &schema["properties"];
+
+        let image = &properties["image"]["properties"];
+        assert_eq!(image["repository"]["format"], "image-repository");
+        assert_eq!(image["tag"]["type"], "string");
+        assert_eq!(image["tag"]["default"], "1.25");
+        assert_eq!(image["digest"]["format"], "image-digest");
+        assert!(image["pullPolicy"].get("format").is_none());
+
+        let limits = &properties["resources"]["properties"]["limits"]["properties"];
+        assert_eq!(limits["cpu"]["format"], "quantity");
+        assert_eq!(limits["memory"]["pattern"], QUANTITY_PATTERN);
+
+        let monitor = &properties["metrics"]["properties"]["serviceMonitor"]["properties"];
+        assert_eq!(monitor["interval"]["format"], "go-duration");
+        // `5m` could be a quantity too, the key says it is a duration
+        assert_eq!(monitor["scrapeTimeout"]["format"], "go-duration");
+
+        let service = &properties["service"]["properties"];
+        assert_eq!(service["port"]["format"], "port");
+        assert_eq!(service["port"]["maximum"], 65535);
+        assert!(service["targetPort"].get("format").is_none());
+        assert_eq!(
+            properties["podAnnotations"]["properties"]["sidecar.istio.io/inject"]["pattern"],
+            BOOLEAN_STRING_PATTERN
+        );
+    }
+
+    #[test]
+    fn types_nulls_as_nullable_and_keeps_defaults_on_leaves() {
+        let schema = web_schema();
+        let properties = &schema["properties"];
+
+        let load_balancer_ip = &properties["service"]["properties"]["loadBalancerIP"];
+        assert_eq!(load_balancer_ip["type"], json!(["string", "null"]));
+        assert_eq!(load_balancer_ip["default"], Value::Null);
+
+        assert_eq!(properties["replicaCount"]["default"], 2);
+        assert_eq!(
+            properties["image"]["properties"]["pullSecrets"]["default"],
+            json!([])
+        );
+        assert!(properties["resources"].get("default").is_none());
+        assert!(properties["image"].get("default").is_none());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn overlays_deployed_values_as_leaf_defaults() {
+        let mut schema = web_schema();
+        let deployed = json!({
+            "replicaCount": 3,
+            "image": {"tag": "1.26", "pullSecrets": ["registry"]},
+            "extra": {"enabled": true}
+        });
+
+        overlay_current_values(&mut schema, &deployed);
+
+        let properties = &schema["properties"];
+        assert_eq!(properties["replicaCount"]["default"], 3);
+        assert_eq!(properties["image"]["properties"]["tag"]["default"], "1.26");
+        assert_eq!(
+            properties["image"]["properties"]["pullSecrets"]["default"],
+            json!(["registry"])
+        );
+        assert_eq!(properties["extra"]["properties"]["enabled"]["default"], true);
+        assert!(schema.get("default").is_none());
+        assert!(properties["image"].get("default").is_none());
+        assert!(properties["extra"].get("default").is_none());
+    }
+
+    #[test]
+    fn merges_item_schemas_across_array_elements() {
+        let schema = web_schema();
+        let properties = &schema["properties"];
+
+        let host = &properties["ingress"]["properties"]["hosts"]["items"];
+        assert_eq!(host["type"], "object");
+        assert_eq!(host["properties"]["host"]["type"], "string");
+        assert_eq!(host["properties"]["paths"]["items"]["type"], "string");
+        assert_eq!(host["properties"]["tls"]["type"], "boolean");
+        assert!(host["properties"]["tls"].get("default").is_none());
+
+        let env = &properties["extraEnvVars"]["items"]["properties"];
+        assert_eq!(env["value"]["type"], json!(["string", "integer", "null"]));
+
+        assert_eq!(properties["tolerations"]["items"]["type"], "string");
+    }
+
+    #[test]
+    fn keeps_only_the_hints_all_elements_agree_on() {
+        // As returned by `helm get values -o json`
+        let values = json!({
+            "sidecars": [
+                { "name": "proxy", "resources": { "limits": { "cpu": "100m" } } },
+                { "name": "logger", "resources": { "limits": { "cpu": "1" } }, "image": "fluent-bit" }
+            ],
+            "weights": [1, 2.5, null],
+            "timeouts": ["30s", "1m"],
+            "ports": [null, null]
+        });
+
+        let schema = infer_schema_from_values(&values);
+        let properties = &schema["properties"];
+
+        let sidecar = &properties["sidecars"]["items"]["properties"];
+        assert_eq!(sidecar["image"]["type"], "string");
+        let cpu = &sidecar["resources"]["properties"]["limits"]["properties"]["cpu"];
+        assert_eq!(cpu["type"], "string");
+        assert!(cpu.get("format").is_none());
+
+        assert_eq!(
+            properties["weights"]["items"]["type"],
+            json!(["number", "null"])
+        );
+        assert_eq!(properties["timeouts"]["items"]["format"], "go-duration");
+        assert_eq!(
+            properties["ports"]["items"]["type"],
+            json!(["string", "null"])
+        );
+    }
+}
@@ -0,0 +1,538 @@
+//! Values documentation module
+//!
+//! Reads the comments that document a chart's `values.yaml`, which a YAML
+//! parser discards, and attaches them to the schema inferred from it. Two
+//! conventions are understood:
+//!
+//! - helm-docs: a `# -- description` comment directly above a key, continued
+//!   on the following comment lines, with an optional `(type)` prefix and
+//!   `# @section -- Name` grouping. The older `# path.to.key -- description`
+//!   form is read as well.
+//! - Bitnami readme-generator: `## @param path.to.key [modifiers] description`
+//!   lines, grouped by the preceding `## @section Name`.
+
+use super::values::HINT_KEYWORDS;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// Documentation of a single value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValueDoc {
+    pub description: Option<String>,
+    /// JSON schema type declared in the comment.
+    pub value_type: Option<String>,
+    /// Whether the value may also be `null`.
+    pub nullable: bool,
+    pub section: Option<String>,
+}
+
+/// Documentation of a `values.yaml`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValuesDocs {
+    /// Documented values by dotted path, e.g. `image.tag`.
+    pub values: BTreeMap<String, ValueDoc>,
+    /// Section names in the order they appear.
+    pub sections: Vec<String>,
+}
+
+/// A helm-docs comment waiting for the key below it.
+#[derive(Default)]
+struct PendingDoc {
+    doc: ValueDoc,
+    /// Whether following plain comment lines continue the description.
+    continues: bool,
+    ignored: bool,
+}
+
+/// Parse the documentation comments of a `values.yaml`.
+pub fn parse_values_docs(yaml: &str) -> ValuesDocs {
+    let mut docs = ValuesDocs::default();
+    // Keys enclosing the current line with their indentation; `None` marks a sequence item
+    let mut stack: Vec<(usize, Option<String>)> = Vec::new();
+    let mut pending: Option<PendingDoc> = None;
+    let mut section: Option<String> = None;
+    // Indentation of the key whose block scalar (`|` or `>`) is being skipped
+    let mut block_scalar: Option<usize> = None;
+
+    for line in yaml.lines() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+
+        if let Some(key_indent) = block_scalar {
+            if trimmed.is_empty() || indent > key_indent {
+                continue;
+            }
+            block_scalar = None;
+        }
+        if trimmed.is_empty() {
+            pending = None;
+            continue;
+        }
+        if trimmed.starts_with("---") || trimmed.starts_with("...") {
+            stack.clear();
+            pending = None;
+            continue;
+        }
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            parse_comment(comment, &mut docs, &mut pending, &mut section);
+            continue;
+        }
+
+        let mut indent = indent;
+        let mut content = trimmed;
+        if let Some(item) = content
+            .strip_prefix('-')
+            .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+        {
+            // Sequences may be indented at the level of their parent key
+            while stack
+                .last()
+                .is_some_and(|(i, key)| *i > indent || (*i == indent && key.is_none()))
+            {
+                stack.pop();
+            }
+            stack.push((indent, None));
+            let item_content = item.trim_start();
+            indent += content.len() - item_content.len();
+            content = item_content;
+        } else {
+            while stack.last().is_some_and(|(i, _)| *i >= indent) {
+                stack.pop();
+            }
+        }
+
+        let Some((key, value)) = split_key(content) else {
+            pending = None;
+            continue;
+        };
+        let in_sequence = stack.iter().any(|(_, key)| key.is_none());
+        let path = stack
+            .iter()
+            .filter_map(|(_, key)| key.as_deref())
+            .chain([key.as_str()])
+            .collect::<Vec<_>>()
+            .join(".");
+
+        if let Some(doc) = pending.take() {
+            if !doc.ignored && !in_sequence {
+                docs.values.insert(path, doc.doc);
+            }
+        }
+        if value.starts_with('|') || value.starts_with('>') {
+            block_scalar = Some(indent);
+        }
+        stack.push((indent, Some(key)));
+    }
+    docs
+}
+
+/// Handle the text of a comment line after its first `#`.
+fn parse_comment(
+    comment: &str,
+    docs: &mut ValuesDocs,
+    pending: &mut Option<PendingDoc>,
+    section: &mut Option<String>,
+) {
+    let body = comment.trim_start_matches('#').trim();
+
+    if let Some(rest) = body.strip_prefix("@section") {
+        let rest = rest.trim();
+        let (name, for_next_key) = match rest.strip_prefix("--") {
+            // helm-docs: the section of the key below
+            Some(name) => (name.trim(), true),
+            // readme-generator: the section of the parameters that follow
+            None => (rest, false),
+        };
+        if name.is_empty() {
+            return;
+        }
+        if !docs.sections.iter().any(|s| s == name) {
+            docs.sections.push(name.to_string());
+        }
+        if for_next_key {
+            let pending = pending.get_or_insert_with(PendingDoc::default);
+            pending.doc.section = Some(name.to_string());
+            pending.continues = false;
+        } else {
+            *section = Some(name.to_string());
+        }
+        return;
+    }
+    if let Some(param) = body
+        .strip_prefix("@param ")
+        .or_else(|| body.strip_prefix("@extra "))
+    {
+        if let Some((path, doc)) = parse_param(param) {
+            docs.values.insert(
+                path,
+                ValueDoc {
+                    section: section.clone(),
+                    ..doc
+                },
+            );
+        }
+        return;
+    }
+    if body.starts_with("@ignored") {
+        pending.get_or_insert_with(PendingDoc::default).ignored = true;
+        return;
+    }
+    if body.starts_with('@') {
+        // @default, @raw, @skip and other annotations carry no description
+        if let Some(pending) = pending.as_mut() {
+            pending.continues = false;
+        }
+        return;
+    }
+
+    if let Some(description) = body
+        .strip_prefix("--")
+        .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+    {
+        let (value_type, description) = split_type(description.trim());
+        let pending = pending.get_or_insert_with(PendingDoc::default);
+        pending.doc.description = Some(description.to_string()).filter(|d| !d.is_empty());
+        pending.doc.value_type = value_type;
+        pending.continues = true;
+        return;
+    }
+    // Older helm-docs comments name the key they document
+    if let Some((path, description)) = body.split_once(" -- ") {
+        if !path.is_empty() && !path.contains(char::is_whitespace) {
+            let (value_type, description) = split_type(description.trim());
+            docs.values.insert(
+                path.to_string(),
+                ValueDoc {
+                    description: Some(description.to_string()),
+                    value_type,
+                    ..ValueDoc::default()
+                },
+            );
+            return;
+        }
+    }
+
+    if let Some(pending) = pending.as_mut().filter(|p| p.continues) {
+        if !body.is_empty() {
+            let description = pending.doc.description.get_or_insert_with(String::new);
+            if !description.is_empty() {
+                description.push(' ');
+            }
+            description.push_str(body);
+        }
+    }
+}
+
+/// Parse `path [modifiers] description` of a readme-generator `@param`.
+fn parse_param(param: &str) -> Option<(String, ValueDoc)> {
+    let param = param.trim();
+    let (path, rest) = param.split_once(char::is_whitespace).unwrap_or((param, ""));
+    if path.is_empty() {
+        return None;
+    }
+
+    let mut doc = ValueDoc::default();
+    let mut rest = rest.trim();
+    if let Some((modifiers, description)) = rest
+        .strip_prefix('[')
+        .and_then(|modifiers| modifiers.split_once(']'))
+    {
+        for modifier in modifiers.split(',').map(str::trim) {
+            match modifier {
+                "nullable" => doc.nullable = true,
+                _ => {
+                    if let Some(value_type) = json_type(modifier) {
+                        doc.value_type = Some(value_type.to_string());
+                    }
+                }
+            }
+        }
+        rest = description.trim();
+    }
+    doc.description = Some(rest.to_string()).filter(|d| !d.is_empty());
+    Some((path.to_string(), doc))
+}
+
+/// Split a helm-docs `(type)` prefix off a description.
+fn split_type(description: &str) -> (Option<String>, &str) {
+    description
+        .strip_prefix('(')
+        .and_then(|rest| rest.split_once(')'))
+        .and_then(|(value_type, rest)| Some((json_type(value_type.trim())?, rest.trim())))
+        .map_or((None, description), |(value_type, rest)| {
+            (Some(value_type.to_string()), rest)
+        })
+}
+
+/// The JSON schema type of a type name used by helm-docs or readme-generator.
+fn json_type(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "string" | "tpl" => "string",
+        "int" | "integer" => "integer",
+        "float" | "number" => "number",
+        "bool" | "boolean" => "boolean",
+        "list" | "array" => "array",
+        "object" | "dict" | "map" | "yaml" => "object",
+        _ => return None,
+    })
+}
+
+/// Split `key: value` into the unquoted key and the value.
+fn split_key(content: &str) -> Option<(String, &str)> {
+    let (key, rest) = match content.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let end = content[1..].find(quote)? + 1;
+            (content[1..end].to_string(), &content[end + 1..])
+        }
+        _ => {
+            let end = content
+                .find(": ")
+                .or_else(|| content.strip_suffix(':').map(str::len))?;
+            let key = &content[..end];
+            if key.is_empty() || key.contains(['#', '{', '[', '"', '\'']) {
+                return None;
+            }
+            (key.trim_end().to_string(), &content[end..])
+        }
+    };
+    let value = rest.strip_prefix(':')?;
+    if !value.is_empty() && !value.starts_with(' ') {
+        return None;
+    }
+    Some((key, value.trim()))
+}
+
+/// Attach descriptions, declared types and sections to the properties of a
+/// schema inferred from the documented values.
+pub fn apply_values_docs(schema: &mut Value, docs: &ValuesDocs) {
+    for (path, doc) in &docs.values {
+        let Some(property) = find_property(schema, path).and_then(Value::as_object_mut) else {
+            continue;
+        };
+        if let Some(description) = &doc.description {
+            property.insert("description".to_string(), json!(description));
+        }
+        let inferred_types: Vec<String> = match property.get("type") {
+            Some(Value::Array(types)) => types
+                .iter()
+                .filter_map(Value::as_str)
+                .map(String::from)
+                .collect(),
+            Some(Value::String(value_type)) => vec![value_type.clone()],
+            _ => Vec::new(),
+        };
+        // A null default stays valid whatever type is declared for it
+        let nullable = doc.nullable
+            || inferred_types.iter().any(|t| t == "null")
+            || property.get("default").is_some_and(Value::is_null);
+        let value_type = doc
+            .value_type
+            .clone()
+            .or_else(|| inferred_types.iter().find(|t| *t != "null").cloned());
+        if let Some(value_type) = value_type {
+            // Hints inferred from the value do not hold for another declared type
+            if inferred_types.iter().any(|t| t != "null" && *t != value_type) {
+                for keyword in HINT_KEYWORDS {
+                    property.remove(*keyword);
+                }
+            }
+            let value_type = if nullable && value_type != "null" {
+                json!([value_type, "null"])
+            } else {
+                json!(value_type)
+            };
+            property.insert("type".to_string(), value_type);
+        }
+        if let Some(section) = &doc.section {
+            property.insert("x-section".to_string(), json!(section));
+        }
+    }
+    if let (false, Some(schema)) = (docs.sections.is_empty(), schema.as_object_mut()) {
+        schema.insert("x-sections".to_string(), json!(docs.sections));
+    }
+}
+
+/// The property at a dotted path of an object schema.
+fn find_property<'a>(schema: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+    path.split('.')
+        .try_fold(schema, |node, key| node.get_mut("properties")?.get_mut(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::values::infer_schema_from_values;
+
+    const HELM_DOCS_VALUES: &str = r#"
+# -- Number of replicas
+replicaCount: 1
+
+image:
+  # -- Image repository
+  repository: nginx
+  # -- (string) Overrides the image tag. Defaults to the
+  # chart appVersion.
+  # @default -- `.Chart.AppVersion`
+  tag:
+
+# @section -- Networking
+# -- Annotations for the service
+podAnnotations: {}
+
+config: |
+  # -- not a comment
+  key: value
+
+tolerations:
+  # -- not documented, inside a list
+  - key: dedicated
+    operator: Equal
+
+# @ignored
+# -- Internal setting
+internal: true
+
+# resources.limits -- Resource limits of the pod
+resources:
+  limits: {}
+"#;
+
+    const BITNAMI_VALUES: &str = r#"
+## @section Global parameters
+## Global Docker image parameters
+##
+
+## @param global.imageRegistry Global Docker image registry
+## @param global.storageClass [string, nullable] Global StorageClass for Persistent Volume(s)
+##
+global:
+  imageRegistry: ""
+  storageClass:
+
+## @section Common parameters
+
+## @param replicaCount Number of replicas to deploy
+## @param extraEnvVars [array] Extra environment variables
+## @skip podLabels
+replicaCount: 1
+extraEnvVars:
+podLabels:
+  app: web
+"#;
+
+    #[test]
+    fn reads_helm_docs_comments() {
+        let docs = parse_values_docs(HELM_DOCS_VALUES);
+
+        assert_eq!(
+            docs.values["replicaCount"].description.as_deref(),
+            Some("Number of replicas")
+        );
+        assert_eq!(
+            docs.values["image.repository"].description.as_deref(),
+            Some("Image repository")
+        );
+        let tag = &docs.values["image.tag"];
+        assert_eq!(
+            tag.description.as_deref(),
+            Some("Overrides the image tag. Defaults to the chart appVersion.")
+        );
+        assert_eq!(tag.value_type.as_deref(), Some("string"));
+        assert_eq!(
+            docs.values["podAnnotations"].section.as_deref(),
+            Some("Networking")
+        );
+        assert_eq!(docs.sections, vec!["Networking"]);
+        assert_eq!(
+            docs.values["resources.limits"].description.as_deref(),
+            Some("Resource limits of the pod")
+        );
+
+        let paths: Vec<&str> = docs.values.keys().map(String::as_str).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "image.repository",
+                "image.tag",
+                "podAnnotations",
+                "replicaCount",
+                "resources.limits"
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_readme_generator_comments() {
+        let docs = parse_values_docs(BITNAMI_VALUES);
+
+        assert_eq!(
+            docs.sections,
+            vec!["Global parameters", "Common parameters"]
+        );
+        let storage_class = &docs.values["global.storageClass"];
+        assert_eq!(
+            storage_class.description.as_deref(),
+            Some("Global StorageClass for Persistent Volume(s)")
+        );
+        assert_eq!(storage_class.value_type.as_deref(), Some("string"));
+        assert!(storage_class.nullable);
+        assert_eq!(storage_class.section.as_deref(), Some("Global parameters"));
+        assert_eq!(
+            docs.values["replicaCount"].section.as_deref(),
+            Some("Common parameters")
+        );
+        assert!(!docs.values.contains_key("podLabels"));
+    }
+
+    #[test]
+    fn attaches_docs_to_inferred_schemas() {
+        let values: Value = serde_yaml::from_str(BITNAMI_VALUES).unwrap();
+        let mut schema = infer_schema_from_values(&values);
+
+        apply_values_docs(&mut schema, &parse_values_docs(BITNAMI_VALUES));
+
+        let properties = &schema["properties"];
+        assert_eq!(
+            properties["replicaCount"]["description"],
+            "Number of replicas to deploy"
+        );
+        assert_eq!(properties["replicaCount"]["type"], "integer");
+        assert_eq!(properties["replicaCount"]["x-section"], "Common parameters");
+        assert_eq!(properties["extraEnvVars"]["type"], json!(["array", "null"]));
+        assert_eq!(
+            properties["global"]["properties"]["storageClass"]["type"],
+            json!(["string", "null"])
+        );
+        assert_eq!(
+            schema["x-sections"],
+            json!(["Global parameters", "Common parameters"])
+        );
+    }
+
+    #[test]
+    fn declared_types_drop_inferred_hints_and_keep_null_defaults() {
+        let yaml = r#"
+image:
+  # -- (string) Overrides the image tag
+  tag:
+service:
+  # -- (string) Named or numeric port of the service
+  port: 80
+"#;
+        let values: Value = serde_yaml::from_str(yaml).unwrap();
+        let mut schema = infer_schema_from_values(&values);
+
+        apply_values_docs(&mut schema, &parse_values_docs(yaml));
+
+        let properties = &schema["properties"];
+        assert_eq!(
+            properties["image"]["properties"]["tag"]["type"],
+            json!(["string", "null"])
+        );
+        let port = &properties["service"]["properties"]["port"];
+        assert_eq!(port["type"], "string");
+        for keyword in HINT_KEYWORDS {
+            assert!(port.get(*keyword).is_none(), "{} kept on {}", keyword, port);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Generates `src/schema/k8s_definitions.json`, the Kubernetes definitions
+//! that chart schemas are resolved against.
+//!
+//! The definitions are those of the Kubernetes v1.30 OpenAPI spec, as
+//! published by the k8s-openapi crate: every core/v1 and apps/v1 resource
+//! and the types they use, including the meta/v1 ones. Run from this
+//! directory with `cargo run > ../../src/schema/k8s_definitions.json`.
+
+use k8s_openapi::api::{apps::v1 as apps, core::v1 as core};
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use serde_json::{json, Map, Value};
+
+const INT_OR_STRING: &str = "io.k8s.apimachinery.pkg.util.intstr.IntOrString";
+const QUANTITY: &str = "io.k8s.apimachinery.pkg.api.resource.Quantity";
+
+macro_rules! resources {
+    ($gen:expr, $($resource:ty),* $(,)?) => {
+        $( $gen.subschema_for::<$resource>(); )*
+    };
+}
+
+fn main() {
+    let mut gen = SchemaGenerator::new(SchemaSettings::draft07());
+    resources!(
+        gen,
+        core::Binding,
+        core::ComponentStatus,
+        core::ConfigMap,
+        core::Endpoints,
+        core::Event,
+        core::LimitRange,
+        core::Namespace,
+        core::Node,
+        core::PersistentVolume,
+        core::PersistentVolumeClaim,
+        core::Pod,
+        core::PodTemplate,
+        core::ReplicationController,
+        core::ResourceQuota,
+        core::Secret,
+        core::Service,
+        core::ServiceAccount,
+        apps::ControllerRevision,
+        apps::DaemonSet,
+        apps::Deployment,
+        apps::ReplicaSet,
+        apps::StatefulSet,
+    );
+
+    let mut definitions: Map<String, Value> = gen
+        .take_definitions()
+        .into_iter()
+        .map(|(name, schema)| {
+            let mut schema = serde_json::to_value(schema).expect("schemas serialize to JSON");
+            strip_extensions(&mut schema);
+            (name, schema)
+        })
+        .collect();
+    // int-or-string is only an extension, and quantities may be numbers too
+    for (name, types) in [
+        (INT_OR_STRING, ["string", "integer"]),
+        (QUANTITY, ["string", "number"]),
+    ] {
+        if let Some(Value::Object(definition)) = definitions.get_mut(name) {
+            definition.insert(
+                "oneOf".to_string(),
+                json!([{ "type": types[0] }, { "type": types[1] }]),
+            );
+            definition.remove("type");
+        }
+    }
+    definitions.sort_keys();
+
+    let output = json!({ "definitions": definitions });
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&output).expect("definitions serialize to JSON")
+    );
+}
+
+/// Remove the `x-kubernetes-*` extensions, which only matter to the API server.
+fn strip_extensions(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|key, _| !key.starts_with("x-kubernetes-"));
+            map.values_mut().for_each(strip_extensions);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_extensions),
+        _ => {}
+    }
+}
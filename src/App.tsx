@@ -2,6 +2,7 @@
 
 import { useState, useEffect, useRef, useMemo, useCallback } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { errorKind, formatError, RudderError } from './errors';
 import {
   Dialog,
   DialogBackdrop,
@@ -25,14 +26,17 @@ import ContextSwitcher from './ContextSwitcher';
 type SortField = 'name' | 'namespace' | 'status' | 'updated';
 type SortDirection = 'asc' | 'desc';
 
+// Mirrors HelmRelease in src-tauri/src/helm/release.rs
 interface HelmRelease {
   name: string;
   namespace: string;
-  revision: string;
-  updated: string;
+  revision: number;
+  updated: string | null;
   status: string;
   chart: string;
-  app_version: string;
+  chartName: string;
+  chartVersion: string;
+  appVersion: string;
 }
 
 const navigation = [
@@ -103,7 +107,7 @@ function App() {
           comparison = a.status.localeCompare(b.status);
           break;
         case 'updated':
-          comparison = new Date(a.updated).getTime() - new Date(b.updated).getTime();
+          comparison = new Date(a.updated ?? 0).getTime() - new Date(b.updated ?? 0).getTime();
           break;
       }
       return sortDirection === 'asc' ? comparison : -comparison;
@@ -117,7 +121,7 @@ function App() {
     failed: helmData.filter((r) => r.status === 'failed').length,
     pending: helmData.filter((r) => r.status.includes('pending')).length,
     namespaces: new Set(helmData.map((r) => r.namespace)).size,
-    uniqueCharts: new Set(helmData.map((r) => r.chart.replace(/-\d+\.\d+\.\d+$/, ''))).size,
+    uniqueCharts: new Set(helmData.map((r) => r.chartName)).size,
   }), [helmData]);
 
   // Keyboard shortcuts
@@ -148,18 +152,17 @@ function App() {
     try {
       setLoading(true);
       setError('');
-      const result = await invoke<string>('list_helm_releases');
-      const releases = JSON.parse(result) as HelmRelease[];
+      const releases = await invoke<HelmRelease[]>('list_helm_releases');
       setHelmData(releases);
     } catch (e) {
-      const errorMsg = e instanceof Error ? e.message : String(e);
-      
-      if (errorMsg.includes('Kubernetes cluster unreachable') || errorMsg.includes('127.0.0.1:6443')) {
+      const kind = errorKind(e);
+
+      if (kind === 'clusterUnreachable') {
         setShowKubeconfig(true);
-      } else if (errorMsg.includes('Helm command failed')) {
-        setError(`${errorMsg}\n\n🔧 Solution Options:\n1. Make sure Helm is installed: https://helm.sh/docs/intro/install/\n2. Run 'helm version' to verify installation\n3. For Windows: try 'choco install kubernetes-helm'`);
+      } else if (kind === 'binaryMissing') {
+        setError(`${(e as RudderError).message}\n\n🔧 Solution Options:\n1. Make sure Helm is installed: https://helm.sh/docs/intro/install/\n2. Run 'helm version' to verify installation\n3. For Windows: try 'choco install kubernetes-helm'`);
       } else {
-        setError(errorMsg);
+        setError(formatError(e));
       }
     } finally {
       setLoading(false);
@@ -172,7 +175,7 @@ function App() {
       setError('');
       getReleases();
     } catch (e) {
-      setError(`Failed to update kubeconfig: ${e}`);
+      setError(`Failed to update kubeconfig: ${formatError(e)}`);
     }
   }
 
@@ -478,7 +481,7 @@ function App() {
                           <circle r={1} cx={1} cy={1} />
                         </svg>
                         <p className="whitespace-nowrap">
-                          Updated {new Date(release.updated).toLocaleDateString()}
+                          Updated {release.updated ? new Date(release.updated).toLocaleDateString() : 'unknown'}
                         </p>
                         <svg viewBox="0 0 2 2" className="size-0.5 flex-none fill-gray-300 dark:fill-gray-500">
                           <circle r={1} cx={1} cy={1} />
@@ -561,20 +564,13 @@ function App() {
 
       {/* Release Editor Modal */}
       {selectedRelease && (() => {
-        // Parse chart name and version from the chart string (e.g., "trivy-operator-0.19.0")
-        const versionMatch = selectedRelease.chart.match(/-(\d+\.\d+\.\d+)$/);
-        const chartVersion = versionMatch ? versionMatch[1] : '';
-        const chartName = versionMatch 
-          ? selectedRelease.chart.substring(0, versionMatch.index).trim()
-          : selectedRelease.chart;
-        
         return (
           <ReleaseEditor
             releaseName={selectedRelease.name}
             namespace={selectedRelease.namespace}
             chartPath={selectedRelease.chart}
-            chartName={chartName}
-            chartVersion={chartVersion}
+            chartName={selectedRelease.chartName}
+            chartVersion={selectedRelease.chartVersion}
             // Default repo name, could be enhanced to detect from chart info
             repoName="stable"
             onClose={() => setSelectedRelease(null)}
@@ -10,6 +10,7 @@ import {
   ArrowPathIcon,
   ExclamationTriangleIcon,
 } from '@heroicons/react/24/outline';
+import { formatError } from './errors';
 
 interface ContextSwitcherProps {
   onContextChange?: (context: string) => void;
@@ -43,7 +44,7 @@ export default function ContextSwitcher({ onContextChange, className = '' }: Con
       setContexts(data.contexts);
       setCurrentContext(data.current);
     } catch (e) {
-      setError(`Failed to load contexts: ${e}`);
+      setError(`Failed to load contexts: ${formatError(e)}`);
       console.error('Failed to load contexts:', e);
     } finally {
       setLoading(false);
@@ -63,7 +64,7 @@ export default function ContextSwitcher({ onContextChange, className = '' }: Con
       // Notify parent component to refresh data
       onContextChange?.(newContext);
     } catch (e) {
-      setError(`Failed to switch context: ${e}`);
+      setError(`Failed to switch context: ${formatError(e)}`);
       console.error('Failed to switch context:', e);
     } finally {
       setSwitching(false);
@@ -19,6 +19,7 @@ import {
   CommandLineIcon,
 } from '@heroicons/react/24/outline';
 import PodExec from './PodExec';
+import { formatError } from './errors';
 
 interface ContainerStatus {
   name: string;
@@ -222,7 +223,7 @@ export default function LogViewer({ releaseName, namespace }: LogViewerProps) {
         }
       }
     } catch (e) {
-      setError(`Failed to load pods: ${e}`);
+      setError(`Failed to load pods: ${formatError(e)}`);
     } finally {
       setLoading(false);
     }
@@ -237,7 +238,7 @@ export default function LogViewer({ releaseName, namespace }: LogViewerProps) {
       });
       setPodDescription(result);
     } catch (e) {
-      setPodDescription(`Failed to load description: ${e}`);
+      setPodDescription(`Failed to load description: ${formatError(e)}`);
     } finally {
       setLoadingDescription(false);
     }
@@ -265,7 +266,7 @@ export default function LogViewer({ releaseName, namespace }: LogViewerProps) {
       });
       setLogs(result);
     } catch (e) {
-      setError(`Failed to fetch logs: ${e}`);
+      setError(`Failed to fetch logs: ${formatError(e)}`);
     } finally {
       setLoading(false);
     }
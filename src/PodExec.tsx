@@ -10,6 +10,7 @@ import {
   CheckIcon,
   ArrowPathIcon,
 } from '@heroicons/react/24/outline';
+import { formatError } from './errors';
 
 interface PodExecProps {
   podName: string;
@@ -104,7 +105,7 @@ export default function PodExec({ podName, namespace, container, onClose }: PodE
       if (!silent) {
         setHistory(prev => [...prev, {
           command: commandToRun,
-          output: `Error: ${e}`,
+          output: `Error: ${formatError(e)}`,
           exitCode: 1,
           timestamp: new Date(),
         }]);
@@ -12,6 +12,7 @@ import {
   DocumentDuplicateIcon,
   CheckIcon,
 } from '@heroicons/react/24/outline';
+import { formatError } from './errors';
 
 interface CommandHistory {
   command: string;
@@ -112,7 +113,7 @@ export default function QuickTerminal({ namespace = 'default', context }: QuickT
       const duration = Date.now() - startTime;
       setHistory(prev => [...prev, {
         command: command.replace(/\{namespace\}/g, namespace),
-        output: `Error: ${e}`,
+        output: `Error: ${formatError(e)}`,
         exitCode: 1,
         timestamp: new Date(),
         duration,
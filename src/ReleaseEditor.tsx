@@ -10,6 +10,7 @@ import LogViewer from './LogViewer';
 import Troubleshooter from './Troubleshooter';
 import DiffViewer from './DiffViewer';
 import QuickTerminal from './QuickTerminal';
+import { errorKind, formatError } from './errors';
 
 interface ReleaseEditorProps {
   releaseName: string;
@@ -31,6 +32,22 @@ interface SchemaProperty {
   enum?: string[];
 }
 
+// Mirrors UpgradePreview in src-tauri/src/helm/preview.rs
+interface UpgradePreview {
+  summary: { added: number; removed: number; changed: number };
+  resources: { kind: string; name: string; namespace: string | null; change: 'added' | 'removed' | 'changed'; diff: string }[];
+  currentManifest: string;
+  manifest: string;
+}
+
+// Mirrors RevisionComparison in src-tauri/src/helm/revision.rs
+interface RevisionComparison {
+  from: number;
+  to: number;
+  values: { path: string; change: 'added' | 'removed' | 'changed' }[];
+  resources: { kind: string; name: string; namespace: string | null; change: 'added' | 'removed' | 'changed'; diff: string }[];
+}
+
 interface HelmRevision {
   revision: number;
   updated: string;
@@ -40,6 +57,51 @@ interface HelmRevision {
   description: string;
 }
 
+// Defaults are set on leaf and array properties, so objects are collected from their properties
+function schemaDefaults(properties: Record<string, SchemaProperty>): Record<string, any> {
+  const defaults: Record<string, any> = {};
+  Object.entries(properties).forEach(([key, prop]) => {
+    if (prop.default !== undefined) {
+      defaults[key] = prop.default;
+    } else if (prop.properties) {
+      const nested = schemaDefaults(prop.properties);
+      if (Object.keys(nested).length > 0) {
+        defaults[key] = nested;
+      }
+    }
+  });
+  return defaults;
+}
+
+function isPlainObject(value: any): value is Record<string, any> {
+  return value !== null && typeof value === 'object' && !Array.isArray(value);
+}
+
+// Values that differ from the base, descending into objects; arrays and leaves are compared whole
+function changedValues(values: Record<string, any>, base: Record<string, any>): Record<string, any> {
+  const changed: Record<string, any> = {};
+  Object.entries(values).forEach(([key, value]) => {
+    if (isPlainObject(value) && isPlainObject(base[key])) {
+      const nested = changedValues(value, base[key]);
+      if (Object.keys(nested).length > 0) {
+        changed[key] = nested;
+      }
+    } else if (JSON.stringify(value) !== JSON.stringify(base[key])) {
+      changed[key] = value;
+    }
+  });
+  return changed;
+}
+
+// Merge overrides into values the way helm does: objects are merged, anything else is replaced
+function mergeValues(values: Record<string, any>, overrides: Record<string, any>): Record<string, any> {
+  const merged: Record<string, any> = { ...values };
+  Object.entries(overrides).forEach(([key, value]) => {
+    merged[key] = isPlainObject(value) && isPlainObject(merged[key]) ? mergeValues(merged[key], value) : value;
+  });
+  return merged;
+}
+
 function ReleaseEditor({ releaseName, namespace, chartPath, chartName, chartVersion, repoName = "default", onClose, onSuccess }: ReleaseEditorProps) {
   const [activeTab, setActiveTab] = useState<'values' | 'history' | 'logs' | 'troubleshoot' | 'terminal'>('values');
   const [schema, setSchema] = useState<Record<string, SchemaProperty> | null>(null);
@@ -51,6 +113,8 @@ function ReleaseEditor({ releaseName, namespace, chartPath, chartName, chartVers
   const [showRawEditor, setShowRawEditor] = useState(false);
   const [rawValues, setRawValues] = useState<string>('');
   const [currentValues, setCurrentValues] = useState<any>({});
+  // Values set on the release itself, as opposed to the chart defaults shown in the form
+  const [releaseValues, setReleaseValues] = useState<Record<string, any>>({});
   
   // History tab state
   const [historyLoading, setHistoryLoading] = useState<boolean>(false);
@@ -62,6 +126,7 @@ function ReleaseEditor({ releaseName, namespace, chartPath, chartName, chartVers
   const [showPreview, setShowPreview] = useState<boolean>(false);
   const [dryRunning, setDryRunning] = useState<boolean>(false);
   const [dryRunResult, setDryRunResult] = useState<string>('');
+  const [previewSummary, setPreviewSummary] = useState<UpgradePreview['summary'] | null>(null);
   const [currentManifest, setCurrentManifest] = useState<string>('');
   const [_currentYamlValues, setCurrentYamlValues] = useState<string>('');
 
@@ -98,43 +163,30 @@ function ReleaseEditor({ releaseName, namespace, chartPath, chartName, chartVers
       console.log('Schema keys:', Object.keys(schema));
       setSchema(schema);
       
-      // Try to get the current values from the release
+      // Get the values set on the release, which are all that is sent back
+      // on upgrade besides the edits made in the form
+      let userValues: Record<string, any> = {};
       try {
-        // In a real implementation, you would invoke a command to get current values
-        // For now, we'll use defaults if schema is available or empty object if not
-        let values: any = {};
-        
-        if (currentValues && Object.keys(currentValues).length > 0) {
-          values = currentValues;
-        } else {
-          // Extract defaults from schema if available
-          Object.entries(schema).forEach(([key, prop]: [string, any]) => {
-            if (prop.default !== undefined) {
-              values[key] = prop.default;
-            }
-          });
-        }
-        
-        console.log('Using values:', values);
-        setFormData(values);
-        setCurrentValues(values);
-        setRawValues(JSON.stringify(values, null, 2));
+        const valuesJson = await invoke<string>('get_release_values', {
+          releaseName,
+          namespace,
+          output: 'json',
+        });
+        userValues = JSON.parse(valuesJson) ?? {};
       } catch (err: any) {
         console.error('Failed to load current values:', err);
-        // Use at least values from schema
-        const defaults: any = {};
-        Object.entries(schema).forEach(([key, prop]: [string, any]) => {
-          if (prop.default !== undefined) {
-            defaults[key] = prop.default;
-          } 
-        });
-        console.log('Using default values:', defaults);
-        setFormData(defaults);
-        setCurrentValues(defaults);
-        setRawValues(JSON.stringify(defaults, null, 2));
       }
+      setReleaseValues(userValues);
+
+      // The form shows the schema defaults, which include the deployed values;
+      // without a schema the release's values are edited directly
+      const values = Object.keys(schema).length > 0 ? schemaDefaults(schema) : userValues;
+      console.log('Using values:', values);
+      setFormData(values);
+      setCurrentValues(values);
+      setRawValues(JSON.stringify(values, null, 2));
     } catch (e) {
-      setError(`Failed to load schema: ${e}`);
+      setError(`Failed to load schema: ${formatError(e)}`);
     } finally {
       setLoading(false);
     }
@@ -156,14 +208,28 @@ function ReleaseEditor({ releaseName, namespace, chartPath, chartName, chartVers
       console.log('Parsed history:', parsedHistory);
       setRevisions(parsedHistory.reverse()); // Show newest first
     } catch (e) {
-      setHistoryError(`Failed to load history: ${e}`);
+      setHistoryError(`Failed to load history: ${formatError(e)}`);
     } finally {
       setHistoryLoading(false);
     }
   }
 
   async function handleRollback(revision: number) {
-    if (!confirm(`Are you sure you want to rollback to revision ${revision}?`)) {
+    // Summarize what the rollback changes before asking for confirmation
+    let preview = '';
+    try {
+      const comparison = await invoke<RevisionComparison>('compare_revisions', {
+        releaseName,
+        namespace,
+        to: revision,
+      });
+      const resources = comparison.resources.map((r) => `  ${r.change} ${r.kind}/${r.name}`);
+      preview = `\n\n${comparison.values.length} value(s) and ${comparison.resources.length} resource(s) change:\n${resources.join('\n')}`;
+    } catch (e) {
+      preview = `\n\nCould not preview changes: ${formatError(e)}`;
+    }
+
+    if (!confirm(`Are you sure you want to rollback to revision ${revision}?${preview}`)) {
       return;
     }
     
@@ -184,24 +250,43 @@ function ReleaseEditor({ releaseName, namespace, chartPath, chartName, chartVers
         onClose();
       }, 2000);
     } catch (e) {
-      setHistoryError(`Rollback failed: ${e}`);
+      setHistoryError(`Rollback failed: ${formatError(e)}`);
     } finally {
       setRollingBack(false);
     }
   }
 
+  // Values to install: the release's own values plus the edits made in the
+  // form, so that chart defaults keep following the chart
+  function userValuesJson(): string {
+    if (schema && Object.keys(schema).length === 0 && rawValues) {
+      // Use raw values when no schema is available
+      return rawValues;
+    }
+    return JSON.stringify(mergeValues(releaseValues, changedValues(formData, currentValues)));
+  }
+
+  // Runs a command that checks the values against the chart's schema, cached under the
+  // same key as it was loaded with, and offers to go on unchecked when none is cached
+  async function invokeValidated<T>(command: string, args: Record<string, unknown>): Promise<T> {
+    const schemaKey = { chartName, chartVersion, repoName };
+    try {
+      return await invoke<T>(command, { ...args, schemaKey });
+    } catch (e) {
+      if (errorKind(e) !== 'schemaNotFound' || !confirm(`${formatError(e)}\n\nContinue without validating the values?`)) {
+        throw e;
+      }
+      return invoke<T>(command, { ...args, schemaKey, skipValidation: true });
+    }
+  }
+
   async function handlePreviewChanges() {
     try {
       setDryRunning(true);
       setError('');
       setDryRunResult('');
       
-      let valuesJson;
-      if (schema && Object.keys(schema).length === 0 && rawValues) {
-        valuesJson = rawValues;
-      } else {
-        valuesJson = JSON.stringify(formData);
-      }
+      const valuesJson = userValuesJson();
 
       // Get current values for comparison
       try {
@@ -214,29 +299,20 @@ function ReleaseEditor({ releaseName, namespace, chartPath, chartName, chartVers
         setCurrentYamlValues('# No existing values found (new release?)');
       }
 
-      // Get current manifest for comparison
-      try {
-        const manifest = await invoke<string>('get_release_manifest', {
-          releaseName,
-          namespace,
-        });
-        setCurrentManifest(manifest);
-      } catch {
-        setCurrentManifest('# No existing manifest found (new release?)');
-      }
-
-      // Run dry-run to get new manifest
-      const dryRunOutput = await invoke<string>('helm_dry_run', {
+      // Run dry-run and diff it against the deployed release
+      const preview = await invokeValidated<UpgradePreview>('preview_upgrade', {
         releaseName,
         chartPath,
-        namespace,
         valuesJson,
+        options: { namespace, version: chartVersion || undefined },
       });
 
-      setDryRunResult(dryRunOutput);
+      setCurrentManifest(preview.currentManifest || '# No existing manifest found (new release?)');
+      setDryRunResult(preview.manifest);
+      setPreviewSummary(preview.summary);
       setShowPreview(true);
     } catch (e) {
-      setError(`Dry run failed: ${e}`);
+      setError(`Dry run failed: ${formatError(e)}`);
     } finally {
       setDryRunning(false);
     }
@@ -248,19 +324,13 @@ function ReleaseEditor({ releaseName, namespace, chartPath, chartName, chartVers
       setError('');
       setSuccess('');
       
-      let valuesJson;
-      if (schema && Object.keys(schema).length === 0 && rawValues) {
-        // Use raw values when no schema is available
-        valuesJson = rawValues;
-      } else {
-        // Use form data
-        valuesJson = JSON.stringify(formData);
-      }
+      const valuesJson = userValuesJson();
       
-      await invoke<string>('helm_upgrade', {
+      await invokeValidated<string>('helm_upgrade', {
         releaseName,
         chartPath,
         valuesJson,
+        options: { namespace, version: chartVersion || undefined },
       });
       
       setSuccess(`Successfully upgraded release: ${releaseName}`);
@@ -269,7 +339,7 @@ function ReleaseEditor({ releaseName, namespace, chartPath, chartName, chartVers
         onClose();
       }, 2000);
     } catch (e) {
-      setError(`Upgrade failed: ${e}`);
+      setError(`Upgrade failed: ${formatError(e)}`);
     } finally {
       setUpgrading(false);
     }
@@ -670,6 +740,11 @@ function ReleaseEditor({ releaseName, namespace, chartPath, chartName, chartVers
                   <p className="mt-1 text-sm text-gray-500 dark:text-gray-400">
                     Review what will change when you upgrade. This is a dry run - no changes have been applied yet.
                   </p>
+                  {previewSummary && (
+                    <p className="mt-2 text-sm font-medium text-gray-900 dark:text-white">
+                      {previewSummary.changed} changed, {previewSummary.added} added, {previewSummary.removed} removed
+                    </p>
+                  )}
 
                   <div className="mt-6 max-h-[60vh] overflow-y-auto">
                     <DiffViewer
@@ -4,8 +4,10 @@ import { Input, Switch, Field, Label, Description } from '@headlessui/react';
 import { MinusCircleIcon, PlusCircleIcon } from '@heroicons/react/20/solid';
 import { ChangeEvent } from 'react';
 
+type SchemaType = 'string' | 'boolean' | 'integer' | 'number' | 'object' | 'array' | 'null';
+
 interface SchemaProperty {
-  type: 'string' | 'boolean' | 'integer' | 'number' | 'object' | 'array';
+  type: SchemaType | SchemaType[];
   description?: string;
   default?: any;
   properties?: Record<string, SchemaProperty>;
@@ -13,6 +15,12 @@ interface SchemaProperty {
   enum?: string[];
 }
 
+// Nullable unions such as ["string", "null"] are edited as their non-null type
+const fieldType = (prop?: SchemaProperty) => {
+  const type = prop?.type;
+  return Array.isArray(type) ? type.find((t) => t !== 'null') : type;
+};
+
 interface SchemaFormProps {
   schema: Record<string, SchemaProperty>;
   formData: any;
@@ -126,13 +134,13 @@ function RecursiveForm({ schema, formData, onChange, level = 0 }: SchemaFormProp
     <div className={`space-y-6 ${indentClass}`}>
       {Object.entries(schema).map(([key, prop]) => {
         const rawValue = formData[key] ?? prop.default;
-        const value = normalizeValue(rawValue, prop.type as string);
+        const value = normalizeValue(rawValue, fieldType(prop) as string);
 
         const handleChange = (newValue: any) => {
           onChange({ ...formData, [key]: newValue });
         };
 
-        switch (prop.type) {
+        switch (fieldType(prop)) {
           case 'string':
             if (prop.enum) {
               return (
@@ -202,7 +210,7 @@ function RecursiveForm({ schema, formData, onChange, level = 0 }: SchemaFormProp
                   className="mt-2 block w-full rounded-md border-0 px-3 py-1.5 text-gray-900 shadow-xs ring-1 ring-inset ring-gray-300 placeholder:text-gray-400 focus:ring-2 focus:ring-inset focus:ring-indigo-600 sm:text-sm/6 dark:bg-white/5 dark:text-white dark:ring-white/10 dark:focus:ring-indigo-500"
                   type="text"
                   value={formatValueForInput(value)}
-                  onChange={(e: ChangeEvent<HTMLInputElement>) => handleChange(parseInputValue(e.target.value, prop.type))}
+                  onChange={(e: ChangeEvent<HTMLInputElement>) => handleChange(parseInputValue(e.target.value, fieldType(prop)))}
                 />
               </Field>
             );
@@ -249,7 +257,7 @@ function RecursiveForm({ schema, formData, onChange, level = 0 }: SchemaFormProp
                       }
                     }
                     
-                    const itemValue = prop.items ? normalizeValue(itemRaw, prop.items.type as string) : itemRaw;
+                    const itemValue = prop.items ? normalizeValue(itemRaw, fieldType(prop.items) as string) : itemRaw;
                     const isItemObject = itemValue && typeof itemValue === 'object' && !Array.isArray(itemValue);
                     const hasItemSchema = !!(prop.items?.properties && Object.keys(prop.items.properties).length > 0);
                     
@@ -274,7 +282,7 @@ function RecursiveForm({ schema, formData, onChange, level = 0 }: SchemaFormProp
                               value={formatValueForInput(itemValue)}
                               onChange={(e: ChangeEvent<HTMLInputElement>) => {
                                 const newArray = [...normalizedArrayValue];
-                                newArray[index] = parseInputValue(e.target.value, prop.items?.type);
+                                newArray[index] = parseInputValue(e.target.value, fieldType(prop.items));
                                 handleChange(newArray);
                               }}
                             />
@@ -296,7 +304,7 @@ function RecursiveForm({ schema, formData, onChange, level = 0 }: SchemaFormProp
                   <button
                     type="button"
                     onClick={() => {
-                      const newItem = prop.items?.type === 'object' ? {} : '';
+                      const newItem = fieldType(prop.items) === 'object' ? {} : '';
                       handleChange([...normalizedArrayValue, newItem]);
                     }}
                     className="inline-flex items-center gap-x-1.5 rounded-md bg-indigo-600 px-2.5 py-1.5 text-sm font-semibold text-white shadow-xs hover:bg-indigo-500 dark:bg-indigo-500 dark:hover:bg-indigo-400"
@@ -311,7 +319,7 @@ function RecursiveForm({ schema, formData, onChange, level = 0 }: SchemaFormProp
           default:
             return (
               <div key={key} className="text-sm text-gray-500 dark:text-gray-400">
-                Unsupported type: {prop.type}
+                Unsupported type: {String(prop.type)}
               </div>
             );
         }
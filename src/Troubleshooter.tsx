@@ -13,6 +13,7 @@ import {
   ChevronRightIcon,
   WrenchScrewdriverIcon,
 } from '@heroicons/react/24/outline';
+import { formatError } from './errors';
 
 interface PodDiagnostic {
   name: string;
@@ -80,7 +81,7 @@ export default function Troubleshooter({ releaseName, namespace }: Troubleshoote
       setReleaseIssues([{
         severity: 'error',
         title: 'Diagnostics failed',
-        description: String(e),
+        description: formatError(e),
         solutions: [{
           title: 'Retry diagnostics',
           description: 'Try running diagnostics again',
@@ -130,7 +131,7 @@ export default function Troubleshooter({ releaseName, namespace }: Troubleshoote
       }
       setActionResult({ success: true, message: result });
     } catch (e) {
-      setActionResult({ success: false, message: String(e) });
+      setActionResult({ success: false, message: formatError(e) });
     } finally {
       setActionRunning(null);
     }
@@ -0,0 +1,50 @@
+// Typed errors returned by the Rust commands (see src-tauri/src/error.rs)
+
+export type ErrorKind =
+  | 'releaseNotFound'
+  | 'chartNotFound'
+  | 'clusterUnreachable'
+  | 'forbidden'
+  | 'unauthorized'
+  | 'binaryMissing'
+  | 'invalidValues'
+  | 'schemaNotFound'
+  | 'invalidInput'
+  | 'conflict'
+  | 'network'
+  | 'timeout'
+  | 'cancelled'
+  | 'database'
+  | 'io'
+  | 'commandFailed';
+
+// A value rejected by the chart's schema; `path` is a JSON pointer such as /image/tag
+export interface ValidationError {
+  path: string;
+  keyword: string;
+  message: string;
+}
+
+export interface RudderError {
+  kind: ErrorKind;
+  message: string;
+  stderr: string | null;
+  remediation: string | null;
+  validationErrors?: ValidationError[];
+}
+
+export function isRudderError(e: unknown): e is RudderError {
+  return typeof e === 'object' && e !== null && 'kind' in e && 'message' in e;
+}
+
+export function errorKind(e: unknown): ErrorKind | undefined {
+  return isRudderError(e) ? e.kind : undefined;
+}
+
+// Human readable message, including the suggested fix when the backend has one
+export function formatError(e: unknown): string {
+  if (isRudderError(e)) {
+    return e.remediation ? `${e.message}\n\n🔧 ${e.remediation}` : e.message;
+  }
+  return e instanceof Error ? e.message : String(e);
+}